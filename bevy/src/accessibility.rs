@@ -0,0 +1,263 @@
+//! The board for screen readers: every square as an accessible node, moves announced as they
+//! are played, and the whole position read out on request.
+
+use bevy::a11y::accesskit::{Action, Live, NodeBuilder, Role};
+use bevy::a11y::{AccessibilityNode, AccessibilityRequested, Focus};
+use bevy::prelude::*;
+
+use crate::analysis::{text_style, PanelColumn};
+use crate::board::Cursor;
+use crate::game::Game;
+use crate::keyboard::{setup_move_entry, typing};
+use crate::piece::{Kind, PieceColor};
+use crate::rules::{square_name, Move, Outcome, Position, Square};
+use crate::states::describe;
+
+pub struct ScreenReaderPlugin;
+
+impl Plugin for ScreenReaderPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<AccessibilitySettings>()
+            .init_resource::<Announcement>()
+            .add_systems(Startup, (setup_board_nodes, setup_announcements.after(setup_move_entry)))
+            .add_systems(Update, (follow_screen_reader, update_board_nodes, focus_cursor))
+            .add_systems(Update, announce_moves.after(follow_screen_reader))
+            .add_systems(Update, describe_position.run_if(not(typing)).after(announce_moves))
+            .add_systems(Update, show_announcement.after(describe_position));
+    }
+}
+
+/// Whether moves and positions are read out; on by `--accessible`, or as soon as a screen
+/// reader asks for the accessibility tree.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct AccessibilitySettings {
+    pub enabled: bool,
+}
+
+/// Latest thing said, shown in the panel and spoken through a live region.
+#[derive(Resource, Default)]
+struct Announcement(String);
+
+/// Holds the 64 square nodes, a8 first as a reader goes through a diagram.
+#[derive(Component)]
+struct BoardNode;
+
+#[derive(Component)]
+struct SquareNode(Square);
+
+#[derive(Component)]
+struct AnnouncementText;
+
+fn piece_name(color: PieceColor, kind: Kind) -> String {
+    format!("{} {}", color.name(), kind.name())
+}
+
+/// A move in words, e.g. "White knight f3 takes e5, check", as played from `pos`.
+pub fn describe_move(pos: &Position, mv: Move) -> String {
+    let Some((color, kind)) = pos.piece_at(mv.from) else { return mv.to_string(); };
+    let mover = format!("{color:?}");
+
+    let mut text = match pos.castling_rook(mv) {
+        Some(_) if mv.to.0 > mv.from.0 => format!("{mover} castles kingside"),
+        Some(_) => format!("{mover} castles queenside"),
+        None => {
+            let taken = pos.captured_square(mv);
+            let verb = if taken.is_some() { "takes" } else { "to" };
+            let mut text = format!("{mover} {} {} {verb} {}", kind.name(), square_name(mv.from), square_name(mv.to));
+
+            if taken.is_some_and(|sq| sq != mv.to) {
+                text.push_str(" en passant");
+            }
+            text
+        }
+    };
+
+    if let Some(promotion) = mv.promotion {
+        text.push_str(&format!(", promotes to {}", promotion.name()));
+    }
+
+    let mut after = pos.clone();
+    after.make_move(mv);
+
+    match after.outcome() {
+        Some(Outcome::Checkmate(_)) => text.push_str(", checkmate"),
+        _ if after.in_check() => text.push_str(", check"),
+        _ => {}
+    }
+
+    text
+}
+
+/// What stands on a square, as its node is named: "e4, white knight" or "e4, empty".
+pub fn describe_square(pos: &Position, sq: Square) -> String {
+    match pos.piece_at(sq) {
+        Some((color, kind)) => format!("{}, {}", square_name(sq), piece_name(color, kind)),
+        None => format!("{}, empty", square_name(sq)),
+    }
+}
+
+/// Every piece, rank by rank from the eighth, skipping empty ranks.
+pub fn describe_by_rank(pos: &Position) -> String {
+    let mut ranks = Vec::new();
+
+    for r in (0..8).rev() {
+        let pieces: Vec<String> = (0..8)
+            .filter_map(|f| pos.piece_at((f, r)).map(|(color, kind)| format!("{} {}", piece_name(color, kind), square_name((f, r)))))
+            .collect();
+
+        if !pieces.is_empty() {
+            ranks.push(format!("Rank {}: {}", r + 1, pieces.join(", ")));
+        }
+    }
+
+    format!("{}. {:?} to move.", ranks.join(". "), pos.turn)
+}
+
+/// Every piece, side by side and kind by kind from the king down.
+pub fn describe_by_piece(pos: &Position) -> String {
+    let kinds = [Kind::King, Kind::Queen, Kind::Rook, Kind::Bishop, Kind::Knight, Kind::Pawn];
+    let mut sides = Vec::new();
+
+    for color in [PieceColor::White, PieceColor::Black] {
+        let groups: Vec<String> = kinds
+            .iter()
+            .filter_map(|&kind| {
+                let squares: Vec<String> = pos.pieces().filter(|&(_, c, k)| c == color && k == kind).map(|(sq, _, _)| square_name(sq)).collect();
+
+                match squares.len() {
+                    0 => None,
+                    1 => Some(format!("{} {}", kind.name(), squares[0])),
+                    _ => Some(format!("{}s {}", kind.name(), squares.join(" "))),
+                }
+            })
+            .collect();
+
+        sides.push(format!("{color:?}: {}", groups.join(", ")));
+    }
+
+    format!("{}. {:?} to move.", sides.join(". "), pos.turn)
+}
+
+fn setup_board_nodes(mut commands: Commands) {
+    let mut board = NodeBuilder::new(Role::Grid);
+    board.set_name("Chess board");
+    board.set_row_count(8);
+    board.set_column_count(8);
+
+    commands.spawn((AccessibilityNode::from(board), BoardNode)).with_children(|board| {
+        for r in (0..8).rev() {
+            for f in 0..8 {
+                let mut cell = NodeBuilder::new(Role::Cell);
+                cell.set_row_index(7 - r as usize);
+                cell.set_column_index(f as usize);
+                cell.add_action(Action::Focus);
+
+                board.spawn((AccessibilityNode::from(cell), SquareNode((f, r))));
+            }
+        }
+    });
+}
+
+fn setup_announcements(mut commands: Commands, asset_server: Res<AssetServer>, column: Query<Entity, With<PanelColumn>>) {
+    let mut region = NodeBuilder::new(Role::Status);
+    region.set_live(Live::Polite);
+
+    let text = commands
+        .spawn((
+            TextBundle::from_section("", text_style(&asset_server, 14.)).with_style(Style { display: Display::None, ..default() }),
+            AccessibilityNode::from(region),
+            AnnouncementText,
+        ))
+        .id();
+
+    commands.entity(column.single()).add_child(text);
+}
+
+fn follow_screen_reader(requested: Res<AccessibilityRequested>, mut settings: ResMut<AccessibilitySettings>) {
+    if requested.get() && !settings.enabled {
+        info!("screen reader detected, reading moves out");
+        settings.enabled = true;
+    }
+}
+
+/// Names every square after what stands on it.
+fn update_board_nodes(game: Res<Game>, mut squares: Query<(&SquareNode, &mut AccessibilityNode)>) {
+    if !game.is_changed() {
+        return;
+    }
+
+    for (SquareNode(sq), mut node) in &mut squares {
+        node.set_name(describe_square(&game.position, *sq));
+    }
+}
+
+/// The square under the keyboard cursor takes the accessibility focus, so it is read out.
+fn focus_cursor(cursor: Res<Cursor>, mut focus: ResMut<Focus>, squares: Query<(Entity, &SquareNode)>) {
+    if !cursor.is_changed() {
+        return;
+    }
+
+    if let Some(sq) = cursor.0 {
+        focus.0 = squares.iter().find(|(_, node)| node.0 == sq).map(|(entity, _)| entity);
+    }
+}
+
+fn announce_moves(game: Res<Game>, settings: Res<AccessibilitySettings>, mut announcement: ResMut<Announcement>, mut plies: Local<usize>) {
+    if !game.is_changed() {
+        return;
+    }
+
+    let len = game.moves.len();
+    let played = len == *plies + 1;
+    *plies = len;
+
+    if !settings.enabled {
+        return;
+    }
+
+    let mut text = match game.moves.last() {
+        Some(&mv) if played => describe_move(&game.position_at(len - 1), mv),
+        _ => String::new(),
+    };
+
+    if let Some(outcome) = game.outcome() {
+        if !text.is_empty() {
+            text.push_str(". ");
+        }
+        text.push_str(&describe(outcome));
+    }
+
+    if !text.is_empty() {
+        announcement.0 = text;
+    }
+}
+
+/// F5 reads the position out rank by rank, F6 piece by piece.
+fn describe_position(keys: Res<ButtonInput<KeyCode>>, game: Res<Game>, settings: Res<AccessibilitySettings>, mut announcement: ResMut<Announcement>) {
+    if !settings.enabled {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::F5) {
+        announcement.0 = describe_by_rank(&game.position);
+    } else if keys.just_pressed(KeyCode::F6) {
+        announcement.0 = describe_by_piece(&game.position);
+    }
+}
+
+fn show_announcement(
+    settings: Res<AccessibilitySettings>,
+    announcement: Res<Announcement>,
+    mut texts: Query<(&mut Text, &mut Style, &mut AccessibilityNode), With<AnnouncementText>>,
+) {
+    if !(settings.is_changed() || announcement.is_changed()) {
+        return;
+    }
+
+    for (mut text, mut style, mut node) in &mut texts {
+        style.display = if settings.enabled { Display::Flex } else { Display::None };
+        text.sections[0].value.clone_from(&announcement.0);
+        node.set_name(announcement.0.clone());
+    }
+}
@@ -0,0 +1,485 @@
+use bevy::prelude::*;
+use bevy::utils::synccell::SyncCell;
+
+use crate::board::{BoardView, Preview};
+use crate::consts::{PANEL_COLOR, PANEL_WIDTH, TEXT_COLOR};
+use crate::engine::{external_analysis, EngineAnalysis, EngineProcess, EngineSettings};
+use crate::game::{Game, Player, Players};
+use crate::piece::{Piece, PieceColor};
+use crate::rules::Position;
+use crate::san;
+use crate::search::{Limits, SearchThread};
+use crate::keyboard::typing;
+use crate::states::in_game;
+use crate::theme::Appearance;
+use crate::uci::{Info, Score};
+
+pub const EVAL_BAR_WIDTH: f32 = 24.;
+const MAX_PV_PLIES: usize = 10;
+pub const MOVE_COLOR: Color = Color::srgb(0.2, 0.2, 0.2);
+pub const MOVE_HOVER_COLOR: Color = Color::srgb(0.35, 0.35, 0.35);
+
+pub struct AnalysisPlugin;
+
+impl Plugin for AnalysisPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<AnalysisSettings>()
+            .init_resource::<BuiltinSearch>()
+            .add_systems(Startup, setup_panel)
+            .add_systems(Update, toggle_analysis.run_if(in_game).run_if(not(typing)))
+            .add_systems(Update, run_builtin_search.after(toggle_analysis))
+            .add_systems(Update, update_panel.after(run_builtin_search))
+            .add_systems(Update, click_pv_move)
+            .add_systems(Update, clear_preview.after(click_pv_move))
+            .add_systems(Update, show_preview.after(clear_preview));
+    }
+}
+
+#[derive(Resource)]
+pub struct AnalysisSettings {
+    pub enabled: bool,
+    pub multipv: usize,
+}
+
+#[derive(Resource, Default)]
+struct BuiltinSearch {
+    thread: Option<SyncCell<SearchThread>>,
+    position: Option<Position>,
+}
+
+/// Column right of the eval bar that other panels add their widgets to.
+#[derive(Component)]
+pub struct PanelColumn;
+
+/// Row of buttons at the top of the panel column.
+#[derive(Component)]
+pub struct PanelToolbar;
+
+#[derive(Component)]
+struct EvalFill;
+
+#[derive(Component)]
+struct AnalysisHeader;
+
+#[derive(Component)]
+struct PvLines;
+
+/// One principal variation. Its entities are kept while the lines come in and filled in place,
+/// so a click on a move is not lost to a button rebuilt under it.
+#[derive(Component)]
+struct PvRow {
+    line: usize,
+    score: Entity,
+    /// Move number, button and button label for each ply, the unused ones hidden.
+    plies: Vec<(Entity, Entity, Entity)>,
+}
+
+/// Position reached after playing the principal variation up to this move.
+#[derive(Component)]
+struct PvMove(Position);
+
+#[derive(Component)]
+struct PreviewPiece;
+
+impl Default for AnalysisSettings {
+    fn default() -> Self {
+        Self { enabled: false, multipv: 3 }
+    }
+}
+
+/// Engines report scores for the side to move; the panel always shows White's view.
+pub fn white_score(turn: PieceColor, score: Score) -> Score {
+    match (turn, score) {
+        (PieceColor::White, _) => score,
+        (PieceColor::Black, Score::Cp(cp)) => Score::Cp(-cp),
+        (PieceColor::Black, Score::Mate(n)) => Score::Mate(-n),
+    }
+}
+
+/// Share of the eval bar filled white, the expected score for White.
+pub fn white_share(score: Score) -> f32 {
+    match score {
+        Score::Cp(cp) => 1. / (1. + (-0.00368208 * cp as f32).exp()),
+        Score::Mate(n) if n > 0 => 1.,
+        Score::Mate(_) => 0.,
+    }
+}
+
+pub fn score_text(score: Score) -> String {
+    match score {
+        Score::Cp(cp) => format!("{:+.2}", cp as f32 / 100.),
+        Score::Mate(n) if n > 0 => format!("#{n}"),
+        Score::Mate(n) => format!("-#{}", -n),
+    }
+}
+
+fn analysis_allowed(players: &Players) -> bool {
+    players.white == Player::Human && players.black == Player::Human
+}
+
+pub fn text_style(asset_server: &AssetServer, font_size: f32) -> TextStyle {
+    TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size,
+        color: TEXT_COLOR,
+    }
+}
+
+pub fn setup_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(0.),
+                top: Val::Px(0.),
+                width: Val::Px(PANEL_WIDTH),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Row,
+                ..default()
+            },
+            background_color: PANEL_COLOR.into(),
+            ..default()
+        })
+        .with_children(|panel| {
+            panel
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(EVAL_BAR_WIDTH),
+                        height: Val::Percent(100.),
+                        flex_direction: FlexDirection::Column,
+                        justify_content: JustifyContent::FlexEnd,
+                        ..default()
+                    },
+                    background_color: Color::BLACK.into(),
+                    ..default()
+                })
+                .with_children(|bar| {
+                    bar.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(100.),
+                                height: Val::Percent(50.),
+                                ..default()
+                            },
+                            background_color: Color::WHITE.into(),
+                            ..default()
+                        },
+                        EvalFill,
+                    ));
+                });
+
+            panel
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            flex_grow: 1.,
+                            padding: UiRect::all(Val::Px(8.)),
+                            row_gap: Val::Px(10.),
+                            overflow: Overflow::clip(),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    PanelColumn,
+                ))
+                .with_children(|column| {
+                    column.spawn((
+                        NodeBundle {
+                            style: Style { flex_wrap: FlexWrap::Wrap, column_gap: Val::Px(6.), row_gap: Val::Px(6.), ..default() },
+                            ..default()
+                        },
+                        PanelToolbar,
+                    ));
+                    column.spawn((TextBundle::from_section("", text_style(&asset_server, 20.)), AnalysisHeader));
+                    column.spawn((
+                        NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Column,
+                                row_gap: Val::Px(8.),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        PvLines,
+                    ));
+                });
+        });
+}
+
+fn toggle_analysis(keys: Res<ButtonInput<KeyCode>>, mut settings: ResMut<AnalysisSettings>) {
+    if keys.just_pressed(KeyCode::KeyA) {
+        settings.enabled = !settings.enabled;
+    }
+}
+
+fn run_builtin_search(
+    settings: Res<AnalysisSettings>,
+    players: Res<Players>,
+    game: Res<Game>,
+    external: Option<Res<EngineProcess>>,
+    engine_settings: Option<Res<EngineSettings>>,
+    mut search: ResMut<BuiltinSearch>,
+    mut analysis: ResMut<EngineAnalysis>,
+) {
+    let wanted = settings.enabled
+        && analysis_allowed(&players)
+        && !external_analysis(engine_settings, external)
+        && game.outcome().is_none();
+
+    if !wanted {
+        if search.thread.is_some() {
+            *search = BuiltinSearch::default();
+        }
+        return;
+    }
+
+    if search.position.as_ref() != Some(&game.position) {
+        // Dropping the old thread stops it before the new one starts.
+        search.thread = None;
+        analysis.reset(game.position.clone());
+
+        let thread = SearchThread::spawn(game.position.clone(), game.keys().to_vec(), Limits::default(), settings.multipv);
+        search.thread = Some(SyncCell::new(thread));
+        search.position = Some(game.position.clone());
+    }
+
+    let Some(thread) = search.thread.as_mut() else { return; };
+
+    for info in thread.get().poll() {
+        analysis.update(info);
+    }
+}
+
+fn update_panel(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<AnalysisSettings>,
+    players: Res<Players>,
+    analysis: Res<EngineAnalysis>,
+    fill: Query<Entity, With<EvalFill>>,
+    header: Query<Entity, With<AnalysisHeader>>,
+    lines: Query<Entity, With<PvLines>>,
+    rows: Query<&PvRow>,
+    mut texts: Query<&mut Text>,
+    mut styles: Query<&mut Style>,
+    mut pv_moves: Query<&mut PvMove>,
+    mut shown: Local<Option<(usize, Position)>>,
+) {
+    if !(settings.is_changed() || players.is_changed() || analysis.is_changed()) {
+        return;
+    }
+
+    let visible = settings.enabled && analysis_allowed(&players);
+    let best = analysis.lines.first().filter(|_| visible);
+    let turn = analysis.position.as_ref().map_or(PieceColor::White, |p| p.turn);
+    let score = best.and_then(|info| info.score).map(|s| white_score(turn, s));
+
+    if let Ok(mut style) = styles.get_mut(fill.single()) {
+        style.height = Val::Percent(100. * score.map_or(0.5, white_share));
+    }
+
+    let status = if !analysis_allowed(&players) {
+        String::from("Analysis is not available in this game")
+    } else if !settings.enabled {
+        String::from("Press A to analyse")
+    } else {
+        match (score, best.and_then(|info| info.depth)) {
+            (Some(score), Some(depth)) => format!("{}   depth {depth}", score_text(score)),
+            _ => String::from("Thinking..."),
+        }
+    };
+    set_text(&mut texts, header.single(), status);
+
+    let container = lines.single();
+    let (true, Some(position)) = (visible, analysis.position.as_ref()) else {
+        if shown.take().is_some() {
+            commands.entity(container).despawn_descendants();
+        }
+        return;
+    };
+
+    // New rows only for another position or another number of lines; otherwise the text changes.
+    let layout = (analysis.lines.len(), position.clone());
+    if shown.as_ref() != Some(&layout) {
+        commands.entity(container).despawn_descendants();
+        commands.entity(container).with_children(|parent| {
+            for (line, info) in analysis.lines.iter().enumerate() {
+                spawn_line(parent, &asset_server, line, position, info);
+            }
+        });
+        *shown = Some(layout);
+        return;
+    }
+
+    for row in &rows {
+        let Some(info) = analysis.lines.get(row.line) else { continue; };
+        let plies = pv_plies(position, info);
+        set_text(&mut texts, row.score, pv_score(position, info));
+
+        for (i, &(number, button, label)) in row.plies.iter().enumerate() {
+            let ply = plies.get(i);
+            let move_number = ply.and_then(|(number, _, _)| number.clone());
+
+            set_display(&mut styles, number, move_number.is_some());
+            set_display(&mut styles, button, ply.is_some());
+            set_text(&mut texts, number, move_number.unwrap_or_default());
+
+            if let Some((_, san, after)) = ply {
+                set_text(&mut texts, label, san.clone());
+
+                if let Ok(mut pv_move) = pv_moves.get_mut(button) {
+                    if pv_move.0 != *after {
+                        pv_move.0 = after.clone();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Changes the text only if it differs, so the layout is left alone otherwise.
+fn set_text(texts: &mut Query<&mut Text>, entity: Entity, value: String) {
+    if let Ok(mut text) = texts.get_mut(entity) {
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+fn set_display(styles: &mut Query<&mut Style>, entity: Entity, shown: bool) {
+    let display = if shown { Display::Flex } else { Display::None };
+
+    if let Ok(mut style) = styles.get_mut(entity) {
+        if style.display != display {
+            style.display = display;
+        }
+    }
+}
+
+fn pv_score(position: &Position, info: &Info) -> String {
+    info.score.map_or(String::from("?"), |s| score_text(white_score(position.turn, s)))
+}
+
+/// Each ply of the variation as shown: the move number if one goes before it, the move, and the
+/// position after it.
+fn pv_plies(position: &Position, info: &Info) -> Vec<(Option<String>, String, Position)> {
+    let moves = &info.pv[..info.pv.len().min(MAX_PV_PLIES)];
+    let mut pos = position.clone();
+
+    moves
+        .iter()
+        .zip(san::line(position, moves))
+        .enumerate()
+        .map(|(i, (mv, san))| {
+            let number = (i == 0 || pos.turn == PieceColor::White).then(|| san::move_number(&pos));
+            pos.make_move(*mv);
+            (number, san, pos.clone())
+        })
+        .collect()
+}
+
+/// A row with room for `MAX_PV_PLIES` moves, filled with `info` for a start.
+fn spawn_line(parent: &mut ChildBuilder, asset_server: &AssetServer, line: usize, position: &Position, info: &Info) {
+    let plies = pv_plies(position, info);
+    let display = |shown: bool| if shown { Display::Flex } else { Display::None };
+
+    let mut row = parent.spawn(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            flex_wrap: FlexWrap::Wrap,
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(4.),
+            row_gap: Val::Px(2.),
+            ..default()
+        },
+        ..default()
+    });
+
+    let mut score = Entity::PLACEHOLDER;
+    let mut slots = Vec::with_capacity(MAX_PV_PLIES);
+
+    row.with_children(|row| {
+        score = row.spawn(TextBundle::from_section(pv_score(position, info), text_style(asset_server, 16.))).id();
+
+        for i in 0..MAX_PV_PLIES {
+            let (number, san, after) = plies.get(i).cloned().unwrap_or_else(|| (None, String::new(), position.clone()));
+            let shown = i < plies.len();
+
+            let number_text = row
+                .spawn(TextBundle::from_section(number.clone().unwrap_or_default(), text_style(asset_server, 14.)).with_style(Style { display: display(number.is_some()), ..default() }))
+                .id();
+
+            let mut label = Entity::PLACEHOLDER;
+            let button = row
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            display: display(shown),
+                            padding: UiRect::horizontal(Val::Px(3.)),
+                            ..default()
+                        },
+                        background_color: MOVE_COLOR.into(),
+                        ..default()
+                    },
+                    PvMove(after),
+                ))
+                .with_children(|button| {
+                    label = button.spawn(TextBundle::from_section(san, text_style(asset_server, 14.))).id();
+                })
+                .id();
+
+            slots.push((number_text, button, label));
+        }
+    });
+
+    row.insert(PvRow { line, score, plies: slots });
+}
+
+fn click_pv_move(
+    mut preview: ResMut<Preview>,
+    mut interactions: Query<(&Interaction, &PvMove, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    for (interaction, pv_move, mut color) in &mut interactions {
+        match *interaction {
+            Interaction::Pressed => preview.0 = Some(pv_move.0.clone()),
+            Interaction::Hovered => *color = MOVE_HOVER_COLOR.into(),
+            Interaction::None => *color = MOVE_COLOR.into(),
+        }
+    }
+}
+
+fn clear_preview(game: Res<Game>, keys: Res<ButtonInput<KeyCode>>, mut preview: ResMut<Preview>) {
+    if preview.0.is_some() && (game.is_changed() || keys.just_pressed(KeyCode::Escape)) {
+        preview.0 = None;
+    }
+}
+
+fn show_preview(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    preview: Res<Preview>,
+    view: Res<BoardView>,
+    appearance: Res<Appearance>,
+    previews: Query<Entity, With<PreviewPiece>>,
+    mut pieces: Query<&mut Visibility, With<Piece>>,
+) {
+    if !(preview.is_changed() || view.is_changed() || appearance.is_changed()) {
+        return;
+    }
+
+    for entity in &previews {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for mut visibility in &mut pieces {
+        *visibility = if preview.0.is_some() { Visibility::Hidden } else { Visibility::Inherited };
+    }
+
+    let Some(position) = &preview.0 else { return; };
+
+    for (loc, team, kind) in position.pieces() {
+        commands.spawn((Piece { kind, team, loc }.sprite_bundle(&asset_server, &view, appearance.pieces()), PreviewPiece));
+    }
+}
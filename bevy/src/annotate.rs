@@ -0,0 +1,259 @@
+//! Post-game review: an evaluation of every position, the moves that lost the most
+//! by it, and the game written out as annotated PGN.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::analysis::{white_score, white_share};
+use crate::game::Game;
+use crate::pgn::{Annotation, PgnGame};
+use crate::piece::PieceColor;
+use crate::rules::{Move, Position};
+use crate::san;
+use crate::search::{self, Limits};
+use crate::uci::Score;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Judgement {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+/// Engine verdict on one position, from the side to move's point of view.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PositionEval {
+    pub score: Score,
+    pub best: Option<Move>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoveReview {
+    pub played: Move,
+    /// Expected score for the player who moved, before and after the move.
+    pub before: f64,
+    pub after: f64,
+    pub judgement: Option<Judgement>,
+    pub accuracy: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameReview {
+    pub start: Position,
+    /// One more than there are moves: the final position is evaluated too.
+    pub evals: Vec<PositionEval>,
+    pub moves: Vec<MoveReview>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReviewProgress {
+    Evaluated(usize),
+    Finished(GameReview),
+}
+
+/// Review running on its own thread.
+pub struct ReviewThread {
+    stop: Arc<AtomicBool>,
+    reports: Receiver<ReviewProgress>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Judgement {
+    /// Drop in expected score, on a 0 to 1 scale, from which a move gets this label.
+    fn threshold(self) -> f64 {
+        match self {
+            Self::Inaccuracy => 0.05,
+            Self::Mistake => 0.10,
+            Self::Blunder => 0.15,
+        }
+    }
+
+    pub fn nag(self) -> u8 {
+        match self {
+            Self::Inaccuracy => 6,
+            Self::Mistake => 2,
+            Self::Blunder => 4,
+        }
+    }
+
+    pub fn glyph(self) -> &'static str {
+        match self {
+            Self::Inaccuracy => "?!",
+            Self::Mistake => "?",
+            Self::Blunder => "??",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Inaccuracy => "Inaccuracy",
+            Self::Mistake => "Mistake",
+            Self::Blunder => "Blunder",
+        }
+    }
+}
+
+/// Label for a move that took the mover's expected score from `before` to `after`.
+pub fn judge(before: f64, after: f64) -> Option<Judgement> {
+    [Judgement::Blunder, Judgement::Mistake, Judgement::Inaccuracy]
+        .into_iter()
+        .find(|j| before - after >= j.threshold())
+}
+
+/// Accuracy of a single move in percent, from the drop in winning chances.
+pub fn move_accuracy(before: f64, after: f64) -> f64 {
+    let drop = 100. * (before - after).max(0.);
+    (103.1668 * (-0.04354 * drop).exp() - 3.1669).clamp(0., 100.)
+}
+
+/// Evaluates the start and every position after it with the built-in search.
+/// `progress` hears how many positions are done; `None` if stopped first.
+pub fn evaluate(
+    start: &Position,
+    moves: &[Move],
+    limits: &Limits,
+    stop: &AtomicBool,
+    mut progress: impl FnMut(usize),
+) -> Option<Vec<PositionEval>> {
+    let mut game = Game::new(start.clone());
+    let mut evals = Vec::with_capacity(moves.len() + 1);
+
+    for i in 0..=moves.len() {
+        if i > 0 {
+            game.play(moves[i - 1]);
+        }
+
+        let keys = game.keys();
+        let mut score = None;
+        let best = search::search(&game.position, &keys[..keys.len() - 1], limits, 1, stop, |info| score = info.score);
+
+        if stop.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let score = match (best, game.outcome()) {
+            // Checkmated: the worst score there is for the side to move.
+            (None, _) if game.position.in_check() => Score::Mate(0),
+            (None, _) | (_, Some(_)) => Score::Cp(0),
+            (Some(_), None) => score.unwrap_or(Score::Cp(0)),
+        };
+
+        evals.push(PositionEval { score, best: best.filter(|_| game.outcome().is_none()) });
+        progress(i + 1);
+    }
+
+    Some(evals)
+}
+
+/// Judges every move by how much of the mover's expected score it gave away.
+pub fn review(start: &Position, moves: &[Move], evals: Vec<PositionEval>) -> GameReview {
+    let reviews = moves
+        .iter()
+        .zip(evals.windows(2))
+        .map(|(&played, pair)| {
+            let before = white_share(pair[0].score) as f64;
+            let after = 1. - white_share(pair[1].score) as f64;
+            let judgement = judge(before, after).filter(|_| pair[0].best != Some(played));
+
+            MoveReview { played, before, after, judgement, accuracy: move_accuracy(before, after) }
+        })
+        .collect();
+
+    GameReview { start: start.clone(), evals, moves: reviews }
+}
+
+impl GameReview {
+    /// Mean move accuracy for `color`, `None` if it made no moves.
+    pub fn accuracy(&self, color: PieceColor) -> Option<f64> {
+        let first = (self.start.turn != color) as usize;
+        let own: Vec<f64> = self.moves.iter().skip(first).step_by(2).map(|m| m.accuracy).collect();
+
+        (!own.is_empty()).then(|| own.iter().sum::<f64>() / own.len() as f64)
+    }
+
+    /// The game with a `[%eval]` after every move and NAGs on the bad ones,
+    /// naming the better move where there was one.
+    pub fn pgn(&self, result: &str) -> PgnGame {
+        let mut game = PgnGame::new(self.start.clone());
+        let mut pos = self.start.clone();
+
+        game.result = result.to_string();
+        game.set_tag("Annotator", "Chess-Clone");
+
+        for (review, pair) in self.moves.iter().zip(self.evals.windows(2)) {
+            let best = pair[0].best.filter(|_| review.judgement.is_some()).map(|mv| san::san(&pos, mv));
+
+            pos.make_move(review.played);
+
+            let mut comment = match pair[1].score {
+                Score::Mate(0) => String::new(),
+                score => format!("[%eval {}]", eval_text(white_score(pos.turn, score))),
+            };
+
+            if let (Some(judgement), Some(best)) = (review.judgement, best) {
+                if !comment.is_empty() {
+                    comment.push(' ');
+                }
+                comment.push_str(&format!("{}. {best} was best.", judgement.name()));
+            }
+
+            game.moves.push(review.played);
+            game.annotations.push(Annotation {
+                nags: review.judgement.map(Judgement::nag).into_iter().collect(),
+                comment: (!comment.is_empty()).then_some(comment),
+            });
+        }
+
+        for color in [PieceColor::White, PieceColor::Black] {
+            if let Some(accuracy) = self.accuracy(color) {
+                game.set_tag(&format!("{color:?}Accuracy"), format!("{accuracy:.1}"));
+            }
+        }
+
+        game
+    }
+}
+
+impl ReviewThread {
+    pub fn spawn(start: Position, moves: Vec<Move>, limits: Limits) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (tx, reports) = mpsc::channel();
+        let flag = stop.clone();
+
+        let handle = thread::spawn(move || {
+            let evals = evaluate(&start, &moves, &limits, &flag, |done| {
+                let _ = tx.send(ReviewProgress::Evaluated(done));
+            });
+
+            if let Some(evals) = evals {
+                let _ = tx.send(ReviewProgress::Finished(review(&start, &moves, evals)));
+            }
+        });
+
+        Self { stop, reports, handle: Some(handle) }
+    }
+
+    pub fn poll(&self) -> Vec<ReviewProgress> {
+        self.reports.try_iter().collect()
+    }
+}
+
+impl Drop for ReviewThread {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Score in the `[%eval]` form: pawns from White's side, or `#n`/`#-n` for mates.
+pub fn eval_text(score: Score) -> String {
+    match score {
+        Score::Cp(cp) => format!("{:.2}", cp as f64 / 100.),
+        Score::Mate(n) => format!("#{n}"),
+    }
+}
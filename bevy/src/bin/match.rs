@@ -0,0 +1,206 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chess::pgn;
+use chess::search::Limits;
+use chess::stats::{Sprt, SprtStatus, Tally};
+use chess::tournament::{self, Adjudication, EngineSpec, MatchConfig, ScoreRule, TimeControl};
+use chess::uci::EngineConfig;
+
+const USAGE: &str = "\
+Usage: match --engine <ENGINE> --engine <ENGINE> [OPTIONS]
+
+Engines:
+  --engine builtin [depth=N] [nodes=N] [name=NAME]
+  --engine <PATH> [name=NAME] [arg=ARG]... [option.NAME=VALUE]... [timeout=MS]
+
+Options:
+  --games <N>                  Games to play [default: 100]
+  --tc <[MOVES/]SECS[+INC]>    Time control [default: 10+0.1]
+  --book <FILE>                EPD or PGN openings, each played with both colours
+  --book-plies <N>             Moves taken from PGN openings [default: 8]
+  --concurrency <N>            Games played at once [default: available cores]
+  --pgnout <FILE>              Append finished games to FILE
+  --resign <CP> <MOVES>        Adjudicate once both engines agree on a lead of CP
+  --draw <CP> <MOVES> <AFTER>  Adjudicate a draw once both stay within CP after move AFTER
+  --max-moves <N>              Adjudicate a draw after N moves
+  --tb                         Adjudicate positions with three men or fewer
+  --sprt <ELO0> <ELO1> [ALPHA] [BETA]
+                               Stop once the SPRT decides [default: 0.05 0.05]";
+
+struct Args {
+    config: MatchConfig,
+    pgnout: Option<PathBuf>,
+}
+
+fn main() {
+    let args = match parse(std::env::args().skip(1).collect()) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}");
+            eprintln!("{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    let config = &args.config;
+    let names = [config.engines[0].name(), config.engines[1].name()];
+
+    let result = tournament::run(config, |record, tally| {
+        let game = &record.played.game;
+        println!(
+            "Finished game {} ({} vs {}): {} {{{}}}",
+            record.round + 1,
+            game.tag("White").unwrap_or("?"),
+            game.tag("Black").unwrap_or("?"),
+            game.result,
+            record.played.reason,
+        );
+        println!(
+            "Score of {} vs {}: {} - {} - {}  [{:.3}] {}",
+            names[0], names[1], tally.wins, tally.losses, tally.draws, tally.score(), tally.games(),
+        );
+
+        if let Some(path) = &args.pgnout {
+            let written = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{}", pgn::write(game)));
+
+            if let Err(err) = written {
+                eprintln!("{}: {err}", path.display());
+            }
+        }
+    });
+
+    match result {
+        Ok(tally) => report(config, &tally),
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn report(config: &MatchConfig, tally: &Tally) {
+    match tally.elo() {
+        Some((elo, margin)) => println!("Elo difference: {elo:.1} +/- {margin:.1}"),
+        None => println!("Elo difference: unknown"),
+    }
+
+    if let Some(sprt) = config.sprt {
+        let (lower, upper) = sprt.bounds();
+        let verdict = match sprt.status(tally) {
+            SprtStatus::Continue => "no decision",
+            SprtStatus::AcceptH0 => "H0 accepted",
+            SprtStatus::AcceptH1 => "H1 accepted",
+        };
+
+        println!(
+            "SPRT: llr {:.2} ({lower:.2}, {upper:.2}) [{}, {}] - {verdict}",
+            sprt.llr(tally), sprt.elo0, sprt.elo1,
+        );
+    }
+}
+
+fn parse(args: Vec<String>) -> Result<Args, String> {
+    let mut engines = Vec::new();
+    let mut games = 100;
+    let mut time_control = TimeControl { moves: None, base: Duration::from_secs(10), increment: Duration::from_millis(100) };
+    let mut book = None;
+    let mut book_plies = 8;
+    let mut concurrency = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut pgnout = None;
+    let mut adjudication = Adjudication::default();
+    let mut sprt = None;
+
+    let mut args = args.into_iter().peekable();
+
+    while let Some(arg) = args.next() {
+        // Values for the flag, up to the next flag.
+        let mut values = Vec::new();
+        while let Some(value) = args.next_if(|a| !a.starts_with("--")) {
+            values.push(value);
+        }
+
+        let number = |i: usize| -> Result<f64, String> {
+            let value = values.get(i).ok_or_else(|| format!("{arg} needs more values"))?;
+            value.parse().map_err(|_| format!("{arg}: {value} is not a number"))
+        };
+
+        match arg.as_str() {
+            "--engine" => engines.push(engine(&values)?),
+            "--games" => games = number(0)? as usize,
+            "--tc" => {
+                let value = values.first().ok_or("--tc needs a value")?;
+                time_control = value.parse().map_err(|e| format!("{e}"))?;
+            }
+            "--book" => book = Some(PathBuf::from(values.first().ok_or("--book needs a file")?)),
+            "--book-plies" => book_plies = number(0)? as usize,
+            "--concurrency" => concurrency = (number(0)? as usize).max(1),
+            "--pgnout" => pgnout = Some(PathBuf::from(values.first().ok_or("--pgnout needs a file")?)),
+            "--resign" => adjudication.resign = Some(ScoreRule { cp: number(0)? as i32, moves: number(1)? as u32 }),
+            "--draw" => {
+                adjudication.draw = Some(ScoreRule { cp: number(0)? as i32, moves: number(1)? as u32 });
+                adjudication.draw_after = number(2)? as u32;
+            }
+            "--max-moves" => adjudication.max_moves = Some(number(0)? as u32),
+            "--tb" => adjudication.tablebase = true,
+            "--sprt" => {
+                sprt = Some(Sprt {
+                    elo0: number(0)?,
+                    elo1: number(1)?,
+                    alpha: if values.len() > 2 { number(2)? } else { 0.05 },
+                    beta: if values.len() > 3 { number(3)? } else { 0.05 },
+                });
+            }
+            "--help" | "-h" => return Err(String::from("Engine-vs-engine matches")),
+            other => return Err(format!("unknown argument {other}")),
+        }
+    }
+
+    let engines: [EngineSpec; 2] = engines.try_into().map_err(|_| String::from("exactly two engines are needed"))?;
+    let openings = match book {
+        Some(path) => tournament::load_openings(&path, book_plies)?,
+        None => Vec::new(),
+    };
+
+    Ok(Args {
+        config: MatchConfig { engines, games, time_control, openings, adjudication, sprt, concurrency },
+        pgnout,
+    })
+}
+
+fn engine(values: &[String]) -> Result<EngineSpec, String> {
+    let (command, settings) = values.split_first().ok_or("--engine needs builtin or a path")?;
+    let mut name = None;
+    let mut limits = Limits::default();
+    let mut config = EngineConfig::new(command);
+
+    for setting in settings {
+        let (key, value) = setting.split_once('=').ok_or_else(|| format!("expected key=value, got {setting}"))?;
+        let number = || value.parse::<u64>().map_err(|_| format!("{key}: {value} is not a number"));
+
+        match key {
+            "name" => name = Some(value.to_string()),
+            "depth" => limits.depth = Some(number()? as u32),
+            "nodes" => limits.nodes = Some(number()?),
+            "arg" => config.args.push(value.to_string()),
+            "timeout" => config.timeout = Duration::from_millis(number()?),
+            _ => match key.strip_prefix("option.") {
+                Some(option) => config.options.push((option.to_string(), value.to_string())),
+                None => return Err(format!("unknown engine setting {key}")),
+            },
+        }
+    }
+
+    if command == "builtin" {
+        Ok(EngineSpec::Builtin { name: name.unwrap_or_else(|| String::from("builtin")), limits })
+    } else {
+        let name = name.unwrap_or_else(|| config.path.file_stem().map_or(command.clone(), |s| s.to_string_lossy().into_owned()));
+        Ok(EngineSpec::Uci { name, config })
+    }
+}
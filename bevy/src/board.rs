@@ -1,8 +1,20 @@
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
+use bevy::sprite::{Anchor, MaterialMesh2dBundle, Mesh2dHandle};
+use bevy::ui::FocusPolicy;
+use bevy::window::{PrimaryWindow, WindowResized};
 
-use crate::consts::{COLORS, SQUARE_SIZE, OFFSET};
-use crate::piece::{TakePieceEvent, MovePieceEvent, Piece};
+use crate::analysis::{setup_panel, text_style, PanelToolbar, MOVE_COLOR, MOVE_HOVER_COLOR};
+use crate::consts::{GHOST_COLOR, MIN_SQUARE_SIZE, PANEL_COLOR, PANEL_WIDTH, SQUARE_SIZE};
+use crate::game::{Game, HistoryEvent, PlayMoveEvent, Player, Players};
+use crate::keyboard::{typing, MoveEntry};
+use crate::piece::{piece_scale, pieces_moving, spawn_pieces, Kind, PieceColor, TakePieceEvent, MovePieceEvent, PromotePieceEvent, Piece, Tween};
+use crate::premove::Premoves;
+use crate::rules::{Move, Position, Square};
+use crate::setup::NewGameEvent;
+use crate::states::{in_game, AppState};
+use crate::theme::{light_square, Appearance, Theme};
+use crate::tray::material;
 
 pub struct BoardPlugin;
 
@@ -10,20 +22,156 @@ impl Plugin for BoardPlugin {
     fn build(&self, app: &mut App) {
         app
             .insert_resource(Selected(None))
+            .init_resource::<Drag>()
+            .init_resource::<Cursor>()
+            .init_resource::<Preview>()
+            .init_resource::<BoardView>()
+            .init_resource::<BoardSettings>()
+            .init_resource::<Promotion>()
             .insert_resource(Board { entities: [None; 8 * 8] })
-            .add_systems(Startup, create_board)
-            .add_systems(Update, user_input);
+            .add_systems(Startup, (create_board, create_hint_shapes))
+            .add_systems(Startup, setup_board_buttons.after(setup_panel))
+            .add_systems(Update, user_input.run_if(not(promoting)).run_if(in_state(AppState::Playing)))
+            .add_systems(Update, key_cursor.run_if(not(pieces_moving)).run_if(not(promoting)).run_if(not(typing)).run_if(in_state(AppState::Playing)))
+            .add_systems(Update, choose_promotion.run_if(in_state(AppState::Playing)).after(user_input).after(key_cursor))
+            .add_systems(Update, play_move.after(choose_promotion))
+            .add_systems(Update, (orient_board, board_buttons.run_if(in_game), fit_board))
+            .add_systems(Update, take_back.after(board_buttons).before(play_move))
+            .add_systems(Update, apply_view.after(orient_board).after(board_buttons).after(fit_board))
+            .add_systems(Update, restyle_board)
+            .add_systems(Update, highlight_squares.after(play_move).after(apply_view))
+            .add_systems(Update, toggle_coordinates.run_if(in_game).run_if(not(typing)))
+            .add_systems(Update, label_coordinates.after(toggle_coordinates).after(apply_view));
     }
 }
 
+/// The ghost sits just under the pieces, a dragged piece above all of them.
+const GHOST_Z: f32 = 0.9;
+const DRAG_Z: f32 = 10.;
+
+/// Coordinate labels as shares of a square: their height and their distance from its edges.
+const COORDINATE_SIZE: f32 = 0.22;
+const COORDINATE_MARGIN: f32 = 0.05;
+
+const PROMOTION_ICON_SIZE: f32 = 48.;
+
 #[derive(Resource)]
 struct Selected(Option<Entity>);
 
+/// Square the keyboard cursor is on, once the arrow keys have brought it up.
+#[derive(Resource, Default)]
+pub struct Cursor(pub Option<Square>);
+
+/// Position shown instead of the game, e.g. a line from the analysis panel.
+/// Clicking the board goes back to the game.
+#[derive(Resource, Default)]
+pub struct Preview(pub Option<Position>);
+
+/// How the board is laid out on screen; everything placed on or read off the board goes through it.
+/// The board is centred on the world origin, the camera keeps it left of the panel.
+#[derive(Resource)]
+pub struct BoardView {
+    /// Black at the bottom.
+    pub flipped: bool,
+    /// Follows the window size.
+    pub square_size: f32,
+}
+
+/// What is drawn on the board besides squares and pieces, and how moves are made on it.
+#[derive(Resource, Clone, Debug)]
+pub struct BoardSettings {
+    /// File letters along the bottom rank and rank numbers along the left file.
+    pub coordinates: bool,
+    /// Promotions are made queens; otherwise the piece is picked each time.
+    pub auto_queen: bool,
+    /// Moves may be queued while the opponent is to move.
+    pub premoves: bool,
+}
+
+/// Pawn move waiting for the piece it promotes to be picked.
+#[derive(Resource, Default)]
+pub struct Promotion(pub Option<Move>);
+
+#[derive(Component)]
+struct PromotionCard;
+
+/// Where moves made on the board go.
+#[derive(SystemParam)]
+struct MoveInput<'w> {
+    settings: Res<'w, BoardSettings>,
+    promotion: ResMut<'w, Promotion>,
+    premoves: ResMut<'w, Premoves>,
+    play_move_wr: EventWriter<'w, PlayMoveEvent>,
+}
+
+#[derive(Component, Copy, Clone)]
+struct PromotionChoice(Kind);
+
+/// File or rank name in the corner of an edge square.
+#[derive(Component)]
+struct CoordinateLabel;
+
+/// One of the 64 board squares.
+#[derive(Component)]
+struct Tile(Square);
+
+#[derive(Component, Copy, Clone, Eq, PartialEq)]
+enum BoardButton {
+    Flip,
+    Undo,
+    Redo,
+    Resign,
+    Draw,
+}
+
+/// Piece held with the mouse button down, following the cursor until it is dropped.
+#[derive(Resource, Default)]
+struct Drag(Option<Entity>);
+
+/// Faded copy of a dragged piece left on the square it came from.
+#[derive(Component)]
+struct Ghost;
+
+/// Overlay drawn for the selection and the last move, rebuilt whenever either changes.
+#[derive(Component)]
+struct Highlight;
+
+/// Shared meshes and materials for the move hints.
+#[derive(Resource)]
+struct HintShapes {
+    dot: Mesh2dHandle,
+    ring: Mesh2dHandle,
+    color: Handle<ColorMaterial>,
+}
+
 #[derive(Resource)]
 pub struct Board {
     entities: [Option<Entity>; 8 * 8],
 }
 
+impl MoveInput<'_> {
+    /// Plays the move, or holds a promotion back until its piece is picked. True if it went ahead.
+    fn submit(&mut self, mv: Move) -> bool {
+        if mv.promotion.is_some() && !self.settings.auto_queen {
+            self.promotion.0 = Some(mv);
+            return false;
+        }
+
+        self.play_move_wr.send(PlayMoveEvent(mv));
+        true
+    }
+
+    /// Moves `side`'s piece from `from` to `to`: queued while the opponent is to move, played
+    /// otherwise. `None` if there is no such move, `Some(false)` if the piece stays for now.
+    fn make(&mut self, pos: &Position, side: PieceColor, from: Square, to: Square) -> Option<bool> {
+        if side != pos.turn {
+            return self.premoves.queue(pos, side, from, to).then_some(false);
+        }
+
+        legal_move(pos, from, to).map(|mv| self.submit(mv))
+    }
+}
+
 impl Board {
     pub fn add_entity(&mut self, r: usize, c: usize, piece: Entity) {
         self.entities[8 * r + c] = Some(piece);
@@ -37,70 +185,736 @@ impl Board {
         self.entities[8 * dst.1 + dst.0] = self.entities[8 * src.1 + src.0];
         self.entities[8 * src.1 + src.0] = None;
     }
+
+    pub fn remove_entity(&mut self, r: usize, c: usize) -> Option<Entity> {
+        self.entities[8 * r + c].take()
+    }
 }
 
 fn user_input(
-    mut board: ResMut<Board>,
-    mut selected: ResMut<Selected>, 
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    appearance: Res<Appearance>,
+    game: Res<Game>,
+    players: Res<Players>,
+    board: Res<Board>,
+    mut selected: ResMut<Selected>,
+    mut preview: ResMut<Preview>,
+    mut drag: ResMut<Drag>,
     mouse: Res<ButtonInput<MouseButton>>,
-    pieces: Query<(Entity, &Piece)>,
-    windows: Query<&Window, With<PrimaryWindow>>, 
-    mut take_piece_wr: EventWriter<TakePieceEvent>,
-    mut move_piece_wr: EventWriter<MovePieceEvent>,
+    mut pieces: Query<(&Piece, &mut Transform, Has<Tween>)>,
+    ghosts: Query<Entity, With<Ghost>>,
+    view: Res<BoardView>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut moves: MoveInput,
 ) {
-    if !mouse.just_pressed(MouseButton::Left) {
+    // The side moved from this screen; while its opponent is to move, its moves are premoves.
+    let Some(side) = local_side(&game, &players) else { return; };
+    if side != game.position.turn && !moves.settings.premoves {
         return;
     }
 
-    let Some(Vec2 { x, y }) = windows.single().cursor_position() else { return; };
-    let (r, c) = ((7 - (y / SQUARE_SIZE) as usize), ((x / SQUARE_SIZE) as usize));
+    let (camera, camera_transform) = cameras.single();
+    let world = windows.single().cursor_position().and_then(|c| camera.viewport_to_world_2d(camera_transform, c));
+    let square = world.and_then(|w| view.square_at(w));
+
+    if let Some(entity) = drag.0 {
+        if mouse.pressed(MouseButton::Left) {
+            if let (Some(world), Ok((_, mut transform, _))) = (world, pieces.get_mut(entity)) {
+                transform.translation = world.extend(DRAG_Z);
+            }
+            return;
+        }
+
+        drag.0 = None;
+        for ghost in &ghosts {
+            commands.entity(ghost).despawn_recursive();
+        }
 
-    if selected.0.is_none() {
-        selected.0 = board.get_entity(r, c);
+        let Ok((piece, mut transform, _)) = pieces.get_mut(entity) else { return; };
+
+        // A legal drop slides the piece on from where it was let go. A premove, or a piece
+        // waiting for its promotion to be picked, goes back until the move is made.
+        let from = moves.premoves.destination(piece.loc);
+        let made = square.filter(|&sq| sq != from).and_then(|sq| moves.make(&game.position, side, from, sq));
+        if made.is_some() {
+            selected.0 = None;
+        }
+        if made != Some(true) {
+            transform.translation = piece.get_vec3(&view);
+        }
         return;
     }
 
-    let Ok((entity, piece)) = pieces.get(selected.0.unwrap()) else {
+    // A piece held keeps following the cursor above, but nothing new is picked or clicked while
+    // pieces are still sliding.
+    if !mouse.just_pressed(MouseButton::Left) || pieces.iter().any(|(_, _, moving)| moving) {
         return;
-    };
+    }
 
-    if let Some(Ok((dst_entity, dst_piece))) = board.get_entity(r, c).map(|e: Entity| pieces.get(e)) {
-        if piece.team == dst_piece.team {
-            selected.0 = Some(dst_entity);
-            return;
+    let Some((c, r)) = square else { return; };
+
+    if preview.0.is_some() {
+        preview.0 = None;
+        return;
+    }
+
+    let from = selected.0.and_then(|entity| pieces.get(entity).ok()).map(|(piece, _, _)| moves.premoves.destination(piece.loc));
+    if from.and_then(|from| moves.make(&game.position, side, from, (c, r))).is_some() {
+        selected.0 = None;
+        return;
+    }
+
+    // With premoves queued, the piece they bring to the square is the one picked.
+    let (oc, or) = moves.premoves.origin((c, r));
+    selected.0 = board.get_entity(or as usize, oc as usize).filter(|&e| pieces.get(e).is_ok_and(|(piece, _, _)| moves.premoves.destination(piece.loc) == (c, r)));
+
+    // Only the side moved from here picks pieces up; the other side's can still be selected.
+    let Some((entity, piece)) = selected.0.and_then(|e| pieces.get(e).ok().map(|(piece, _, _)| (e, *piece))) else { return; };
+    if piece.team != side {
+        return;
+    }
+
+    drag.0 = Some(entity);
+
+    let mut ghost = piece.sprite_bundle(&asset_server, &view, appearance.pieces());
+    ghost.sprite.color = GHOST_COLOR;
+    ghost.transform.translation.z = GHOST_Z;
+    commands.spawn((ghost, Ghost));
+}
+
+/// Shift and the arrow keys walk a cursor over the board. Enter picks up the piece under it,
+/// or puts the one picked up down there; Escape lets go of both.
+fn key_cursor(
+    keys: Res<ButtonInput<KeyCode>>,
+    game: Res<Game>,
+    players: Res<Players>,
+    board: Res<Board>,
+    view: Res<BoardView>,
+    mut cursor: ResMut<Cursor>,
+    mut selected: ResMut<Selected>,
+    pieces: Query<&Piece>,
+    mut moves: MoveInput,
+) {
+    let steps = [(KeyCode::ArrowUp, (0, 1)), (KeyCode::ArrowDown, (0, -1)), (KeyCode::ArrowLeft, (-1, 0)), (KeyCode::ArrowRight, (1, 0))];
+
+    if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        for (key, (df, dr)) in steps {
+            if !keys.just_pressed(key) {
+                continue;
+            }
+
+            // The cursor shows up on the king of the side to move, then moves the way the
+            // arrow points on screen.
+            let Some((f, r)) = cursor.0 else {
+                cursor.0 = Some(game.position.king(game.position.turn).unwrap_or((4, 0)));
+                continue;
+            };
+
+            let (df, dr) = if view.flipped { (-df, -dr) } else { (df, dr) };
+            cursor.0 = Some(((f as i8 + df).clamp(0, 7) as u8, (r as i8 + dr).clamp(0, 7) as u8));
         }
+    }
 
-        take_piece_wr.send(TakePieceEvent(dst_entity));
+    if keys.just_pressed(KeyCode::Escape) {
+        cursor.0 = None;
+        selected.0 = None;
     }
 
-    selected.0 = None;
-    board.move_entity((piece.loc.0 as usize, piece.loc.1 as usize), (c, r));
-    move_piece_wr.send(MovePieceEvent((entity, (c as u8, r as u8))));
+    let Some((c, r)) = cursor.0.filter(|_| keys.just_pressed(KeyCode::Enter)) else { return; };
+
+    if players.get(game.position.turn) != Player::Human {
+        return;
+    }
+
+    let from = selected.0.and_then(|entity| pieces.get(entity).ok()).map(|piece| piece.loc);
+    if let Some(mv) = from.and_then(|from| legal_move(&game.position, from, (c, r))) {
+        selected.0 = None;
+        moves.submit(mv);
+        return;
+    }
+
+    selected.0 = board.get_entity(r as usize, c as usize).filter(|&e| pieces.get(e).is_ok_and(|p| p.team == game.position.turn));
 }
 
-fn create_board(mut commands: Commands) {
-    for row in 0..8 {
-        for col in 0..8 {
-            let pos = Vec3::new(
-                OFFSET + col as f32 * SQUARE_SIZE, 
-                OFFSET + row as f32 * SQUARE_SIZE, 0.
-            );
+/// The legal move between two squares; a promotion is made to a queen, which the picker may change.
+fn legal_move(pos: &Position, from: Square, to: Square) -> Option<Move> {
+    [Move::new(from, to), Move::promote(from, to, Kind::Queen)].into_iter().find(|&mv| pos.is_legal(mv))
+}
 
-            commands
-                .spawn((
-                    SpriteBundle {
-                        sprite: Sprite {
-                            color: COLORS[((row + col) & 1) as usize],
+/// Run condition: the promotion picker is up and the board waits for it.
+pub fn promoting(promotion: Res<Promotion>) -> bool {
+    promotion.0.is_some()
+}
+
+/// Shows the promotion picker above the board while a promotion waits, and plays the move with
+/// the piece clicked. Escape or a right click take the move back instead.
+fn choose_promotion(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    appearance: Res<Appearance>,
+    game: Res<Game>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut promotion: ResMut<Promotion>,
+    cards: Query<Entity, With<PromotionCard>>,
+    mut choices: Query<(&Interaction, &PromotionChoice, &mut BackgroundColor), Changed<Interaction>>,
+    mut play_move_wr: EventWriter<PlayMoveEvent>,
+) {
+    if let Some(mv) = promotion.0 {
+        if game.is_changed() || keys.just_pressed(KeyCode::Escape) || mouse.just_pressed(MouseButton::Right) {
+            promotion.0 = None;
+        }
+
+        for (interaction, choice, mut color) in &mut choices {
+            match *interaction {
+                Interaction::Pressed => {
+                    play_move_wr.send(PlayMoveEvent(Move::promote(mv.from, mv.to, choice.0)));
+                    promotion.0 = None;
+                }
+                Interaction::Hovered => *color = MOVE_HOVER_COLOR.into(),
+                Interaction::None => *color = MOVE_COLOR.into(),
+            }
+        }
+    }
+
+    if !promotion.is_changed() {
+        return;
+    }
+
+    for card in &cards {
+        commands.entity(card).despawn_recursive();
+    }
+
+    let Some(mv) = promotion.0 else { return; };
+    let team = game.position.piece_at(mv.from).map_or(game.position.turn, |(color, _)| color);
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(16.),
+                    left: Val::Px(0.),
+                    right: Val::Px(PANEL_WIDTH),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+            PromotionCard,
+        ))
+        .with_children(|row| {
+            row.spawn(NodeBundle {
+                style: Style { padding: UiRect::all(Val::Px(8.)), column_gap: Val::Px(6.), ..default() },
+                background_color: PANEL_COLOR.into(),
+                focus_policy: FocusPolicy::Block,
+                ..default()
+            })
+            .with_children(|card| {
+                for kind in [Kind::Queen, Kind::Rook, Kind::Bishop, Kind::Knight] {
+                    card.spawn((
+                        ButtonBundle {
+                            style: Style { padding: UiRect::all(Val::Px(4.)), ..default() },
+                            background_color: MOVE_COLOR.into(),
                             ..default()
                         },
-                        transform: Transform {
-                            translation: pos,
-                            scale: Vec3::new(SQUARE_SIZE, SQUARE_SIZE, 0.),
+                        PromotionChoice(kind),
+                    ))
+                    .with_children(|button| {
+                        button.spawn(ImageBundle {
+                            style: Style { width: Val::Px(PROMOTION_ICON_SIZE), height: Val::Px(PROMOTION_ICON_SIZE), ..default() },
+                            image: asset_server.load(appearance.pieces().path(team, kind)).into(),
                             ..default()
-                        },
+                        });
+                    });
+                }
+            });
+        });
+}
+
+pub fn play_move(
+    mut game: ResMut<Game>,
+    mut board: ResMut<Board>,
+    mut play_move_ev: EventReader<PlayMoveEvent>,
+    mut take_piece_wr: EventWriter<TakePieceEvent>,
+    mut move_piece_wr: EventWriter<MovePieceEvent>,
+    mut promote_piece_wr: EventWriter<PromotePieceEvent>,
+) {
+    for PlayMoveEvent(mv) in play_move_ev.read() {
+        if game.outcome().is_some() || !game.position.is_legal(*mv) {
+            warn!("ignoring illegal move {mv}");
+            continue;
+        }
+
+        if let Some((c, r)) = game.position.captured_square(*mv) {
+            if let Some(entity) = board.remove_entity(r as usize, c as usize) {
+                take_piece_wr.send(TakePieceEvent(entity));
+            }
+        }
+
+        if let Some((src, dst)) = game.position.castling_rook(*mv) {
+            if let Some(rook) = board.get_entity(src.1 as usize, src.0 as usize) {
+                board.move_entity((src.0 as usize, src.1 as usize), (dst.0 as usize, dst.1 as usize));
+                move_piece_wr.send(MovePieceEvent((rook, dst)));
+            }
+        }
+
+        let Some(entity) = board.get_entity(mv.from.1 as usize, mv.from.0 as usize) else { continue; };
+
+        board.move_entity((mv.from.0 as usize, mv.from.1 as usize), (mv.to.0 as usize, mv.to.1 as usize));
+        move_piece_wr.send(MovePieceEvent((entity, mv.to)));
+
+        if let Some(kind) = mv.promotion {
+            promote_piece_wr.send(PromotePieceEvent((entity, kind)));
+        }
+
+        game.play(*mv);
+
+        if let Some(outcome) = game.outcome() {
+            info!("game over: {outcome:?}");
+        }
+    }
+}
+
+impl Default for BoardSettings {
+    fn default() -> Self {
+        Self { coordinates: true, auto_queen: true, premoves: true }
+    }
+}
+
+impl Default for BoardView {
+    fn default() -> Self {
+        Self { flipped: false, square_size: SQUARE_SIZE }
+    }
+}
+
+impl BoardView {
+    /// Centre of a square in world space, at depth `z`.
+    pub fn to_world(&self, sq: Square, z: f32) -> Vec3 {
+        let (c, r) = self.orient(sq);
+        Vec3::new(self.offset() + c as f32 * self.square_size, self.offset() + r as f32 * self.square_size, z)
+    }
+
+    /// Square under a point in world space, `None` off the board.
+    pub fn square_at(&self, world: Vec2) -> Option<Square> {
+        let c = ((world.x - self.offset()) / self.square_size + 0.5).floor();
+        let r = ((world.y - self.offset()) / self.square_size + 0.5).floor();
+
+        ((0. ..8.).contains(&c) && (0. ..8.).contains(&r)).then(|| self.orient((c as u8, r as u8)))
+    }
+
+    /// Size relative to the one sprites are made for.
+    pub fn scale(&self) -> f32 {
+        self.square_size / SQUARE_SIZE
+    }
+
+    /// Centre of the bottom left square, on both axes.
+    fn offset(&self) -> f32 {
+        -3.5 * self.square_size
+    }
+
+    /// Turns a square into its place counted from the bottom left, and back.
+    fn orient(&self, (c, r): Square) -> Square {
+        if self.flipped { (7 - c, 7 - r) } else { (c, r) }
+    }
+}
+
+fn create_hint_shapes(
+    mut commands: Commands,
+    appearance: Res<Appearance>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(HintShapes {
+        dot: meshes.add(Circle::new(SQUARE_SIZE * 0.16)).into(),
+        ring: meshes.add(Annulus::new(SQUARE_SIZE * 0.42, SQUARE_SIZE * 0.5)).into(),
+        color: materials.add(appearance.theme().hint),
+    });
+}
+
+fn highlight_squares(
+    mut commands: Commands,
+    game: Res<Game>,
+    selected: Res<Selected>,
+    cursor: Res<Cursor>,
+    preview: Res<Preview>,
+    view: Res<BoardView>,
+    appearance: Res<Appearance>,
+    premoves: Res<Premoves>,
+    shapes: Res<HintShapes>,
+    pieces: Query<&Piece>,
+    highlights: Query<Entity, With<Highlight>>,
+) {
+    if !(game.is_changed() || selected.is_changed() || cursor.is_changed() || preview.is_changed() || view.is_changed() || appearance.is_changed() || premoves.is_changed()) {
+        return;
+    }
+
+    let theme = appearance.theme();
+
+    for entity in &highlights {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    // A previewed position has its own pieces, the game's squares mean nothing there.
+    if preview.0.is_some() {
+        return;
+    }
+
+    let mut tint = |sq: Square, color: Color| {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite { color, ..default() },
+                transform: Transform {
+                    translation: view.to_world(sq, 0.5),
+                    scale: Vec3::new(view.square_size, view.square_size, 1.),
+                    ..default()
+                },
+                ..default()
+            },
+            Highlight,
+        ));
+    };
+
+    if let Some(last) = game.moves.last() {
+        tint(last.from, theme.last_move);
+        tint(last.to, theme.last_move);
+    }
+
+    for mv in &premoves.moves {
+        tint(mv.from, theme.premove);
+        tint(mv.to, theme.premove);
+    }
+
+    if let Some(sq) = cursor.0 {
+        tint(sq, theme.cursor);
+    }
+
+    let Some(piece) = selected.0.and_then(|entity| pieces.get(entity).ok()) else { return; };
+    tint(premoves.destination(piece.loc), theme.selected);
+
+    let mut moves: Vec<Move> = game.position.legal_moves().into_iter().filter(|mv| mv.from == piece.loc).collect();
+    // Promotions reach the same square four times.
+    moves.dedup_by_key(|mv| mv.to);
+
+    for mv in moves {
+        let mesh = if game.position.captured_square(mv).is_some() { &shapes.ring } else { &shapes.dot };
+
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: mesh.clone(),
+                material: shapes.color.clone(),
+                transform: Transform::from_translation(view.to_world(mv.to, 0.6)).with_scale(Vec3::splat(view.scale())),
+                ..default()
+            },
+            Highlight,
+        ));
+    }
+}
+
+fn create_board(mut commands: Commands, asset_server: Res<AssetServer>, view: Res<BoardView>, appearance: Res<Appearance>) {
+    for row in 0..8 {
+        for col in 0..8 {
+            let (sprite, texture) = tile_sprite(&asset_server, appearance.theme(), (col, row));
+
+            commands.spawn((
+                SpriteBundle {
+                    sprite,
+                    texture,
+                    transform: Transform {
+                        translation: view.to_world((col, row), 0.),
+                        scale: Vec3::new(view.square_size, view.square_size, 0.),
                         ..default()
                     },
+                    ..default()
+                },
+                Tile((col, row)),
             ));
         }
     }
 }
+
+/// A square in the theme's colour, or its image stretched over the square.
+fn tile_sprite(asset_server: &AssetServer, theme: &Theme, sq: Square) -> (Sprite, Handle<Image>) {
+    match theme.square_texture(sq) {
+        Some(path) => (Sprite { custom_size: Some(Vec2::ONE), ..default() }, asset_server.load(path.to_string())),
+        None => (Sprite { color: theme.square_color(sq), ..default() }, Handle::default()),
+    }
+}
+
+/// Another theme chosen: the squares and the move hints take its colours.
+fn restyle_board(
+    asset_server: Res<AssetServer>,
+    appearance: Res<Appearance>,
+    shapes: Res<HintShapes>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut tiles: Query<(&Tile, &mut Sprite, &mut Handle<Image>)>,
+) {
+    if !appearance.is_changed() || appearance.is_added() {
+        return;
+    }
+
+    for (tile, mut sprite, mut texture) in &mut tiles {
+        (*sprite, *texture) = tile_sprite(&asset_server, appearance.theme(), tile.0);
+    }
+
+    if let Some(material) = materials.get_mut(&shapes.color) {
+        material.color = appearance.theme().hint;
+    }
+}
+
+fn toggle_coordinates(keys: Res<ButtonInput<KeyCode>>, mut settings: ResMut<BoardSettings>) {
+    if keys.just_pressed(KeyCode::KeyC) {
+        settings.coordinates = !settings.coordinates;
+    }
+}
+
+fn label_coordinates(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<BoardSettings>,
+    view: Res<BoardView>,
+    appearance: Res<Appearance>,
+    labels: Query<Entity, With<CoordinateLabel>>,
+) {
+    if !(settings.is_changed() || view.is_changed() || appearance.is_changed()) {
+        return;
+    }
+
+    for entity in &labels {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    if !settings.coordinates {
+        return;
+    }
+
+    let (left, bottom) = if view.flipped { (7, 7) } else { (0, 0) };
+    let inset = view.square_size * (0.5 - COORDINATE_MARGIN);
+
+    let files = (0..8).map(|f| ((f, bottom), (b'a' + f) as char, Vec2::new(inset, -inset), Anchor::BottomRight));
+    let ranks = (0..8).map(|r| ((left, r), (b'1' + r) as char, Vec2::new(-inset, inset), Anchor::TopLeft));
+
+    for ((c, r), name, corner, anchor) in files.chain(ranks) {
+        // Drawn in the other square colour so it shows on both.
+        let style = TextStyle {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: view.square_size * COORDINATE_SIZE,
+            color: if light_square((c, r)) { appearance.theme().dark } else { appearance.theme().light },
+        };
+
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(name, style),
+                text_anchor: anchor,
+                transform: Transform::from_translation(view.to_world((c, r), 0.7) + corner.extend(0.)),
+                ..default()
+            },
+            CoordinateLabel,
+        ));
+    }
+}
+
+/// Black at the bottom whenever only Black is played from this side of the screen.
+fn orient_board(players: Res<Players>, mut view: ResMut<BoardView>) {
+    if players.is_changed() {
+        view.flipped = players.white != Player::Human && players.black == Player::Human;
+    }
+}
+
+fn board_buttons(
+    keys: Res<ButtonInput<KeyCode>>,
+    entry: Res<MoveEntry>,
+    players: Res<Players>,
+    mut game: ResMut<Game>,
+    mut view: ResMut<BoardView>,
+    mut interactions: Query<(&Interaction, &BoardButton, &mut BackgroundColor), Changed<Interaction>>,
+    mut history_wr: EventWriter<HistoryEvent>,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    let mut pressed = if entry.focused {
+        None
+    } else if keys.just_pressed(KeyCode::KeyF) {
+        Some(BoardButton::Flip)
+    } else if ctrl && (keys.just_pressed(KeyCode::KeyY) || shift && keys.just_pressed(KeyCode::KeyZ)) {
+        Some(BoardButton::Redo)
+    } else if ctrl && keys.just_pressed(KeyCode::KeyZ) {
+        Some(BoardButton::Undo)
+    } else if ctrl && keys.just_pressed(KeyCode::KeyR) {
+        Some(BoardButton::Resign)
+    } else if ctrl && keys.just_pressed(KeyCode::KeyD) {
+        Some(BoardButton::Draw)
+    } else {
+        None
+    };
+
+    for (interaction, button, mut color) in &mut interactions {
+        match *interaction {
+            Interaction::Pressed => pressed = Some(*button),
+            Interaction::Hovered => *color = MOVE_HOVER_COLOR.into(),
+            Interaction::None => *color = MOVE_COLOR.into(),
+        }
+    }
+
+    match pressed {
+        Some(BoardButton::Flip) => view.flipped = !view.flipped,
+        Some(BoardButton::Undo) => {
+            history_wr.send(HistoryEvent::Undo);
+        }
+        Some(BoardButton::Redo) => {
+            history_wr.send(HistoryEvent::Redo);
+        }
+        Some(BoardButton::Resign) => {
+            if let Some(color) = local_side(&game, &players) {
+                game.resign(color);
+            }
+        }
+        Some(BoardButton::Draw) => {
+            let Some(color) = local_side(&game, &players) else { return; };
+            let opponent = color.other();
+
+            // A program takes the offer unless it is ahead on material.
+            let accepted = game.offer_draw(color)
+                || players.get(opponent) != Player::Human
+                    && material(&game, opponent) <= material(&game, color)
+                    && game.offer_draw(opponent);
+
+            info!("{color:?} offers a draw: {}", if accepted { "accepted" } else { "pending" });
+        }
+        None => {}
+    }
+}
+
+/// Side the player at this screen speaks for: theirs against a program, the side to move
+/// when both are played here, nobody when two programs play.
+fn local_side(game: &Game, players: &Players) -> Option<PieceColor> {
+    let turn = game.position.turn;
+
+    [turn, turn.other()].into_iter().find(|&color| players.get(color) == Player::Human)
+}
+
+/// Takes moves back, or replays them, until a player on this screen is to move again.
+/// The pieces are set up afresh after a takeback or for a new game.
+fn take_back(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    appearance: Res<Appearance>,
+    view: Res<BoardView>,
+    players: Res<Players>,
+    mut game: ResMut<Game>,
+    mut board: ResMut<Board>,
+    mut selected: ResMut<Selected>,
+    mut drag: ResMut<Drag>,
+    mut history_ev: EventReader<HistoryEvent>,
+    mut new_game_ev: EventReader<NewGameEvent>,
+    pieces: Query<Entity, With<Piece>>,
+    ghosts: Query<Entity, With<Ghost>>,
+    mut play_move_wr: EventWriter<PlayMoveEvent>,
+) {
+    let our_turn = |pos: &Position| !players.any_human() || players.get(pos.turn) == Player::Human;
+    let mut rebuild = new_game_ev.read().count() > 0;
+
+    for event in history_ev.read() {
+        match event {
+            HistoryEvent::Undo => {
+                if game.undo().is_none() {
+                    continue;
+                }
+
+                // Against an engine its reply goes too.
+                while !our_turn(&game.position) && game.undo().is_some() {}
+
+                // Captured pieces come back, so the board is set up afresh.
+                rebuild = true;
+            }
+            HistoryEvent::Redo => {
+                let mut pos = game.position.clone();
+
+                for mv in game.undone() {
+                    play_move_wr.send(PlayMoveEvent(mv));
+                    pos.make_move(mv);
+
+                    if our_turn(&pos) {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    if !rebuild {
+        return;
+    }
+
+    for entity in pieces.iter().chain(&ghosts) {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    *board = Board { entities: [None; 8 * 8] };
+    selected.0 = None;
+    drag.0 = None;
+    spawn_pieces(&mut commands, &asset_server, &view, appearance.pieces(), &mut board, &game.position);
+}
+
+/// Puts squares and pieces where the view says; pieces still sliding just land.
+fn apply_view(
+    mut commands: Commands,
+    view: Res<BoardView>,
+    appearance: Res<Appearance>,
+    mut tiles: Query<(&Tile, &mut Transform), Without<Piece>>,
+    mut pieces: Query<(Entity, &Piece, &mut Transform), Without<Tile>>,
+) {
+    if !view.is_changed() {
+        return;
+    }
+
+    for (tile, mut transform) in &mut tiles {
+        transform.translation = view.to_world(tile.0, 0.);
+        transform.scale = Vec3::new(view.square_size, view.square_size, 0.);
+    }
+
+    for (entity, piece, mut transform) in &mut pieces {
+        commands.entity(entity).remove::<Tween>();
+        transform.translation = piece.get_vec3(&view);
+        transform.scale = piece_scale(&view, appearance.pieces());
+    }
+}
+
+/// Largest board that fits beside the panel.
+fn fit_board(mut resized: EventReader<WindowResized>, mut view: ResMut<BoardView>) {
+    let Some(window) = resized.read().last() else { return; };
+    let size = ((window.width - PANEL_WIDTH).min(window.height) / 8.).max(MIN_SQUARE_SIZE);
+
+    if size != view.square_size {
+        view.square_size = size;
+    }
+}
+
+fn setup_board_buttons(mut commands: Commands, asset_server: Res<AssetServer>, toolbar: Query<Entity, With<PanelToolbar>>) {
+    commands.entity(toolbar.single()).with_children(|toolbar| {
+        for (button, label) in [
+            (BoardButton::Flip, "Flip board"),
+            (BoardButton::Undo, "Undo"),
+            (BoardButton::Redo, "Redo"),
+            (BoardButton::Resign, "Resign"),
+            (BoardButton::Draw, "Offer draw"),
+        ] {
+            toolbar
+                .spawn((
+                    ButtonBundle {
+                        style: Style { padding: UiRect::axes(Val::Px(8.), Val::Px(4.)), ..default() },
+                        background_color: MOVE_COLOR.into(),
+                        ..default()
+                    },
+                    button,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(label, text_style(&asset_server, 16.)));
+                });
+        }
+    });
+}
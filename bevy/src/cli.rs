@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use crate::engine::{EngineMode, EngineSettings};
+use crate::piece::PieceColor;
+use crate::settings::Settings;
+use crate::setup::{GameConfig, Opponent, SideChoice};
+use crate::timer::{ClockSettings, Timing};
+use crate::uci::EngineConfig;
+
+pub const USAGE: &str = "usage: chess [--multipv <n>] [--clock [moves/]secs[+inc][:...]] [--delay bronstein|simple] [--animation <ms>] [--accessible] [--engine <path> [--engine-arg <arg>]... \
+[--engine-option <name>=<value>]... [--engine-plays white|black] [--analyse] [--movetime <ms>] [--ponder]]";
+
+pub struct Args {
+    pub engine: Option<EngineSettings>,
+    /// Number of lines shown in the analysis panel.
+    pub multipv: usize,
+    /// Time control for both players; without one nobody plays on a clock.
+    pub clock: Option<ClockSettings>,
+    /// How long pieces take to slide, zero for no animation; the settings' when not given.
+    pub animation: Option<Duration>,
+    /// Moves and positions read out for screen readers from the start.
+    pub accessible: bool,
+}
+
+impl Args {
+    /// Setup screen choices matching the command line: its engine as the opponent and its clock,
+    /// or the settings' time control.
+    pub fn game_config(&self, settings: &Settings) -> GameConfig {
+        let mut config = GameConfig { clock: settings.time_control.clone(), ..GameConfig::default() };
+
+        if let Some(EngineSettings { mode: EngineMode::Play(color), .. }) = &self.engine {
+            config.opponent = Opponent::Engine;
+            config.side = if *color == PieceColor::White { SideChoice::Black } else { SideChoice::White };
+        }
+
+        if let Some(clock) = &self.clock {
+            config.clock = clock.to_string();
+            config.timing = clock.timing;
+        }
+
+        config
+    }
+
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut config: Option<EngineConfig> = None;
+        let mut engine_args = Vec::new();
+        let mut options = Vec::new();
+        let mut mode = EngineMode::Play(PieceColor::Black);
+        let mut movetime = Duration::from_secs(1);
+        let mut multipv = 3;
+        let mut clock: Option<ClockSettings> = None;
+        let mut timing = Timing::default();
+        let mut ponder = false;
+        let mut animation = None;
+        let mut accessible = false;
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+
+            match arg.as_str() {
+                "--engine" => config = Some(EngineConfig::new(value()?)),
+                "--engine-arg" => engine_args.push(value()?),
+                "--engine-option" => {
+                    let option = value()?;
+                    let (name, val) = option.split_once('=').ok_or(format!("expected name=value, got {option}"))?;
+                    options.push((name.to_string(), val.to_string()));
+                }
+                "--engine-plays" => {
+                    mode = match value()?.as_str() {
+                        "white" => EngineMode::Play(PieceColor::White),
+                        "black" => EngineMode::Play(PieceColor::Black),
+                        other => return Err(format!("unknown side {other}")),
+                    }
+                }
+                "--analyse" => mode = EngineMode::Analyse,
+                "--movetime" => {
+                    let ms = value()?.parse().map_err(|_| "movetime must be in milliseconds".to_string())?;
+                    movetime = Duration::from_millis(ms);
+                }
+                "--multipv" => {
+                    multipv = value()?.parse().ok().filter(|&n| n > 0).ok_or("multipv must be a positive number")?;
+                }
+                "--clock" => clock = Some(value()?.parse().map_err(|e| format!("{e}"))?),
+                "--delay" => {
+                    timing = match value()?.as_str() {
+                        "bronstein" => Timing::Bronstein,
+                        "simple" => Timing::Simple,
+                        other => return Err(format!("unknown delay {other}")),
+                    }
+                }
+                "--ponder" => ponder = true,
+                "--animation" => {
+                    let ms = value()?.parse().map_err(|_| "animation must be in milliseconds".to_string())?;
+                    animation = Some(Duration::from_millis(ms));
+                }
+                "--accessible" => accessible = true,
+                other => return Err(format!("unknown argument {other}")),
+            }
+        }
+
+        if mode == EngineMode::Analyse && multipv > 1 && !options.iter().any(|(name, _): &(String, String)| name.eq_ignore_ascii_case("MultiPV")) {
+            options.push(("MultiPV".to_string(), multipv.to_string()));
+        }
+
+        if ponder && !options.iter().any(|(name, _)| name.eq_ignore_ascii_case("Ponder")) {
+            options.push(("Ponder".to_string(), "true".to_string()));
+        }
+
+        if let Some(clock) = clock.as_mut() {
+            clock.timing = timing;
+        }
+
+        let engine = config.map(|mut config| {
+            config.args = engine_args;
+            config.options = options;
+            EngineSettings { config, mode, movetime, ponder }
+        });
+
+        Ok(Self { engine, multipv, clock, animation, accessible })
+    }
+}
@@ -3,10 +3,26 @@ use bevy::color::Color;
 pub const WIDTH: f32 = SQUARE_SIZE * 8.;
 pub const HEIGHT: f32 = SQUARE_SIZE * 8.;
 
+/// Square size the window opens with; the board scales from there as it is resized.
 pub const SQUARE_SIZE: f32 = 75.;
-pub const OFFSET: f32 = (-(8. / 2. * SQUARE_SIZE)) + SQUARE_SIZE / 2.;
+/// Smallest square the window can be shrunk to.
+pub const MIN_SQUARE_SIZE: f32 = 32.;
+
+pub const PANEL_WIDTH: f32 = 300.;
+pub const PANEL_COLOR: Color = Color::srgb(0.12, 0.12, 0.12);
+pub const TEXT_COLOR: Color = Color::WHITE;
+pub const IDLE_TEXT_COLOR: Color = Color::srgb(0.55, 0.55, 0.55);
+/// Background of the option currently picked in a row of choices.
+pub const CHOSEN_COLOR: Color = Color::srgb(0.25, 0.4, 0.6);
 
 pub const COLORS: [Color; 2] = [
     Color::srgb(181.0 / 255.0, 136.0 / 255.0, 99.0 / 255.0),
     Color::srgb(240.0 / 255.0, 217.0 / 255.0, 181.0 / 255.0)
 ];
+
+pub const SELECTED_COLOR: Color = Color::srgba(0.08, 0.47, 0.31, 0.5);
+pub const LAST_MOVE_COLOR: Color = Color::srgba(0.61, 0.78, 0.0, 0.41);
+pub const HINT_COLOR: Color = Color::srgba(0.08, 0.33, 0.12, 0.5);
+pub const CURSOR_COLOR: Color = Color::srgba(0.2, 0.45, 0.9, 0.5);
+pub const PREMOVE_COLOR: Color = Color::srgba(0.8, 0.25, 0.2, 0.45);
+pub const GHOST_COLOR: Color = Color::srgba(1., 1., 1., 0.35);
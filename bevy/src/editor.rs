@@ -0,0 +1,340 @@
+//! Setting up any position by hand, to play or analyse from.
+
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+use bevy::window::PrimaryWindow;
+
+use crate::analysis::{text_style, AnalysisSettings, MOVE_COLOR, MOVE_HOVER_COLOR};
+use crate::board::{BoardView, Preview};
+use crate::consts::{CHOSEN_COLOR, PANEL_COLOR, PANEL_WIDTH};
+use crate::game::{Game, Players};
+use crate::piece::{Kind, Piece, PieceColor};
+use crate::rules::{square_name, Position, Square};
+use crate::setup::{GameConfig, NewGameEvent, Opponent, SetupScreen, StartChoice};
+use crate::states::AppState;
+use crate::theme::Appearance;
+
+const ICON_SIZE: f32 = 32.;
+const KINDS: [Kind; 6] = [Kind::King, Kind::Queen, Kind::Rook, Kind::Bishop, Kind::Knight, Kind::Pawn];
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<PositionEditor>()
+            .add_systems(OnEnter(AppState::Editor), setup_editor)
+            .add_systems(OnExit(AppState::Editor), close_editor)
+            .add_systems(
+                Update,
+                (edit_board, choose_option, show_editor.after(edit_board).after(choose_option)).run_if(in_state(AppState::Editor)),
+            );
+    }
+}
+
+/// The position on the editor's board and the piece a click puts down.
+#[derive(Resource)]
+pub struct PositionEditor {
+    pub position: Position,
+    /// `None` takes pieces off.
+    pub brush: Option<(PieceColor, Kind)>,
+    /// Where Cancel leads back to.
+    pub back: AppState,
+    error: Option<String>,
+}
+
+#[derive(Component, Copy, Clone, Debug, PartialEq)]
+enum EditorOption {
+    Brush(Option<(PieceColor, Kind)>),
+    Turn(PieceColor),
+    Castle(PieceColor, bool),
+    EnPassant(Option<Square>),
+    Standard,
+    Clear,
+    Play,
+    Analyze,
+    Cancel,
+}
+
+#[derive(Component)]
+struct EditorCard;
+
+impl Default for PositionEditor {
+    fn default() -> Self {
+        Self { position: Position::default(), brush: Some((PieceColor::White, Kind::Pawn)), back: AppState::MainMenu, error: None }
+    }
+}
+
+impl PositionEditor {
+    /// Puts the brush on `sq`; the same piece there again is taken off.
+    pub fn paint(&mut self, sq: Square) {
+        let piece = self.brush.filter(|&brush| self.position.piece_at(sq) != Some(brush));
+        self.position.set_piece(sq, piece);
+        self.tidy();
+    }
+
+    pub fn erase(&mut self, sq: Square) {
+        self.position.set_piece(sq, None);
+        self.tidy();
+    }
+
+    /// Drops castling and en passant rights the board no longer allows.
+    pub fn tidy(&mut self) {
+        for color in [PieceColor::White, PieceColor::Black] {
+            for king_side in [true, false] {
+                if !self.position.castling_possible(color, king_side) {
+                    self.position.castling.set(color, king_side, false);
+                }
+            }
+        }
+
+        if self.position.en_passant.is_some_and(|ep| !self.position.en_passant_candidates().contains(&ep)) {
+            self.position.en_passant = None;
+        }
+
+        self.error = None;
+    }
+
+    fn chosen(&self, option: &EditorOption) -> bool {
+        match *option {
+            EditorOption::Brush(brush) => self.brush == brush,
+            EditorOption::Turn(color) => self.position.turn == color,
+            EditorOption::Castle(color, king_side) => self.position.castling.get(color, king_side),
+            EditorOption::EnPassant(ep) => self.position.en_passant == ep,
+            _ => false,
+        }
+    }
+}
+
+/// Starts from the position the next game would, or the standard one.
+fn setup_editor(mut commands: Commands, config: Res<GameConfig>, mut editor: ResMut<PositionEditor>) {
+    editor.position = match config.start {
+        StartChoice::Fen => Position::from_fen(config.fen.trim()).unwrap_or_default(),
+        StartChoice::Standard => Position::default(),
+    };
+    editor.tidy();
+
+    // Takes the place of the panel, the board stays free to click on.
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(0.),
+                    right: Val::Px(0.),
+                    width: Val::Px(PANEL_WIDTH),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(10.)),
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: PANEL_COLOR.into(),
+                focus_policy: FocusPolicy::Block,
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+            EditorCard,
+            StateScoped(AppState::Editor),
+        ));
+}
+
+fn close_editor(mut preview: ResMut<Preview>) {
+    preview.0 = None;
+}
+
+/// Left click puts the chosen piece down, right click takes one off.
+fn edit_board(
+    mut editor: ResMut<PositionEditor>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    view: Res<BoardView>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+) {
+    if !(mouse.just_pressed(MouseButton::Left) || mouse.just_pressed(MouseButton::Right)) {
+        return;
+    }
+
+    let (camera, camera_transform) = cameras.single();
+    let world = windows.single().cursor_position().and_then(|c| camera.viewport_to_world_2d(camera_transform, c));
+    let Some(sq) = world.and_then(|w| view.square_at(w)) else { return; };
+
+    if mouse.just_pressed(MouseButton::Left) {
+        editor.paint(sq);
+    } else {
+        editor.erase(sq);
+    }
+}
+
+fn choose_option(
+    mut editor: ResMut<PositionEditor>,
+    mut config: ResMut<GameConfig>,
+    mut screen: ResMut<SetupScreen>,
+    mut game: ResMut<Game>,
+    mut players: ResMut<Players>,
+    mut analysis: ResMut<AnalysisSettings>,
+    mut next: ResMut<NextState<AppState>>,
+    mut interactions: Query<(&Interaction, &EditorOption, &mut BackgroundColor), Changed<Interaction>>,
+    mut new_game_wr: EventWriter<NewGameEvent>,
+) {
+    for (interaction, option, mut color) in &mut interactions {
+        match *interaction {
+            Interaction::Pressed => match *option {
+                EditorOption::Brush(brush) => editor.brush = brush,
+                EditorOption::Turn(turn) => {
+                    editor.position.turn = turn;
+                    editor.tidy();
+                }
+                EditorOption::Castle(color, king_side) => {
+                    let castling = !editor.position.castling.get(color, king_side);
+                    editor.position.castling.set(color, king_side, castling);
+                }
+                EditorOption::EnPassant(ep) => editor.position.en_passant = ep,
+                EditorOption::Standard => {
+                    editor.position = Position::default();
+                    editor.tidy();
+                }
+                EditorOption::Clear => {
+                    editor.position = Position::empty();
+                    editor.tidy();
+                }
+                EditorOption::Play | EditorOption::Analyze => {
+                    if let Err(err) = editor.position.validate() {
+                        editor.error = Some(err.to_string());
+                        continue;
+                    }
+
+                    config.start = StartChoice::Fen;
+                    config.fen = editor.position.fen();
+
+                    if *option == EditorOption::Play {
+                        screen.back = Some(AppState::Editor);
+                        next.set(AppState::Setup);
+                        continue;
+                    }
+
+                    // Both sides moved by hand, no clock, the analysis running.
+                    config.opponent = Opponent::Human;
+                    config.clock.clear();
+                    *game = Game::new(editor.position.clone());
+                    *players = Players::default();
+                    analysis.enabled = true;
+                    new_game_wr.send(NewGameEvent);
+                    next.set(AppState::Playing);
+                }
+                EditorOption::Cancel => next.set(editor.back),
+            },
+            Interaction::Hovered => *color = MOVE_HOVER_COLOR.into(),
+            Interaction::None => *color = if editor.chosen(option) { CHOSEN_COLOR } else { MOVE_COLOR }.into(),
+        }
+    }
+}
+
+/// Shows the edited position on the board and lays the panel out again after every change.
+fn show_editor(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    editor: Res<PositionEditor>,
+    appearance: Res<Appearance>,
+    mut preview: ResMut<Preview>,
+    card: Query<Entity, With<EditorCard>>,
+) {
+    // Clicking the board or Escape clears a preview; the editor keeps its own up.
+    if editor.is_changed() || preview.0.is_none() {
+        preview.0 = Some(editor.position.clone());
+    }
+
+    if !(editor.is_changed() || appearance.is_changed()) {
+        return;
+    }
+
+    let Ok(card) = card.get_single() else { return; };
+    commands.entity(card).despawn_descendants();
+
+    let pos = &editor.position;
+    let row = |parent: &mut ChildBuilder, title: &str, options: Vec<(EditorOption, String)>| {
+        parent.spawn(TextBundle::from_section(title, text_style(&asset_server, 16.)));
+        parent
+            .spawn(NodeBundle {
+                style: Style { flex_wrap: FlexWrap::Wrap, column_gap: Val::Px(6.), row_gap: Val::Px(6.), ..default() },
+                ..default()
+            })
+            .with_children(|row| {
+                for (option, label) in options {
+                    spawn_option(row, &editor, option, |button| {
+                        button.spawn(TextBundle::from_section(label, text_style(&asset_server, 16.)));
+                    });
+                }
+            });
+    };
+
+    commands.entity(card).with_children(|card| {
+        card.spawn(TextBundle::from_section("Edit position", text_style(&asset_server, 28.)));
+
+        for team in [PieceColor::White, PieceColor::Black] {
+            card.spawn(NodeBundle { style: Style { column_gap: Val::Px(4.), ..default() }, ..default() }).with_children(|palette| {
+                for kind in KINDS {
+                    spawn_option(palette, &editor, EditorOption::Brush(Some((team, kind))), |button| {
+                        button.spawn(ImageBundle {
+                            style: Style { width: Val::Px(ICON_SIZE), height: Val::Px(ICON_SIZE), ..default() },
+                            image: asset_server.load(Piece { kind, team, loc: (0, 0) }.get_asset_path(appearance.pieces())).into(),
+                            ..default()
+                        });
+                    });
+                }
+            });
+        }
+
+        row(card, "", vec![
+            (EditorOption::Brush(None), String::from("Erase")),
+            (EditorOption::Standard, String::from("Standard")),
+            (EditorOption::Clear, String::from("Empty board")),
+        ]);
+
+        row(card, "Side to move", vec![
+            (EditorOption::Turn(PieceColor::White), String::from("White")),
+            (EditorOption::Turn(PieceColor::Black), String::from("Black")),
+        ]);
+
+        let castling: Vec<_> = [(PieceColor::White, true, "White O-O"), (PieceColor::White, false, "White O-O-O"), (PieceColor::Black, true, "Black O-O"), (PieceColor::Black, false, "Black O-O-O")]
+            .into_iter()
+            .filter(|&(color, king_side, _)| pos.castling_possible(color, king_side))
+            .map(|(color, king_side, label)| (EditorOption::Castle(color, king_side), String::from(label)))
+            .collect();
+        if !castling.is_empty() {
+            row(card, "Castling", castling);
+        }
+
+        let candidates = pos.en_passant_candidates();
+        if !candidates.is_empty() {
+            let mut options = vec![(EditorOption::EnPassant(None), String::from("None"))];
+            options.extend(candidates.into_iter().map(|ep| (EditorOption::EnPassant(Some(ep)), square_name(ep))));
+            row(card, "En passant", options);
+        }
+
+        card.spawn(TextBundle::from_section(pos.fen(), text_style(&asset_server, 12.)));
+
+        if let Some(error) = &editor.error {
+            card.spawn(TextBundle::from_section(error.clone(), TextStyle { color: Color::srgb(0.9, 0.4, 0.4), ..text_style(&asset_server, 14.) }));
+        }
+
+        row(card, "", vec![
+            (EditorOption::Play, String::from("Play")),
+            (EditorOption::Analyze, String::from("Analyze")),
+            (EditorOption::Cancel, String::from("Cancel")),
+        ]);
+    });
+}
+
+fn spawn_option(parent: &mut ChildBuilder, editor: &PositionEditor, option: EditorOption, content: impl FnOnce(&mut ChildBuilder)) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style { padding: UiRect::axes(Val::Px(8.), Val::Px(4.)), ..default() },
+                background_color: if editor.chosen(&option) { CHOSEN_COLOR } else { MOVE_COLOR }.into(),
+                ..default()
+            },
+            option,
+        ))
+        .with_children(content);
+}
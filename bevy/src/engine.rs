@@ -0,0 +1,310 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::utils::synccell::SyncCell;
+
+use crate::game::{Game, PlayMoveEvent, Player, Players};
+use crate::piece::PieceColor;
+use crate::rules::{Move, Position};
+use crate::search::{Limits, SearchThread};
+use crate::setup::NewGameEvent;
+use crate::states::{in_game, AppState};
+use crate::timer::{ClockSettings, Timer};
+use crate::uci::{EngineConfig, Event, Go, Info, UciEngine};
+
+/// Time the built-in search takes per move when no clock runs.
+const COMPUTER_MOVETIME: Duration = Duration::from_secs(1);
+
+pub struct EnginePlugin;
+
+impl Plugin for EnginePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<EngineAnalysis>()
+            .init_resource::<ComputerSearch>()
+            .add_systems(Startup, start_engine.run_if(resource_exists::<EngineSettings>))
+            .add_systems(Update, new_engine_game.run_if(resource_exists::<EngineProcess>))
+            .add_systems(Update, drive_engine.after(new_engine_game).run_if(resource_exists::<EngineProcess>).run_if(in_game))
+            .add_systems(Update, drive_computer.run_if(in_state(AppState::Playing)));
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EngineMode {
+    Play(PieceColor),
+    Analyse,
+}
+
+/// External UCI engine requested on the command line.
+#[derive(Resource, Clone, Debug)]
+pub struct EngineSettings {
+    pub config: EngineConfig,
+    pub mode: EngineMode,
+    /// Time per move when no clock runs.
+    pub movetime: Duration,
+    /// Think on the opponent's time about the reply the engine expects.
+    pub ponder: bool,
+}
+
+/// Latest lines reported for `position`, indexed by `multipv - 1`.
+#[derive(Resource, Default)]
+pub struct EngineAnalysis {
+    pub position: Option<Position>,
+    pub lines: Vec<Info>,
+}
+
+#[derive(Resource)]
+pub struct EngineProcess {
+    engine: SyncCell<UciEngine>,
+    /// Position the engine is searching; while pondering, the one after the expected reply.
+    searching: Option<Position>,
+    /// Position the opponent is to move in while the engine ponders.
+    pondering: Option<Position>,
+    /// Reply expected in the position after the engine's move, to ponder on once it is played.
+    expected: Option<(Position, Move)>,
+    /// Position the engine last moved in, until its move reaches the board.
+    moved: Option<Position>,
+    /// A new game has started; the engine hears of it once it is idle.
+    new_game: bool,
+}
+
+/// The built-in search thinking for a side played by [`Player::Computer`].
+#[derive(Resource, Default)]
+struct ComputerSearch {
+    thread: Option<SyncCell<SearchThread>>,
+    position: Option<Position>,
+}
+
+impl EngineAnalysis {
+    pub fn reset(&mut self, position: Position) {
+        self.position = Some(position);
+        self.lines.clear();
+    }
+
+    pub fn update(&mut self, info: Info) {
+        if info.pv.is_empty() {
+            return;
+        }
+
+        let i = info.multipv.unwrap_or(1).max(1) as usize - 1;
+        if self.lines.len() <= i {
+            self.lines.resize(i + 1, Info::default());
+        }
+        self.lines[i] = info;
+    }
+}
+
+fn start_engine(mut commands: Commands, settings: Res<EngineSettings>, mut players: ResMut<Players>) {
+    match UciEngine::spawn(settings.config.clone()) {
+        Ok(engine) => {
+            if let EngineMode::Play(color) = settings.mode {
+                players.set(color, Player::Engine);
+            }
+
+            commands.insert_resource(EngineProcess {
+                engine: SyncCell::new(engine),
+                searching: None,
+                pondering: None,
+                expected: None,
+                moved: None,
+                new_game: false,
+            });
+        }
+        Err(err) => error!("failed to start engine {}: {err}", settings.config.path.display()),
+    }
+}
+
+/// Stops whatever the engine was doing and gives it the side, if any, it plays in the new game.
+fn new_engine_game(
+    players: Res<Players>,
+    mut settings: ResMut<EngineSettings>,
+    mut process: ResMut<EngineProcess>,
+    mut new_game_ev: EventReader<NewGameEvent>,
+) {
+    if new_game_ev.read().count() == 0 {
+        return;
+    }
+
+    if process.searching.is_some() {
+        process.engine.get().stop();
+    }
+
+    process.searching = None;
+    process.pondering = None;
+    process.expected = None;
+    process.moved = None;
+    process.new_game = true;
+
+    let playing = [PieceColor::White, PieceColor::Black].into_iter().find(|&color| players.get(color) == Player::Engine);
+    settings.mode = match (playing, settings.mode) {
+        (Some(color), _) => EngineMode::Play(color),
+        // Out of the game it can still analyse.
+        (None, EngineMode::Play(_)) => EngineMode::Analyse,
+        (None, mode) => mode,
+    };
+}
+
+fn drive_engine(
+    mut commands: Commands,
+    settings: Res<EngineSettings>,
+    game: Res<Game>,
+    mut process: ResMut<EngineProcess>,
+    mut players: ResMut<Players>,
+    mut analysis: ResMut<EngineAnalysis>,
+    clock: Option<Res<ClockSettings>>,
+    timers: Query<&Timer>,
+    mut play_move_wr: EventWriter<PlayMoveEvent>,
+) {
+    let process = &mut *process;
+    let engine = process.engine.get();
+    let current = process.searching.as_ref() == Some(&game.position);
+
+    // The opponent played the move the engine pondered on, so its search counts from here.
+    if current && process.pondering.take().is_some() {
+        engine.ponderhit();
+        analysis.reset(game.position.clone());
+    }
+
+    for event in engine.poll() {
+        match event {
+            Event::Ready => info!("engine {} ready", engine.name.as_deref().unwrap_or("?")),
+            Event::Info(info) if current => analysis.update(info),
+            Event::Info(_) => {}
+            Event::BestMove { best, ponder } => {
+                process.searching = None;
+                process.pondering = None;
+
+                let EngineMode::Play(color) = settings.mode else { continue; };
+                let Some(mv) = best.filter(|_| current && game.position.turn == color) else { continue; };
+
+                play_move_wr.send(PlayMoveEvent(mv));
+                process.moved = Some(game.position.clone());
+
+                if let Some(reply) = ponder.filter(|_| settings.ponder) {
+                    let mut after = game.position.clone();
+                    after.make_move(mv);
+                    process.expected = Some((after, reply));
+                }
+            }
+            Event::UnknownOption(name) => warn!("engine has no option named {name}"),
+            Event::Crashed(reason) => {
+                error!("engine crashed: {reason}");
+                release(&mut commands, &mut players);
+                return;
+            }
+            Event::TimedOut(state) => {
+                error!("engine timed out while in {state:?}");
+                release(&mut commands, &mut players);
+                return;
+            }
+        }
+    }
+
+    // A different reply than the one pondered on, or a search the game has moved past.
+    if process.searching.is_some() && !current && process.pondering.as_ref() != Some(&game.position) {
+        engine.stop();
+        process.pondering = None;
+    }
+
+    if process.moved.as_ref().is_some_and(|moved| *moved != game.position) {
+        process.moved = None;
+    }
+
+    if !engine.is_ready() || process.searching.is_some() || process.moved.is_some() || game.outcome().is_some() {
+        return;
+    }
+
+    if process.new_game {
+        engine.new_game();
+        process.new_game = false;
+        return;
+    }
+
+    let clock_go = || match &clock {
+        Some(clock) => clock.go(game.position.turn, &timers),
+        None => Go::movetime(settings.movetime),
+    };
+
+    let go = match settings.mode {
+        EngineMode::Play(color) if color == game.position.turn => clock_go(),
+        EngineMode::Play(_) => return ponder(process, &game, clock_go()),
+        EngineMode::Analyse => Go::infinite(),
+    };
+
+    process.expected = None;
+    analysis.reset(game.position.clone());
+    engine.go(&game.start, &game.moves, &go);
+    process.searching = Some(game.position.clone());
+}
+
+/// Starts thinking about the expected reply while the opponent is to move.
+fn ponder(process: &mut EngineProcess, game: &Game, go: Go) {
+    let Some((_, reply)) = process.expected.take_if(|(after, _)| *after == game.position) else { return; };
+    if !game.position.is_legal(reply) {
+        return;
+    }
+
+    let mut moves = game.moves.clone();
+    let mut searching = game.position.clone();
+    moves.push(reply);
+    searching.make_move(reply);
+
+    process.engine.get().go(&game.start, &moves, &Go { ponder: true, ..go });
+    process.searching = Some(searching);
+    process.pondering = Some(game.position.clone());
+}
+
+/// Lets the built-in search play its side, on the clock if there is one.
+fn drive_computer(
+    game: Res<Game>,
+    players: Res<Players>,
+    clock: Option<Res<ClockSettings>>,
+    timers: Query<&Timer>,
+    mut search: ResMut<ComputerSearch>,
+    mut play_move_wr: EventWriter<PlayMoveEvent>,
+) {
+    let turn = game.position.turn;
+
+    if players.get(turn) != Player::Computer || game.outcome().is_some() {
+        if search.position.is_some() {
+            *search = ComputerSearch::default();
+        }
+        return;
+    }
+
+    if search.position.as_ref() != Some(&game.position) {
+        let mut limits = Limits::default();
+        match &clock {
+            Some(clock) => limits.budget = clock.go(turn, &timers).time_budget(turn),
+            None => limits.movetime = Some(COMPUTER_MOVETIME),
+        }
+
+        // Dropping the old thread stops it before the new one starts.
+        search.thread = None;
+        let keys = game.keys();
+        let thread = SearchThread::spawn(game.position.clone(), keys[..keys.len() - 1].to_vec(), limits, 1);
+        search.thread = Some(SyncCell::new(thread));
+        search.position = Some(game.position.clone());
+    }
+
+    // The position stays marked as searched until the move is on the board.
+    if let Some(mv) = search.thread.as_mut().and_then(|thread| thread.get().best_move()) {
+        play_move_wr.send(PlayMoveEvent(mv));
+    }
+}
+
+/// Whether an external engine is running in analysis mode.
+pub fn external_analysis(settings: Option<Res<EngineSettings>>, process: Option<Res<EngineProcess>>) -> bool {
+    process.is_some() && settings.is_some_and(|s| s.mode == EngineMode::Analyse)
+}
+
+/// Hands the engine's side back to the local player so the game can go on.
+fn release(commands: &mut Commands, players: &mut Players) {
+    for color in [PieceColor::White, PieceColor::Black] {
+        if players.get(color) == Player::Engine {
+            players.set(color, Player::Human);
+        }
+    }
+
+    commands.remove_resource::<EngineProcess>();
+}
@@ -0,0 +1,226 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::piece::{Kind, PieceColor};
+use crate::rules::{Move, Outcome, Position};
+
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Game>()
+            .init_resource::<Players>()
+            .add_event::<PlayMoveEvent>()
+            .add_event::<HistoryEvent>();
+    }
+}
+
+/// Asks for a move to be played on the board; illegal moves are dropped.
+#[derive(Event)]
+pub struct PlayMoveEvent(pub Move);
+
+/// Asks to take moves back or to replay the ones taken back.
+#[derive(Event, Copy, Clone, Debug, Eq, PartialEq)]
+pub enum HistoryEvent {
+    Undo,
+    Redo,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Player {
+    Human,
+    /// The UCI engine given on the command line.
+    Engine,
+    /// The built-in search.
+    Computer,
+}
+
+#[derive(Resource)]
+pub struct Players {
+    pub white: Player,
+    pub black: Player,
+}
+
+#[derive(Resource)]
+pub struct Game {
+    pub start: Position,
+    pub position: Position,
+    pub moves: Vec<Move>,
+    keys: Vec<u64>,
+    /// Moves taken back, the next one to replay last.
+    undone: Vec<Move>,
+    /// Side whose clock ran out.
+    flagged: Option<PieceColor>,
+    /// End the players chose themselves: a resignation or an agreed draw.
+    agreed: Option<Outcome>,
+    /// Side whose draw offer stands until the other side moves.
+    draw_offer: Option<PieceColor>,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new(Position::default())
+    }
+}
+
+impl Default for Players {
+    fn default() -> Self {
+        Self { white: Player::Human, black: Player::Human }
+    }
+}
+
+impl Players {
+    pub fn get(&self, color: PieceColor) -> Player {
+        match color {
+            PieceColor::White => self.white,
+            PieceColor::Black => self.black,
+        }
+    }
+
+    pub fn set(&mut self, color: PieceColor, player: Player) {
+        match color {
+            PieceColor::White => self.white = player,
+            PieceColor::Black => self.black = player,
+        }
+    }
+
+    /// Whether anybody plays a side from this screen.
+    pub fn any_human(&self) -> bool {
+        self.white == Player::Human || self.black == Player::Human
+    }
+}
+
+impl Game {
+    pub fn new(start: Position) -> Self {
+        Self {
+            keys: vec![start.key()],
+            position: start.clone(),
+            start,
+            moves: Vec::new(),
+            undone: Vec::new(),
+            flagged: None,
+            agreed: None,
+            draw_offer: None,
+        }
+    }
+
+    /// Repetition keys of every position so far, the current one last.
+    pub fn keys(&self) -> &[u64] {
+        &self.keys
+    }
+
+    pub fn play(&mut self, mv: Move) {
+        // Replaying a move taken back keeps the rest for redo, anything else drops them.
+        if self.undone.last() == Some(&mv) {
+            self.undone.pop();
+        } else {
+            self.undone.clear();
+        }
+
+        // Moving on declines the other side's offer.
+        if self.draw_offer == Some(self.position.turn.other()) {
+            self.draw_offer = None;
+        }
+
+        self.position.make_move(mv);
+        self.moves.push(mv);
+        self.keys.push(self.position.key());
+    }
+
+    /// Takes back the last move; it can be replayed until another move is played.
+    pub fn undo(&mut self) -> Option<Move> {
+        let mv = self.moves.pop()?;
+
+        self.keys.pop();
+        self.position = self.position_at(self.moves.len());
+        self.flagged = None;
+        self.agreed = None;
+        self.draw_offer = None;
+        self.undone.push(mv);
+
+        Some(mv)
+    }
+
+    /// Moves taken back, in the order they would be replayed.
+    pub fn undone(&self) -> impl Iterator<Item = Move> + '_ {
+        self.undone.iter().rev().copied()
+    }
+
+    /// Pieces taken so far, as `(owner, kind)` in the order they fell.
+    pub fn captures(&self) -> Vec<(PieceColor, Kind)> {
+        let mut pos = self.start.clone();
+        let mut captures = Vec::new();
+
+        for &mv in &self.moves {
+            captures.extend(pos.captured_square(mv).and_then(|sq| pos.piece_at(sq)));
+            pos.make_move(mv);
+        }
+
+        captures
+    }
+
+    /// Position after the first `plies` moves.
+    pub fn position_at(&self, plies: usize) -> Position {
+        let mut pos = self.start.clone();
+        for &mv in &self.moves[..plies.min(self.moves.len())] {
+            pos.make_move(mv);
+        }
+        pos
+    }
+
+    /// Ends the game on time, unless it is already over.
+    pub fn flag(&mut self, color: PieceColor) {
+        if self.outcome().is_none() {
+            self.flagged = Some(color);
+        }
+    }
+
+    pub fn resign(&mut self, color: PieceColor) {
+        if self.outcome().is_none() {
+            self.agreed = Some(Outcome::Resignation(color.other()));
+        }
+    }
+
+    /// Offers a draw, or takes the other side's offer; true once the game is drawn.
+    pub fn offer_draw(&mut self, color: PieceColor) -> bool {
+        if self.outcome().is_some() {
+            return false;
+        }
+
+        if self.draw_offer == Some(color.other()) {
+            self.agreed = Some(Outcome::Agreement);
+            return true;
+        }
+
+        self.draw_offer = Some(color);
+        false
+    }
+
+    /// Side whose draw offer is waiting for an answer.
+    pub fn draw_offer(&self) -> Option<PieceColor> {
+        self.draw_offer
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        if let Some(outcome) = self.agreed {
+            return Some(outcome);
+        }
+
+        if let Some(color) = self.flagged {
+            if !self.position.has_mating_material(color.other()) {
+                return Some(Outcome::TimeoutVsInsufficientMaterial);
+            }
+            return Some(Outcome::Timeout(color.other()));
+        }
+
+        if let Some(outcome) = self.position.outcome() {
+            return Some(outcome);
+        }
+
+        let current = self.position.key();
+        let seen = self.keys.iter().filter(|&&k| k == current).count();
+
+        (seen >= 3).then_some(Outcome::Repetition)
+    }
+}
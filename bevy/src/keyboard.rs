@@ -0,0 +1,179 @@
+//! Playing without the mouse: moves typed into a box in SAN or coordinates, with the legal
+//! moves that fit what has been typed so far offered as it goes.
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+
+use crate::analysis::{text_style, PanelColumn, MOVE_HOVER_COLOR};
+use crate::consts::IDLE_TEXT_COLOR;
+use crate::game::{Game, PlayMoveEvent, Player, Players};
+use crate::review::setup_review;
+use crate::san;
+use crate::states::AppState;
+
+const BOX_COLOR: Color = Color::srgb(0.05, 0.05, 0.05);
+const MAX_SUGGESTIONS: usize = 8;
+
+pub struct KeyboardPlugin;
+
+impl Plugin for KeyboardPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<MoveEntry>()
+            .add_systems(Startup, setup_move_entry.after(setup_review))
+            .add_systems(OnExit(AppState::Playing), leave_move_entry)
+            .add_systems(Update, type_move.run_if(in_state(AppState::Playing)))
+            .add_systems(Update, show_move_entry.after(type_move));
+    }
+}
+
+/// Text typed into the move box; while it has the focus, keys go to it rather than to shortcuts.
+#[derive(Resource, Default)]
+pub struct MoveEntry {
+    pub text: String,
+    pub focused: bool,
+    error: Option<String>,
+}
+
+#[derive(Component)]
+struct MoveBox;
+
+#[derive(Component)]
+struct MoveBoxText;
+
+#[derive(Component)]
+struct Suggestions;
+
+/// Run condition for single-key shortcuts, which stand down while a move is being typed.
+pub fn typing(entry: Res<MoveEntry>) -> bool {
+    entry.focused
+}
+
+pub fn setup_move_entry(mut commands: Commands, asset_server: Res<AssetServer>, column: Query<Entity, With<PanelColumn>>) {
+    let entry = commands
+        .spawn(NodeBundle {
+            style: Style { flex_direction: FlexDirection::Column, flex_shrink: 0., row_gap: Val::Px(4.), ..default() },
+            ..default()
+        })
+        .with_children(|column| {
+            column
+                .spawn((
+                    ButtonBundle {
+                        style: Style { min_height: Val::Px(24.), padding: UiRect::axes(Val::Px(6.), Val::Px(3.)), ..default() },
+                        background_color: BOX_COLOR.into(),
+                        ..default()
+                    },
+                    MoveBox,
+                ))
+                .with_children(|button| {
+                    button.spawn((TextBundle::from_section("", text_style(&asset_server, 16.)), MoveBoxText));
+                });
+            column.spawn((TextBundle::from_section("", text_style(&asset_server, 14.)), Suggestions));
+        })
+        .id();
+
+    commands.entity(column.single()).add_child(entry);
+}
+
+fn leave_move_entry(mut entry: ResMut<MoveEntry>) {
+    *entry = MoveEntry::default();
+}
+
+/// Tab or a click gives the box the focus and Escape takes it away. Enter plays the move typed,
+/// Tab fills in the first suggestion.
+fn type_move(
+    game: Res<Game>,
+    players: Res<Players>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut entry: ResMut<MoveEntry>,
+    mut keys: EventReader<KeyboardInput>,
+    boxes: Query<&Interaction, With<MoveBox>>,
+    mut play_move_wr: EventWriter<PlayMoveEvent>,
+) {
+    let clicked = boxes.iter().any(|i| *i == Interaction::Pressed);
+    if mouse.just_pressed(MouseButton::Left) && entry.focused != clicked {
+        entry.focused = clicked;
+    }
+
+    for key in keys.read().filter(|k| k.state == ButtonState::Pressed) {
+        if !entry.focused {
+            if key.logical_key == Key::Tab {
+                entry.focused = true;
+            }
+            continue;
+        }
+
+        entry.error = None;
+
+        match &key.logical_key {
+            Key::Character(s) => entry.text.push_str(s),
+            Key::Backspace => {
+                entry.text.pop();
+            }
+            Key::Escape => {
+                entry.text.clear();
+                entry.focused = false;
+            }
+            Key::Tab => {
+                if let Some(&mv) = san::complete(&game.position, &entry.text).first() {
+                    entry.text = san::san(&game.position, mv);
+                }
+            }
+            Key::Enter if players.get(game.position.turn) != Player::Human => {
+                entry.error = Some(String::from("Not your move"));
+            }
+            Key::Enter => match san::read(&game.position, &entry.text) {
+                Some(mv) => {
+                    play_move_wr.send(PlayMoveEvent(mv));
+                    entry.text.clear();
+                }
+                None => entry.error = Some(format!("No legal move {}", entry.text.trim())),
+            },
+            _ => {}
+        }
+    }
+}
+
+fn show_move_entry(
+    game: Res<Game>,
+    entry: Res<MoveEntry>,
+    mut boxes: Query<(Ref<Interaction>, &mut BackgroundColor), With<MoveBox>>,
+    mut texts: Query<&mut Text, With<MoveBoxText>>,
+    mut suggestions: Query<&mut Text, (With<Suggestions>, Without<MoveBoxText>)>,
+) {
+    for (interaction, mut color) in &mut boxes {
+        if !(interaction.is_changed() || entry.is_changed()) {
+            continue;
+        }
+
+        *color = if *interaction == Interaction::Hovered || entry.focused { MOVE_HOVER_COLOR } else { BOX_COLOR }.into();
+    }
+
+    if !(entry.is_changed() || game.is_changed()) {
+        return;
+    }
+
+    let section = &mut texts.single_mut().sections[0];
+    (section.value, section.style.color) = match (entry.focused, entry.text.is_empty()) {
+        (true, _) => (format!("{}|", entry.text), Color::WHITE),
+        (false, true) => (String::from("Type a move (Tab)"), IDLE_TEXT_COLOR),
+        (false, false) => (entry.text.clone(), Color::WHITE),
+    };
+
+    let mut lines = Vec::new();
+
+    if let Some(error) = &entry.error {
+        lines.push(error.clone());
+    } else if entry.focused {
+        let moves = san::complete(&game.position, &entry.text);
+        let sans: Vec<String> = moves.iter().take(MAX_SUGGESTIONS).map(|&mv| san::san(&game.position, mv)).collect();
+        lines.push(sans.join("  "));
+    }
+
+    if let Some(color) = game.draw_offer() {
+        lines.push(format!("{color:?} offers a draw (Ctrl+D to accept)"));
+    }
+
+    suggestions.single_mut().sections[0].value = lines.join("\n");
+}
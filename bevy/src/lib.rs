@@ -0,0 +1,35 @@
+// Bevy systems take everything they touch as parameters.
+#![allow(clippy::too_many_arguments)]
+
+pub mod accessibility;
+pub mod analysis;
+pub mod annotate;
+pub mod board;
+pub mod cli;
+pub mod consts;
+pub mod editor;
+pub mod engine;
+pub mod game;
+pub mod keyboard;
+pub mod marks;
+pub mod movelist;
+pub mod pgn;
+pub mod piece;
+pub mod premove;
+pub mod review;
+pub mod rules;
+pub mod san;
+pub mod search;
+pub mod session;
+pub mod settings;
+pub mod setup;
+pub mod states;
+pub mod stats;
+pub mod tablebase;
+pub mod theme;
+pub mod timer;
+pub mod tournament;
+pub mod tray;
+pub mod uci;
+
+mod tests;
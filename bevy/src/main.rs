@@ -1,30 +1,92 @@
 use bevy::prelude::*;
 
-mod board;
-use board::BoardPlugin;
+use chess::accessibility::{AccessibilitySettings, ScreenReaderPlugin};
+use chess::analysis::{AnalysisPlugin, AnalysisSettings};
+use chess::board::{BoardPlugin, BoardSettings};
+use chess::cli::{self, Args};
+use chess::consts::{MIN_SQUARE_SIZE, PANEL_WIDTH};
+use chess::editor::EditorPlugin;
+use chess::engine::EnginePlugin;
+use chess::game::GamePlugin;
+use chess::keyboard::KeyboardPlugin;
+use chess::marks::MarksPlugin;
+use chess::movelist::MoveListPlugin;
+use chess::piece::{AnimationSettings, PiecePlugin};
+use chess::premove::PremovePlugin;
+use chess::review::ReviewPlugin;
+use chess::session::SessionPlugin;
+use chess::settings::{SettingsFile, SettingsPlugin};
+use chess::setup::SetupPlugin;
+use chess::states::StatesPlugin;
+use chess::theme::ThemePlugin;
+use chess::timer::TimerPlugin;
+use chess::tray::TrayPlugin;
 
-mod piece;
-use piece::PiecePlugin;
+fn main() {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}");
+            eprintln!("{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
 
-mod consts;
-use consts::{HEIGHT, WIDTH};
+    let (file, settings) = SettingsFile::load();
+    let mut app = App::new();
 
-fn main() {
-    App::new()
+    app
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
-                resolution: (WIDTH, HEIGHT).into(),
-                resizable: false,
+                resolution: settings.window.into(),
+                resize_constraints: WindowResizeConstraints {
+                    min_width: 8. * MIN_SQUARE_SIZE + PANEL_WIDTH,
+                    min_height: 8. * MIN_SQUARE_SIZE,
+                    ..default()
+                },
                 ..default()
             }),
             ..default()
         }))
+        .insert_resource(AnalysisSettings { enabled: false, multipv: args.multipv })
+        .insert_resource(AnimationSettings { duration: args.animation.unwrap_or(settings.animation()) })
+        .insert_resource(BoardSettings { coordinates: settings.coordinates, auto_queen: settings.auto_queen, premoves: settings.premoves })
+        .insert_resource(args.game_config(&settings))
+        .insert_resource(settings.clone())
+        .insert_resource(file)
+        .add_plugins(SettingsPlugin)
+        .insert_resource(AccessibilitySettings { enabled: args.accessible })
+        .add_plugins(ThemePlugin)
+        .add_plugins(GamePlugin)
         .add_plugins(BoardPlugin)
         .add_plugins(PiecePlugin)
-        .add_systems(Startup, init)
-        .run();
+        .add_plugins(EnginePlugin)
+        .add_plugins(AnalysisPlugin)
+        .add_plugins(MoveListPlugin)
+        .add_plugins(ReviewPlugin)
+        .add_plugins(TimerPlugin)
+        .add_plugins(TrayPlugin)
+        .add_plugins(MarksPlugin)
+        .add_plugins(SetupPlugin)
+        .add_plugins(EditorPlugin)
+        .add_plugins(KeyboardPlugin)
+        .add_plugins(PremovePlugin)
+        .add_plugins(ScreenReaderPlugin)
+        .add_plugins(SessionPlugin)
+        .add_plugins(StatesPlugin)
+        .add_systems(Startup, init);
+
+    if let Some(engine) = args.engine.clone().or_else(|| settings.engine_settings()) {
+        app.insert_resource(engine);
+    }
+
+    app.run();
 }
 
 fn init(mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
+    // The panel keeps to the right edge, the board is centred in the space left of it.
+    let mut camera = Camera2dBundle::default();
+    camera.transform.translation.x = PANEL_WIDTH / 2.;
+
+    commands.spawn(camera);
 }
@@ -0,0 +1,286 @@
+//! Arrows and circled squares drawn with the right mouse button, kept for each position and
+//! written into PGN comments as `[%cal]` and `[%csl]` commands.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+
+use crate::board::{BoardView, Preview};
+use crate::consts::SQUARE_SIZE;
+use crate::game::Game;
+use crate::pgn::PgnGame;
+use crate::rules::{square_name, Position, Square};
+use crate::setup::NewGameEvent;
+use crate::states::in_game;
+
+const MARK_Z: f32 = 3.;
+const SHAFT_WIDTH: f32 = 0.18;
+const HEAD_LENGTH: f32 = 0.4;
+const HEAD_WIDTH: f32 = 0.45;
+
+pub struct MarksPlugin;
+
+impl Plugin for MarksPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Marks>()
+            .add_systems(Startup, create_mark_shapes)
+            .add_systems(Update, (forget_marks, draw_marks.run_if(in_game)).chain())
+            .add_systems(Update, show_marks.after(draw_marks));
+    }
+}
+
+/// Colours picked with the modifier keys held while drawing, named by their PGN letter.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum MarkColor {
+    Green,
+    Red,
+    Yellow,
+    Blue,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Mark {
+    Circle(MarkColor, Square),
+    Arrow(MarkColor, Square, Square),
+}
+
+/// Marks on every position they were drawn on, by position key.
+#[derive(Resource, Default)]
+pub struct Marks(HashMap<u64, Vec<Mark>>);
+
+#[derive(Resource)]
+struct MarkShapes {
+    ring: Mesh2dHandle,
+    shaft: Mesh2dHandle,
+    head: Mesh2dHandle,
+    colors: [Handle<ColorMaterial>; 4],
+}
+
+#[derive(Component)]
+struct MarkShape;
+
+impl MarkColor {
+    pub fn letter(self) -> char {
+        match self {
+            Self::Green => 'G',
+            Self::Red => 'R',
+            Self::Yellow => 'Y',
+            Self::Blue => 'B',
+        }
+    }
+
+    fn rgba(self) -> Color {
+        match self {
+            Self::Green => Color::srgba(0.08, 0.47, 0.11, 0.8),
+            Self::Red => Color::srgba(0.53, 0.13, 0.13, 0.8),
+            Self::Yellow => Color::srgba(0.9, 0.68, 0., 0.8),
+            Self::Blue => Color::srgba(0., 0.19, 0.53, 0.8),
+        }
+    }
+
+    /// Shift for red, Alt for blue, Ctrl for yellow, green otherwise.
+    fn from_keys(keys: &ButtonInput<KeyCode>) -> Self {
+        if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            Self::Red
+        } else if keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) {
+            Self::Blue
+        } else if keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+            Self::Yellow
+        } else {
+            Self::Green
+        }
+    }
+}
+
+impl Marks {
+    pub fn get(&self, position: &Position) -> &[Mark] {
+        self.0.get(&position.key()).map_or(&[], Vec::as_slice)
+    }
+
+    /// Adds the mark, or takes it away if it is already there; the same shape in another colour is replaced.
+    pub fn toggle(&mut self, position: &Position, mark: Mark) {
+        let marks = self.0.entry(position.key()).or_default();
+        let same_place = |m: &Mark| match (m, mark) {
+            (Mark::Circle(_, a), Mark::Circle(_, b)) => *a == b,
+            (Mark::Arrow(_, a, x), Mark::Arrow(_, b, y)) => (*a, *x) == (b, y),
+            _ => false,
+        };
+
+        match marks.iter().position(same_place) {
+            Some(i) if marks[i] == mark => {
+                marks.remove(i);
+            }
+            Some(i) => marks[i] = mark,
+            None => marks.push(mark),
+        }
+    }
+
+    pub fn clear(&mut self, position: &Position) {
+        self.0.remove(&position.key());
+    }
+
+    /// `[%csl]` and `[%cal]` commands for the marks on `position`, empty if there are none.
+    pub fn commands(&self, position: &Position) -> String {
+        let mut circles = Vec::new();
+        let mut arrows = Vec::new();
+
+        for mark in self.get(position) {
+            match *mark {
+                Mark::Circle(color, sq) => circles.push(format!("{}{}", color.letter(), square_name(sq))),
+                Mark::Arrow(color, from, to) => arrows.push(format!("{}{}{}", color.letter(), square_name(from), square_name(to))),
+            }
+        }
+
+        let mut text = String::new();
+        if !circles.is_empty() {
+            text.push_str(&format!("[%csl {}]", circles.join(",")));
+        }
+        if !arrows.is_empty() {
+            text.push_str(&format!("[%cal {}]", arrows.join(",")));
+        }
+        text
+    }
+
+    /// Writes the marks of each position into the comment of the move that led to it.
+    pub fn annotate(&self, pgn: &mut PgnGame) {
+        let mut pos = pgn.start.clone();
+        pgn.annotations.resize(pgn.moves.len(), default());
+
+        for (&mv, annotation) in pgn.moves.iter().zip(&mut pgn.annotations) {
+            pos.make_move(mv);
+
+            let commands = self.commands(&pos);
+            if commands.is_empty() {
+                continue;
+            }
+
+            annotation.comment = Some(match annotation.comment.take() {
+                Some(comment) => format!("{commands} {comment}"),
+                None => commands,
+            });
+        }
+    }
+}
+
+fn create_mark_shapes(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>, mut materials: ResMut<Assets<ColorMaterial>>) {
+    let head = Triangle2d::new(
+        Vec2::new(0., SQUARE_SIZE * HEAD_WIDTH / 2.),
+        Vec2::new(0., -SQUARE_SIZE * HEAD_WIDTH / 2.),
+        Vec2::new(SQUARE_SIZE * HEAD_LENGTH, 0.),
+    );
+
+    commands.insert_resource(MarkShapes {
+        ring: meshes.add(Annulus::new(SQUARE_SIZE * 0.43, SQUARE_SIZE * 0.49)).into(),
+        // One unit long, stretched to the arrow's length.
+        shaft: meshes.add(Rectangle::new(1., SQUARE_SIZE * SHAFT_WIDTH)).into(),
+        head: meshes.add(head).into(),
+        colors: [MarkColor::Green, MarkColor::Red, MarkColor::Yellow, MarkColor::Blue].map(|c| materials.add(c.rgba())),
+    });
+}
+
+/// Right-click circles a square, right-drag draws an arrow; a left click clears the position's marks.
+fn draw_marks(
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    game: Res<Game>,
+    preview: Res<Preview>,
+    view: Res<BoardView>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut marks: ResMut<Marks>,
+    mut start: Local<Option<Square>>,
+) {
+    let (camera, camera_transform) = cameras.single();
+    let world = windows.single().cursor_position().and_then(|c| camera.viewport_to_world_2d(camera_transform, c));
+    let square = world.and_then(|w| view.square_at(w));
+    let position = preview.0.as_ref().unwrap_or(&game.position);
+
+    if mouse.just_pressed(MouseButton::Left) && square.is_some() {
+        if !marks.get(position).is_empty() {
+            marks.clear(position);
+        }
+        return;
+    }
+
+    if mouse.just_pressed(MouseButton::Right) {
+        *start = square;
+    }
+
+    if !mouse.just_released(MouseButton::Right) {
+        return;
+    }
+
+    let (Some(from), Some(to)) = (start.take(), square) else { return; };
+    let color = MarkColor::from_keys(&keys);
+
+    marks.toggle(position, if from == to { Mark::Circle(color, to) } else { Mark::Arrow(color, from, to) });
+}
+
+/// Marks belong to the game they were drawn in.
+pub fn forget_marks(mut marks: ResMut<Marks>, mut new_game_ev: EventReader<NewGameEvent>) {
+    if new_game_ev.read().count() > 0 {
+        marks.0.clear();
+    }
+}
+
+fn show_marks(
+    mut commands: Commands,
+    game: Res<Game>,
+    preview: Res<Preview>,
+    view: Res<BoardView>,
+    marks: Res<Marks>,
+    shapes: Res<MarkShapes>,
+    shown: Query<Entity, With<MarkShape>>,
+) {
+    if !(game.is_changed() || preview.is_changed() || view.is_changed() || marks.is_changed()) {
+        return;
+    }
+
+    for entity in &shown {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let position = preview.0.as_ref().unwrap_or(&game.position);
+    let mut spawn = |mesh: &Mesh2dHandle, color: MarkColor, transform: Transform| {
+        commands.spawn((
+            MaterialMesh2dBundle { mesh: mesh.clone(), material: shapes.colors[color as usize].clone(), transform, ..default() },
+            MarkShape,
+        ));
+    };
+
+    for (i, &mark) in marks.get(position).iter().enumerate() {
+        // Later marks lie over earlier ones.
+        let z = MARK_Z + i as f32 * 0.01;
+
+        match mark {
+            Mark::Circle(color, sq) => {
+                spawn(&shapes.ring, color, Transform::from_translation(view.to_world(sq, z)).with_scale(Vec3::splat(view.scale())));
+            }
+            Mark::Arrow(color, from, to) => {
+                let from = view.to_world(from, z).truncate();
+                let to = view.to_world(to, z).truncate();
+                let direction = (to - from).normalize();
+                let rotation = Quat::from_rotation_z(direction.y.atan2(direction.x));
+                let head = HEAD_LENGTH * view.square_size;
+                // The shaft starts off the centre of its square and stops where the head begins.
+                let tail = from + direction * view.square_size * 0.25;
+                let neck = to - direction * head;
+
+                spawn(&shapes.shaft, color, Transform {
+                    translation: ((tail + neck) / 2.).extend(z),
+                    rotation,
+                    scale: Vec3::new(tail.distance(neck), view.scale(), 1.),
+                });
+                spawn(&shapes.head, color, Transform {
+                    translation: neck.extend(z),
+                    rotation,
+                    scale: Vec3::splat(view.scale()),
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+
+use crate::analysis::{setup_panel, text_style, PanelColumn, MOVE_COLOR, MOVE_HOVER_COLOR};
+use crate::board::Preview;
+use crate::game::Game;
+use crate::keyboard::MoveEntry;
+use crate::piece::PieceColor;
+use crate::rules::Position;
+use crate::san;
+use crate::states::in_game;
+
+const ROW_HEIGHT: f32 = 22.;
+const CURRENT_MOVE_COLOR: Color = Color::srgb(0.25, 0.4, 0.6);
+
+pub struct MoveListPlugin;
+
+impl Plugin for MoveListPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<MoveList>()
+            .add_systems(Startup, setup_move_list.after(setup_panel))
+            .add_systems(Update, browse_moves.run_if(in_game))
+            .add_systems(Update, update_move_list.after(browse_moves))
+            .add_systems(Update, scroll_move_list.after(update_move_list));
+    }
+}
+
+/// Where the board is in the game's history.
+#[derive(Resource, Default)]
+pub struct MoveList {
+    /// Moves played in the position under review, `None` while following the game.
+    pub viewing: Option<usize>,
+}
+
+/// Clipping frame around the list, hovered to scroll it.
+#[derive(Component)]
+struct MoveListView;
+
+#[derive(Component)]
+struct MoveListContent;
+
+/// Ply and SAN of White's or Black's move in a row.
+type Slot = Option<(usize, String)>;
+
+#[derive(Component)]
+struct MoveButton {
+    ply: usize,
+    current: bool,
+}
+
+impl MoveList {
+    /// Moves played in the position the board shows.
+    fn plies(&self, game: &Game) -> usize {
+        self.viewing.unwrap_or(game.moves.len())
+    }
+}
+
+/// Row of the move list a ply is written in; a game starting with Black leaves White's first slot empty.
+fn row_of(start: &Position, ply: usize) -> usize {
+    (ply + (start.turn == PieceColor::Black) as usize) / 2
+}
+
+pub fn setup_move_list(mut commands: Commands, column: Query<Entity, With<PanelColumn>>) {
+    let view = commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    flex_grow: 1.,
+                    flex_basis: Val::Px(0.),
+                    min_height: Val::Px(0.),
+                    overflow: Overflow::clip_y(),
+                    ..default()
+                },
+                ..default()
+            },
+            Interaction::default(),
+            MoveListView,
+        ))
+        .with_children(|view| {
+            view.spawn((
+                NodeBundle {
+                    style: Style { flex_direction: FlexDirection::Column, ..default() },
+                    ..default()
+                },
+                MoveListContent,
+            ));
+        })
+        .id();
+
+    commands.entity(column.single()).add_child(view);
+}
+
+fn browse_moves(
+    keys: Res<ButtonInput<KeyCode>>,
+    entry: Res<MoveEntry>,
+    game: Res<Game>,
+    mut list: ResMut<MoveList>,
+    mut preview: ResMut<Preview>,
+    mut buttons: Query<(&Interaction, &MoveButton, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    // The preview was cleared or replaced elsewhere: back to following the game.
+    if preview.is_changed() && list.viewing.is_some_and(|plies| preview.0.as_ref() != Some(&game.position_at(plies))) {
+        list.viewing = None;
+    }
+
+    let len = game.moves.len();
+    let plies = list.plies(&game);
+    let mut target = None;
+
+    // Shift and the arrows move the board cursor instead.
+    let stepping = !(entry.focused || keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]));
+    let pressed = |key| stepping && keys.just_pressed(key);
+
+    if pressed(KeyCode::ArrowLeft) {
+        target = Some(plies.saturating_sub(1));
+    } else if pressed(KeyCode::ArrowRight) {
+        target = Some(plies + 1);
+    } else if pressed(KeyCode::Home) {
+        target = Some(0);
+    } else if pressed(KeyCode::End) {
+        target = Some(len);
+    }
+
+    for (interaction, button, mut color) in &mut buttons {
+        match *interaction {
+            Interaction::Pressed => target = Some(button.ply + 1),
+            Interaction::Hovered => *color = MOVE_HOVER_COLOR.into(),
+            Interaction::None => *color = if button.current { CURRENT_MOVE_COLOR } else { MOVE_COLOR }.into(),
+        }
+    }
+
+    let Some(target) = target.filter(|&t| t != plies) else { return; };
+
+    // Reviewing shows the position without touching the game; the last move goes back to it.
+    if target >= len {
+        list.viewing = None;
+        if preview.0.is_some() {
+            preview.0 = None;
+        }
+    } else {
+        list.viewing = Some(target);
+        preview.0 = Some(game.position_at(target));
+    }
+}
+
+fn update_move_list(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    game: Res<Game>,
+    list: Res<MoveList>,
+    content: Query<Entity, With<MoveListContent>>,
+) {
+    if !(game.is_changed() || list.is_changed()) {
+        return;
+    }
+
+    let content = content.single();
+    commands.entity(content).despawn_descendants();
+
+    let current = list.plies(&game).checked_sub(1);
+    let mut rows: Vec<(String, [Slot; 2])> = Vec::new();
+    let mut pos = game.start.clone();
+
+    for (ply, &mv) in game.moves.iter().enumerate() {
+        let slot = (pos.turn == PieceColor::Black) as usize;
+
+        if slot == 0 || rows.is_empty() {
+            rows.push((format!("{}.", pos.fullmove_number), [None, None]));
+        }
+
+        rows.last_mut().unwrap().1[slot] = Some((ply, san::san(&pos, mv)));
+        pos.make_move(mv);
+    }
+
+    commands.entity(content).with_children(|parent| {
+        for (number, moves) in rows {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        height: Val::Px(ROW_HEIGHT),
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(4.),
+                        flex_shrink: 0.,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn(TextBundle::from_section(number, text_style(&asset_server, 14.)).with_style(Style {
+                        width: Val::Px(36.),
+                        ..default()
+                    }));
+
+                    for slot in moves {
+                        let Some((ply, san)) = slot else {
+                            row.spawn(NodeBundle { style: Style { width: Val::Px(72.), ..default() }, ..default() });
+                            continue;
+                        };
+
+                        let current = current == Some(ply);
+
+                        row.spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(72.),
+                                    padding: UiRect::horizontal(Val::Px(3.)),
+                                    ..default()
+                                },
+                                background_color: if current { CURRENT_MOVE_COLOR } else { MOVE_COLOR }.into(),
+                                ..default()
+                            },
+                            MoveButton { ply, current },
+                        ))
+                        .with_children(|button| {
+                            button.spawn(TextBundle::from_section(san, text_style(&asset_server, 14.)));
+                        });
+                    }
+                });
+        }
+    });
+}
+
+/// Scrolls with the mouse wheel over the list, and to the current move whenever it changes.
+fn scroll_move_list(
+    game: Res<Game>,
+    list: Res<MoveList>,
+    mut wheel: EventReader<MouseWheel>,
+    view: Query<(&Node, &Interaction), With<MoveListView>>,
+    mut content: Query<&mut Style, With<MoveListContent>>,
+    mut scroll: Local<f32>,
+) {
+    let (node, interaction) = view.single();
+    let height = node.size().y;
+    let rows = game.moves.len().checked_sub(1).map_or(0, |last| row_of(&game.start, last) + 1);
+
+    for event in wheel.read() {
+        if *interaction == Interaction::None {
+            continue;
+        }
+
+        *scroll -= match event.unit {
+            MouseScrollUnit::Line => event.y * ROW_HEIGHT,
+            MouseScrollUnit::Pixel => event.y,
+        };
+    }
+
+    if game.is_changed() || list.is_changed() {
+        if let Some(ply) = list.plies(&game).checked_sub(1) {
+            let top = row_of(&game.start, ply) as f32 * ROW_HEIGHT;
+            *scroll = scroll.max(top + ROW_HEIGHT - height).min(top);
+        }
+    }
+
+    *scroll = scroll.clamp(0., (rows as f32 * ROW_HEIGHT - height).max(0.));
+
+    let mut style = content.single_mut();
+    if style.top != Val::Px(-*scroll) {
+        style.top = Val::Px(-*scroll);
+    }
+}
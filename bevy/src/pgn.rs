@@ -0,0 +1,255 @@
+use crate::piece::PieceColor;
+use crate::rules::{Move, ParseError, Position, START_FEN};
+use crate::san;
+
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+const LINE_WIDTH: usize = 80;
+
+/// Glyphs written after a move in place of their numeric annotation.
+const GLYPHS: [(&str, u8); 6] = [("!!", 3), ("??", 4), ("!?", 5), ("?!", 6), ("!", 1), ("?", 2)];
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Annotation {
+    pub nags: Vec<u8>,
+    pub comment: Option<String>,
+}
+
+/// A game's tags and mainline; `annotations[i]` belongs to `moves[i]` and may be missing.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub start: Position,
+    pub moves: Vec<Move>,
+    pub annotations: Vec<Annotation>,
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn new(start: Position) -> Self {
+        Self {
+            tags: Vec::new(),
+            start,
+            moves: Vec::new(),
+            annotations: Vec::new(),
+            result: String::from("*"),
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+
+        match self.tags.iter_mut().find(|(n, _)| n == name) {
+            Some(tag) => tag.1 = value,
+            None => self.tags.push((name.to_string(), value)),
+        }
+    }
+}
+
+/// Export format: the seven tag roster first, then the rest, then the wrapped movetext.
+pub fn write(game: &PgnGame) -> String {
+    let mut tags: Vec<(String, String)> = SEVEN_TAG_ROSTER
+        .iter()
+        .map(|&name| {
+            let value = match name {
+                "Result" => game.result.as_str(),
+                "Date" => game.tag(name).unwrap_or("????.??.??"),
+                _ => game.tag(name).unwrap_or("?"),
+            };
+            (name.to_string(), value.to_string())
+        })
+        .collect();
+
+    let fen = game.start.fen();
+    if fen != START_FEN {
+        tags.push((String::from("SetUp"), String::from("1")));
+        tags.push((String::from("FEN"), fen));
+    }
+
+    for (name, value) in &game.tags {
+        if !tags.iter().any(|(n, _)| n == name) {
+            tags.push((name.clone(), value.clone()));
+        }
+    }
+
+    let mut out = String::new();
+    for (name, value) in tags {
+        out.push_str(&format!("[{name} \"{}\"]\n", value.replace('\\', "\\\\").replace('"', "\\\"")));
+    }
+    out.push('\n');
+
+    let mut tokens = Vec::new();
+    let mut pos = game.start.clone();
+    let mut resume = true;
+
+    for (i, &mv) in game.moves.iter().enumerate() {
+        if pos.turn == PieceColor::White || resume {
+            tokens.push(san::move_number(&pos));
+        }
+
+        tokens.push(san::san(&pos, mv));
+        resume = false;
+
+        if let Some(annotation) = game.annotations.get(i) {
+            tokens.extend(annotation.nags.iter().map(|nag| format!("${nag}")));
+
+            if let Some(comment) = &annotation.comment {
+                tokens.push(format!("{{{}}}", comment.replace('}', ")")));
+                resume = true;
+            }
+        }
+
+        pos.make_move(mv);
+    }
+
+    tokens.push(game.result.clone());
+
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + 1 + token.len() > LINE_WIDTH {
+            out.push_str(&line);
+            out.push('\n');
+            line.clear();
+        }
+
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    out.push_str(&line);
+    out.push('\n');
+
+    out
+}
+
+/// Every game in `text`. Variations are skipped; comments and NAGs attach to the move before them.
+pub fn read(text: &str) -> Result<Vec<PgnGame>, ParseError> {
+    let mut games = Vec::new();
+    let mut tags: Vec<(String, String)> = Vec::new();
+    let mut game: Option<(PgnGame, Position)> = None;
+    let mut chars = text.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            '[' => {
+                if let Some((done, _)) = game.take() {
+                    games.push(done);
+                }
+
+                chars.next();
+                let tag: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                tags.push(parse_tag(&tag)?);
+            }
+            '{' => {
+                chars.next();
+                let comment: String = chars.by_ref().take_while(|&c| c != '}').collect();
+
+                if let Some(annotation) = game.as_mut().and_then(|(g, _)| g.annotations.last_mut()) {
+                    annotation.comment = Some(comment.trim().to_string());
+                }
+            }
+            ';' | '%' => {
+                chars.by_ref().take_while(|&c| c != '\n').for_each(drop);
+            }
+            '(' => {
+                let mut depth = 0;
+
+                for c in chars.by_ref() {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => {}
+                    }
+
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "[]{}();".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+
+                if word.is_empty() {
+                    return Err(ParseError(format!("unexpected {c:?}")));
+                }
+
+                let (current, pos) = match &mut game {
+                    Some(game) => game,
+                    None => {
+                        let start = match tags.iter().find(|(n, _)| n == "FEN") {
+                            Some((_, fen)) => Position::from_fen(fen)?,
+                            None => Position::default(),
+                        };
+                        let mut fresh = PgnGame::new(start.clone());
+                        fresh.tags = std::mem::take(&mut tags);
+                        game.insert((fresh, start))
+                    }
+                };
+
+                if matches!(word.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*") {
+                    current.result = word;
+                    games.extend(game.take().map(|(g, _)| g));
+                    continue;
+                }
+
+                if let Some(nag) = word.strip_prefix('$') {
+                    let nag = nag.parse().map_err(|_| ParseError(format!("bad NAG {word}")))?;
+                    if let Some(annotation) = current.annotations.last_mut() {
+                        annotation.nags.push(nag);
+                    }
+                    continue;
+                }
+
+                let san = word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                if san.is_empty() {
+                    continue;
+                }
+
+                let mut annotation = Annotation::default();
+                let bare = san.trim_end_matches(['!', '?']);
+                if let Some(&(_, nag)) = GLYPHS.iter().find(|(glyph, _)| san[bare.len()..] == **glyph) {
+                    annotation.nags.push(nag);
+                }
+
+                let mv = san::parse(pos, bare).ok_or_else(|| ParseError(format!("illegal move {san}")))?;
+                pos.make_move(mv);
+                current.moves.push(mv);
+                current.annotations.push(annotation);
+            }
+        }
+    }
+
+    games.extend(game.map(|(g, _)| g));
+    Ok(games)
+}
+
+fn parse_tag(tag: &str) -> Result<(String, String), ParseError> {
+    let (name, rest) = tag.trim().split_once(char::is_whitespace).ok_or_else(|| ParseError(format!("bad tag [{tag}]")))?;
+    let value = rest.trim().strip_prefix('"').and_then(|v| v.strip_suffix('"'));
+    let value = value.ok_or_else(|| ParseError(format!("bad tag [{tag}]")))?;
+
+    Ok((name.to_string(), value.replace("\\\"", "\"").replace("\\\\", "\\")))
+}
+
+/// Start positions of an EPD file, one per line; operations after the fourth field are ignored.
+pub fn read_epd(text: &str) -> Result<Vec<Position>, ParseError> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| Position::from_fen(&line.split_whitespace().take(4).collect::<Vec<_>>().join(" ")))
+        .collect()
+}
@@ -1,7 +1,12 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::consts::{SQUARE_SIZE, OFFSET};
-use crate::board::Board;
+use crate::board::{Board, BoardView};
+use crate::game::Game;
+use crate::rules::Position;
+use crate::theme::{Appearance, PieceSet};
 
 pub struct PiecePlugin;
 
@@ -10,19 +15,51 @@ impl Plugin for PiecePlugin {
        app
            .add_event::<MovePieceEvent>()
            .add_event::<TakePieceEvent>()
+           .add_event::<PromotePieceEvent>()
+           .init_resource::<AnimationSettings>()
            .add_systems(Startup, create_pieces)
            .add_systems(Update, move_piece)
-           .add_systems(Update, take_piece); 
+           .add_systems(Update, take_piece)
+           .add_systems(Update, promote_piece)
+           .add_systems(Update, animate_pieces.after(move_piece))
+           .add_systems(Update, fade_pieces.after(take_piece))
+           .add_systems(Update, restyle_pieces.after(promote_piece));
     }
 }
 
+/// How long a move takes to slide and a captured piece to fade; zero turns animation off.
+#[derive(Resource, Clone, Debug)]
+pub struct AnimationSettings {
+    pub duration: Duration,
+}
+
+/// Slide of a moving piece towards its new square.
+#[derive(Component)]
+pub struct Tween {
+    from: Vec3,
+    to: Vec3,
+    elapsed: f32,
+}
+
+/// Captured piece on its way out, no longer part of the board.
+#[derive(Component)]
+struct Fade {
+    elapsed: f32,
+}
+
+/// Moving pieces pass over the resting ones.
+const FLIGHT_Z: f32 = 2.;
+
 #[derive(Event)]
 pub struct MovePieceEvent(pub (Entity, (u8, u8)));
 
 #[derive(Event)]
 pub struct TakePieceEvent(pub Entity);
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Event)]
+pub struct PromotePieceEvent(pub (Entity, Kind));
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum Kind {
     Pawn,
     Knight,
@@ -32,12 +69,43 @@ pub enum Kind {
     King,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum PieceColor {
     Black,
     White,
 }
 
+impl Kind {
+    /// Lowercase English name, as in asset paths and spoken moves.
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Pawn => "pawn",
+            Kind::Knight => "knight",
+            Kind::Bishop => "bishop",
+            Kind::Rook => "rook",
+            Kind::Queen => "queen",
+            Kind::King => "king",
+        }
+    }
+}
+
+impl PieceColor {
+    pub fn other(self) -> Self {
+        match self {
+            PieceColor::Black => PieceColor::White,
+            PieceColor::White => PieceColor::Black,
+        }
+    }
+
+    /// Lowercase English name, like `Kind::name`.
+    pub fn name(self) -> &'static str {
+        match self {
+            PieceColor::White => "white",
+            PieceColor::Black => "black",
+        }
+    }
+}
+
 #[derive(Component, Copy, Clone, Eq, PartialEq)]
 pub struct Piece {
     pub kind: Kind,
@@ -50,69 +118,170 @@ impl Piece {
         Self { kind, team, loc }
     }
     
-    fn get_asset_path(&self) -> String {
-        let mut path = match self.team {
-            PieceColor::Black => String::from("black/"),
-            PieceColor::White => String::from("white/"),
-        };
-        
-        path.push_str(match self.kind {
-            Kind::Pawn => "pawn.png",
-            Kind::Knight => "knight.png",
-            Kind::Bishop => "bishop.png",
-            Kind::Rook => "rook.png",
-            Kind::Queen => "queen.png",
-            Kind::King => "king.png",          
-        });
-        
-        path
-    }
-
-    pub fn get_vec3(&self) -> Vec3 {
-        Vec3::new(OFFSET + self.loc.0 as f32 * SQUARE_SIZE, OFFSET + self.loc.1 as f32 * SQUARE_SIZE, 1.)
-    }
-}
-
-fn create_pieces(mut commands: Commands, asset_server: Res<AssetServer>, mut board: ResMut<Board>) {
-    use Kind::*;
-    let pieces = [Rook, Knight, Bishop, King, Queen, Bishop, Knight, Rook];
-    
-    let create_spritebundle = |piece: &Piece| -> SpriteBundle {
+    pub fn get_asset_path(&self, set: &PieceSet) -> String {
+        set.path(self.team, self.kind)
+    }
+
+    pub fn get_vec3(&self, view: &BoardView) -> Vec3 {
+        view.to_world(self.loc, 1.)
+    }
+
+    pub fn sprite_bundle(&self, asset_server: &AssetServer, view: &BoardView, set: &PieceSet) -> SpriteBundle {
         SpriteBundle {
-            texture: asset_server.load(piece.get_asset_path()),
+            texture: asset_server.load(self.get_asset_path(set)),
             transform: Transform {
-                translation: piece.get_vec3(),
-                scale: Vec3::new(0.4, 0.35, 1.0),
+                translation: self.get_vec3(view),
+                scale: piece_scale(view, set),
                 ..default()
             },
             ..default()
         }
-    };
+    }
+}
+
+/// Sprite scale that makes the piece images fit a square.
+pub fn piece_scale(view: &BoardView, set: &PieceSet) -> Vec3 {
+    (set.scale * view.scale()).extend(1.0)
+}
+
+fn create_pieces(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    view: Res<BoardView>,
+    mut board: ResMut<Board>,
+    game: Res<Game>,
+    appearance: Res<Appearance>,
+) {
+    spawn_pieces(&mut commands, &asset_server, &view, appearance.pieces(), &mut board, &game.position);
+}
 
-    for i in 0..8 {
-        let w_pawn = Piece::new(PieceColor::White, Kind::Pawn, (i, 1));
-        let b_pawn = Piece::new(PieceColor::Black, Kind::Pawn, (i, 6));
-        let white = Piece::new(PieceColor::White, pieces[i as usize], (i, 0));
-        let black = Piece::new(PieceColor::Black, pieces[i as usize], (i, 7));
-        
-        board.add_entity(1, i as usize, commands.spawn((create_spritebundle(&w_pawn), w_pawn)).id());
-        board.add_entity(6, i as usize, commands.spawn((create_spritebundle(&b_pawn), b_pawn)).id());
-        board.add_entity(0, i as usize, commands.spawn((create_spritebundle(&white), white)).id());
-        board.add_entity(7, i as usize, commands.spawn((create_spritebundle(&black), black)).id());
+/// Puts a sprite on every piece of `position` and records it on the board.
+pub fn spawn_pieces(commands: &mut Commands, asset_server: &AssetServer, view: &BoardView, set: &PieceSet, board: &mut Board, position: &Position) {
+    for (loc, team, kind) in position.pieces() {
+        let piece = Piece::new(team, kind, loc);
+
+        board.add_entity(loc.1 as usize, loc.0 as usize, commands.spawn((piece.sprite_bundle(asset_server, view, set), piece)).id());
+    }
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        Self { duration: Duration::from_millis(200) }
     }
 }
 
-fn move_piece(mut piece_move_ev: EventReader<MovePieceEvent>, mut query: Query<(&mut Piece, &mut Transform)>) {
+impl AnimationSettings {
+    /// Share of the animation done after `elapsed` seconds.
+    fn progress(&self, elapsed: f32) -> f32 {
+        (elapsed / self.duration.as_secs_f32()).min(1.)
+    }
+}
+
+/// Slow at both ends, fast in the middle.
+fn ease_in_out(t: f32) -> f32 {
+    if t < 0.5 { 4. * t * t * t } else { 1. - (2. - 2. * t).powi(3) / 2. }
+}
+
+/// Whether a piece is still sliding; nothing new is picked up until it is done.
+pub fn pieces_moving(tweens: Query<(), With<Tween>>) -> bool {
+    !tweens.is_empty()
+}
+
+fn move_piece(
+    mut commands: Commands,
+    settings: Res<AnimationSettings>,
+    view: Res<BoardView>,
+    mut piece_move_ev: EventReader<MovePieceEvent>,
+    mut query: Query<(&mut Piece, &mut Transform)>,
+) {
     for ev in piece_move_ev.read() {
         if let Ok((mut piece, mut transform)) = query.get_mut(ev.0.0) {
             piece.loc = ev.0.1;
-            transform.translation = piece.get_vec3();
+
+            if settings.duration.is_zero() {
+                transform.translation = piece.get_vec3(&view);
+            } else {
+                // Starts from wherever the piece is, mid-slide or dropped off a drag.
+                commands.entity(ev.0.0).insert(Tween { from: transform.translation, to: piece.get_vec3(&view), elapsed: 0. });
+            }
+        }
+    }
+}
+
+fn animate_pieces(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<AnimationSettings>,
+    mut query: Query<(Entity, &mut Transform, &mut Tween)>,
+) {
+    for (entity, mut transform, mut tween) in &mut query {
+        tween.elapsed += time.delta_seconds();
+        let t = settings.progress(tween.elapsed);
+
+        if t < 1. {
+            transform.translation = tween.from.lerp(tween.to, ease_in_out(t)).truncate().extend(FLIGHT_Z);
+        } else {
+            transform.translation = tween.to;
+            commands.entity(entity).remove::<Tween>();
         }
     }
 }
 
-fn take_piece(mut commands: Commands, mut piece_take_ev: EventReader<TakePieceEvent>) {
+fn take_piece(mut commands: Commands, settings: Res<AnimationSettings>, mut piece_take_ev: EventReader<TakePieceEvent>) {
     for ev in piece_take_ev.read() {
-        commands.entity(ev.0).despawn_recursive();
+        if settings.duration.is_zero() {
+            commands.entity(ev.0).despawn_recursive();
+        } else {
+            commands.entity(ev.0).remove::<Piece>().insert(Fade { elapsed: 0. });
+        }
+    }
+}
+
+fn fade_pieces(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<AnimationSettings>,
+    mut query: Query<(Entity, &mut Sprite, &mut Fade)>,
+) {
+    for (entity, mut sprite, mut fade) in &mut query {
+        fade.elapsed += time.delta_seconds();
+        let t = settings.progress(fade.elapsed);
+
+        if t < 1. {
+            sprite.color.set_alpha(1. - ease_in_out(t));
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn promote_piece(
+    asset_server: Res<AssetServer>,
+    appearance: Res<Appearance>,
+    mut piece_promote_ev: EventReader<PromotePieceEvent>,
+    mut query: Query<(&mut Piece, &mut Handle<Image>)>,
+) {
+    for ev in piece_promote_ev.read() {
+        if let Ok((mut piece, mut texture)) = query.get_mut(ev.0.0) {
+            piece.kind = ev.0.1;
+            *texture = asset_server.load(piece.get_asset_path(appearance.pieces()));
+        }
+    }
+}
+
+/// Another piece set chosen: every piece on the board takes its image and size from it.
+fn restyle_pieces(
+    asset_server: Res<AssetServer>,
+    view: Res<BoardView>,
+    appearance: Res<Appearance>,
+    mut query: Query<(&Piece, &mut Handle<Image>, &mut Transform)>,
+) {
+    if !appearance.is_changed() || appearance.is_added() {
+        return;
+    }
+
+    for (piece, mut texture, mut transform) in &mut query {
+        *texture = asset_server.load(piece.get_asset_path(appearance.pieces()));
+        transform.scale = piece_scale(&view, appearance.pieces());
     }
 }
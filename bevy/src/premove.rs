@@ -0,0 +1,161 @@
+//! Moves queued on the board while the opponent is thinking, played the moment it is our turn
+//! again if they are legal then, and dropped if not.
+
+use bevy::prelude::*;
+
+use crate::board::play_move;
+use crate::game::{Game, PlayMoveEvent, Player, Players};
+use crate::piece::{Kind, PieceColor};
+use crate::rules::{Move, Position, Square};
+use crate::setup::NewGameEvent;
+use crate::states::AppState;
+
+const STRAIGHT: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const DIAGONAL: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+pub struct PremovePlugin;
+
+impl Plugin for PremovePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Premoves>()
+            .add_systems(Update, (cancel_premoves, play_premoves.after(cancel_premoves).before(play_move)).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// Queued moves, oldest first.
+#[derive(Resource, Default)]
+pub struct Premoves {
+    pub moves: Vec<Move>,
+}
+
+/// Squares a piece could ever move to from `from`, whatever stands in the way now: by the time
+/// it is our move the way may be clear, or a piece may have come to be taken.
+pub fn premove_targets(pos: &Position, color: PieceColor, kind: Kind, (f, r): Square) -> Vec<Square> {
+    let on_board = |(df, dr): (i8, i8)| {
+        let (f, r) = (f as i8 + df, r as i8 + dr);
+        ((0..8).contains(&f) && (0..8).contains(&r)).then_some((f as u8, r as u8))
+    };
+    let lines = |dirs: &[(i8, i8)]| -> Vec<Square> { dirs.iter().flat_map(|&(df, dr)| (1..8).filter_map(move |n| on_board((df * n, dr * n)))).collect() };
+
+    match kind {
+        Kind::Pawn => {
+            let (forward, start) = if color == PieceColor::White { (1, 1) } else { (-1, 6) };
+            let mut steps = vec![(0, forward), (-1, forward), (1, forward)];
+            if r == start {
+                steps.push((0, 2 * forward));
+            }
+            steps.into_iter().filter_map(on_board).collect()
+        }
+        Kind::Knight => [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)].into_iter().filter_map(on_board).collect(),
+        Kind::Bishop => lines(&DIAGONAL),
+        Kind::Rook => lines(&STRAIGHT),
+        Kind::Queen => lines(&[STRAIGHT, DIAGONAL].concat()),
+        Kind::King => {
+            let mut targets: Vec<Square> = STRAIGHT.into_iter().chain(DIAGONAL).filter_map(on_board).collect();
+            let back = if color == PieceColor::White { 0 } else { 7 };
+
+            if (f, r) == (4, back) {
+                for (king_side, file) in [(true, 6), (false, 2)] {
+                    if pos.castling.get(color, king_side) {
+                        targets.push((file, back));
+                    }
+                }
+            }
+            targets
+        }
+    }
+}
+
+impl Premoves {
+    /// The board as the queued moves would leave it, with the opponent standing still.
+    pub fn projected(&self, pos: &Position) -> Position {
+        let mut pos = pos.clone();
+
+        for mv in &self.moves {
+            let piece = pos.piece_at(mv.from).map(|(color, kind)| (color, mv.promotion.unwrap_or(kind)));
+
+            // A king going two files takes its rook along.
+            if piece.is_some_and(|(_, kind)| kind == Kind::King) && mv.from.0.abs_diff(mv.to.0) == 2 {
+                let (rook_from, rook_to) = if mv.to.0 > mv.from.0 { (7, 5) } else { (0, 3) };
+                let rook = pos.piece_at((rook_from, mv.from.1));
+                pos.set_piece((rook_from, mv.from.1), None);
+                pos.set_piece((rook_to, mv.from.1), rook);
+            }
+
+            pos.set_piece(mv.from, None);
+            pos.set_piece(mv.to, piece);
+        }
+
+        pos
+    }
+
+    /// Where the piece on `sq` now ends up once the queued moves are made.
+    pub fn destination(&self, sq: Square) -> Square {
+        self.moves.iter().fold(sq, |sq, mv| if mv.from == sq { mv.to } else { sq })
+    }
+
+    /// Where the piece that the queued moves bring to `sq` stands now.
+    pub fn origin(&self, sq: Square) -> Square {
+        self.moves.iter().rev().fold(sq, |sq, mv| if mv.to == sq { mv.from } else { sq })
+    }
+
+    /// Queues a move of `color`'s piece from `from` to `to` on the projected board, if that piece
+    /// could ever move so. Pawns reaching the last rank become queens.
+    pub fn queue(&mut self, pos: &Position, color: PieceColor, from: Square, to: Square) -> bool {
+        let projected = self.projected(pos);
+        let Some((c, kind)) = projected.piece_at(from) else { return false; };
+
+        if c != color || from == to || !premove_targets(&projected, color, kind, from).contains(&to) {
+            return false;
+        }
+
+        let last_rank = if color == PieceColor::White { 7 } else { 0 };
+        self.moves.push(if kind == Kind::Pawn && to.1 == last_rank { Move::promote(from, to, Kind::Queen) } else { Move::new(from, to) });
+        true
+    }
+
+    /// The oldest queued move if it is legal in `pos`. An illegal one drops the whole queue,
+    /// since the moves after it were planned on top of it.
+    pub fn next(&mut self, pos: &Position) -> Option<Move> {
+        let mv = *self.moves.first()?;
+
+        if pos.is_legal(mv) {
+            self.moves.remove(0);
+            Some(mv)
+        } else {
+            info!("premove {mv} is not legal, dropping {} queued", self.moves.len());
+            self.moves.clear();
+            None
+        }
+    }
+}
+
+/// A right click or Escape drops the queue, as do a takeback and a new game.
+fn cancel_premoves(
+    game: Res<Game>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut premoves: ResMut<Premoves>,
+    mut plies: Local<usize>,
+    mut new_game_ev: EventReader<NewGameEvent>,
+) {
+    let taken_back = game.moves.len() < *plies;
+    *plies = game.moves.len();
+
+    let cancel = mouse.just_pressed(MouseButton::Right) || keys.just_pressed(KeyCode::Escape) || taken_back || new_game_ev.read().count() > 0 || game.outcome().is_some();
+    if cancel && !premoves.moves.is_empty() {
+        premoves.moves.clear();
+    }
+}
+
+/// As soon as the opponent has moved, the next queued move is played.
+fn play_premoves(game: Res<Game>, players: Res<Players>, mut premoves: ResMut<Premoves>, mut play_move_wr: EventWriter<PlayMoveEvent>) {
+    if !game.is_changed() || premoves.moves.is_empty() || players.get(game.position.turn) != Player::Human {
+        return;
+    }
+
+    if let Some(mv) = premoves.next(&game.position) {
+        play_move_wr.send(PlayMoveEvent(mv));
+    }
+}
@@ -0,0 +1,290 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use bevy::utils::synccell::SyncCell;
+
+use crate::analysis::{text_style, PanelColumn, MOVE_COLOR, MOVE_HOVER_COLOR};
+use crate::annotate::{GameReview, ReviewProgress, ReviewThread};
+use crate::board::Preview;
+use crate::game::Game;
+use crate::marks::Marks;
+use crate::movelist::setup_move_list;
+use crate::pgn::PgnGame;
+use crate::piece::PieceColor;
+use crate::rules::{Move, Position};
+use crate::san;
+use crate::search::Limits;
+use crate::setup::GameConfig;
+
+const REVIEW_DEPTH: u32 = 6;
+const REVIEW_MOVETIME: Duration = Duration::from_millis(500);
+const MAX_LISTED: usize = 12;
+
+pub struct ReviewPlugin;
+
+impl Plugin for ReviewPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ReviewState>()
+            .add_systems(Startup, setup_review.after(setup_move_list))
+            .add_systems(Update, click_review_buttons)
+            .add_systems(Update, poll_review.after(click_review_buttons))
+            .add_systems(Update, update_review.after(poll_review))
+            .add_systems(Update, click_review_move);
+    }
+}
+
+/// Review of the finished game, for the moves in `moves`.
+#[derive(Resource, Default)]
+pub struct ReviewState {
+    moves: Vec<Move>,
+    thread: Option<SyncCell<ReviewThread>>,
+    evaluated: usize,
+    review: Option<GameReview>,
+    saved: Option<String>,
+}
+
+#[derive(Component, Copy, Clone, Eq, PartialEq)]
+enum ReviewButton {
+    Analyze,
+    Export,
+}
+
+#[derive(Component)]
+struct ReviewText;
+
+#[derive(Component)]
+struct ReviewMoves;
+
+/// Position in which a flagged move was played.
+#[derive(Component)]
+struct ReviewMove(Position);
+
+pub fn setup_review(mut commands: Commands, asset_server: Res<AssetServer>, column: Query<Entity, With<PanelColumn>>) {
+    let review = commands
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                flex_shrink: 0.,
+                row_gap: Val::Px(6.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|column| {
+            column.spawn((
+                NodeBundle {
+                    style: Style { flex_direction: FlexDirection::Column, row_gap: Val::Px(2.), ..default() },
+                    ..default()
+                },
+                ReviewMoves,
+            ));
+            column.spawn((TextBundle::from_section("", text_style(&asset_server, 14.)), ReviewText));
+
+            for (button, label) in [(ReviewButton::Analyze, "Analyze game"), (ReviewButton::Export, "Export PGN")] {
+                column
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                display: Display::None,
+                                padding: UiRect::axes(Val::Px(8.), Val::Px(4.)),
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            background_color: MOVE_COLOR.into(),
+                            ..default()
+                        },
+                        button,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(label, text_style(&asset_server, 16.)));
+                    });
+            }
+        })
+        .id();
+
+    commands.entity(column.single()).add_child(review);
+}
+
+fn click_review_buttons(
+    game: Res<Game>,
+    config: Res<GameConfig>,
+    marks: Res<Marks>,
+    mut state: ResMut<ReviewState>,
+    mut interactions: Query<(&Interaction, &ReviewButton, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    for (interaction, button, mut color) in &mut interactions {
+        match *interaction {
+            Interaction::Pressed => match button {
+                ReviewButton::Analyze => start_review(&game, &mut state),
+                ReviewButton::Export => export(&game, &config, &marks, &mut state),
+            },
+            Interaction::Hovered => *color = MOVE_HOVER_COLOR.into(),
+            Interaction::None => *color = MOVE_COLOR.into(),
+        }
+    }
+}
+
+/// Starts reviewing the finished game in the background, unless that is already done.
+pub fn start_review(game: &Game, state: &mut ReviewState) {
+    if game.outcome().is_none() || state.thread.is_some() || state.review.is_some() {
+        return;
+    }
+
+    let limits = Limits { depth: Some(REVIEW_DEPTH), movetime: Some(REVIEW_MOVETIME), ..default() };
+    let thread = ReviewThread::spawn(game.start.clone(), game.moves.clone(), limits);
+
+    *state = ReviewState { moves: game.moves.clone(), thread: Some(SyncCell::new(thread)), ..default() };
+}
+
+/// Writes the game to a PGN file, with the review's comments once there is one.
+pub fn export(game: &Game, config: &GameConfig, marks: &Marks, state: &mut ReviewState) {
+    let result = game.outcome().map_or("*", |outcome| outcome.result());
+
+    let mut pgn = match &state.review {
+        Some(review) => review.pgn(result),
+        None => {
+            let mut pgn = PgnGame::new(game.start.clone());
+            pgn.moves = game.moves.clone();
+            pgn.result = result.to_string();
+            pgn
+        }
+    };
+    pgn.set_tag("Event", if config.rated { "Rated game" } else { "Casual game" });
+    marks.annotate(&mut pgn);
+
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let path = format!("chess-review-{secs}.pgn");
+
+    state.saved = Some(match std::fs::write(&path, crate::pgn::write(&pgn)) {
+        Ok(()) => {
+            info!("game saved to {path}");
+            format!("Saved to {path}")
+        }
+        Err(err) => {
+            error!("failed to save {path}: {err}");
+            format!("Could not save: {err}")
+        }
+    });
+}
+
+fn poll_review(game: Res<Game>, mut state: ResMut<ReviewState>) {
+    // A new game makes the old review meaningless.
+    if game.is_changed() && game.moves != state.moves {
+        if state.thread.is_some() || state.review.is_some() || state.saved.is_some() {
+            *state = ReviewState::default();
+        }
+        return;
+    }
+
+    let Some(thread) = state.thread.as_mut() else { return; };
+
+    for progress in thread.get().poll() {
+        match progress {
+            ReviewProgress::Evaluated(done) => state.evaluated = done,
+            ReviewProgress::Finished(review) => {
+                state.review = Some(review);
+                state.thread = None;
+                break;
+            }
+        }
+    }
+}
+
+fn update_review(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    game: Res<Game>,
+    state: Res<ReviewState>,
+    mut buttons: Query<(&ReviewButton, &mut Style)>,
+    mut text: Query<&mut Text, With<ReviewText>>,
+    moves: Query<Entity, With<ReviewMoves>>,
+) {
+    if !(game.is_changed() || state.is_changed()) {
+        return;
+    }
+
+    let over = game.outcome().is_some();
+
+    for (button, mut style) in &mut buttons {
+        let shown = match button {
+            ReviewButton::Analyze => over && state.thread.is_none() && state.review.is_none(),
+            ReviewButton::Export => over,
+        };
+        style.display = if shown { Display::Flex } else { Display::None };
+    }
+
+    text.single_mut().sections[0].value = match (&state.thread, &state.review) {
+        (Some(_), _) => format!("Analysing position {} of {}", state.evaluated, state.moves.len() + 1),
+        (None, Some(review)) => {
+            let accuracy = |color| review.accuracy(color).map_or(String::from("-"), |a| format!("{a:.1}%"));
+            let mut text = format!(
+                "Accuracy: White {}, Black {}",
+                accuracy(PieceColor::White),
+                accuracy(PieceColor::Black),
+            );
+
+            if let Some(saved) = &state.saved {
+                text.push('\n');
+                text.push_str(saved);
+            }
+            text
+        }
+        (None, None) => state.saved.clone().unwrap_or_default(),
+    };
+
+    let container = moves.single();
+    commands.entity(container).despawn_descendants();
+
+    let Some(review) = &state.review else { return; };
+
+    let mut pos = review.start.clone();
+    let mut flagged = Vec::new();
+
+    for (mv, eval) in review.moves.iter().zip(&review.evals) {
+        if let (Some(judgement), Some(best)) = (mv.judgement, eval.best) {
+            let label = format!(
+                "{} {}{} {}, best {}",
+                san::move_number(&pos),
+                san::san(&pos, mv.played),
+                judgement.glyph(),
+                judgement.name(),
+                san::san(&pos, best),
+            );
+            flagged.push((label, pos.clone()));
+        }
+
+        pos.make_move(mv.played);
+    }
+
+    // The list stays short enough to leave the move list above it some room.
+    commands.entity(container).with_children(|parent| {
+        for (label, before) in flagged.into_iter().take(MAX_LISTED) {
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style { padding: UiRect::horizontal(Val::Px(3.)), ..default() },
+                        background_color: MOVE_COLOR.into(),
+                        ..default()
+                    },
+                    ReviewMove(before),
+                ))
+                .with_children(|button| {
+                    button.spawn(TextBundle::from_section(label, text_style(&asset_server, 14.)));
+                });
+        }
+    });
+}
+
+fn click_review_move(
+    mut preview: ResMut<Preview>,
+    mut interactions: Query<(&Interaction, &ReviewMove, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    for (interaction, review_move, mut color) in &mut interactions {
+        match *interaction {
+            Interaction::Pressed => preview.0 = Some(review_move.0.clone()),
+            Interaction::Hovered => *color = MOVE_HOVER_COLOR.into(),
+            Interaction::None => *color = MOVE_COLOR.into(),
+        }
+    }
+}
@@ -0,0 +1,721 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use crate::piece::{Kind, PieceColor};
+
+/// (file, rank) with (0, 0) being a1, the same layout `Piece::loc` uses.
+pub type Square = (u8, u8);
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+const KNIGHT_STEPS: [(i8, i8); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_STEPS: [(i8, i8); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const ROOK_DIRS: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_DIRS: [(i8, i8); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+const PROMOTIONS: [Kind; 4] = [Kind::Queen, Kind::Rook, Kind::Bishop, Kind::Knight];
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    pub promotion: Option<Kind>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(pub String);
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Castling {
+    pub white_king: bool,
+    pub white_queen: bool,
+    pub black_king: bool,
+    pub black_queen: bool,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    Checkmate(PieceColor),
+    Stalemate,
+    InsufficientMaterial,
+    FiftyMoves,
+    Repetition,
+    /// The other side ran out of time.
+    Timeout(PieceColor),
+    /// A side ran out of time, but the other could never have mated it.
+    TimeoutVsInsufficientMaterial,
+    /// The other side resigned.
+    Resignation(PieceColor),
+    /// Both sides agreed to a draw.
+    Agreement,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Position {
+    squares: [Option<(PieceColor, Kind)>; 64],
+    pub turn: PieceColor,
+    pub castling: Castling,
+    pub en_passant: Option<Square>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+}
+
+fn idx(sq: Square) -> usize {
+    8 * sq.1 as usize + sq.0 as usize
+}
+
+fn step(sq: Square, (df, dr): (i8, i8)) -> Option<Square> {
+    let (f, r) = (sq.0 as i8 + df, sq.1 as i8 + dr);
+
+    ((0..8).contains(&f) && (0..8).contains(&r)).then_some((f as u8, r as u8))
+}
+
+fn forward(color: PieceColor) -> i8 {
+    match color {
+        PieceColor::White => 1,
+        PieceColor::Black => -1,
+    }
+}
+
+fn back_rank(color: PieceColor) -> u8 {
+    match color {
+        PieceColor::White => 0,
+        PieceColor::Black => 7,
+    }
+}
+
+pub fn square_name(sq: Square) -> String {
+    format!("{}{}", (b'a' + sq.0) as char, sq.1 + 1)
+}
+
+pub fn parse_square(s: &str) -> Option<Square> {
+    let &[f, r] = s.as_bytes() else { return None; };
+
+    ((b'a'..=b'h').contains(&f) && (b'1'..=b'8').contains(&r)).then(|| (f - b'a', r - b'1'))
+}
+
+pub fn kind_from_char(c: char) -> Option<Kind> {
+    match c.to_ascii_lowercase() {
+        'p' => Some(Kind::Pawn),
+        'n' => Some(Kind::Knight),
+        'b' => Some(Kind::Bishop),
+        'r' => Some(Kind::Rook),
+        'q' => Some(Kind::Queen),
+        'k' => Some(Kind::King),
+        _ => None,
+    }
+}
+
+pub fn kind_char(kind: Kind) -> char {
+    match kind {
+        Kind::Pawn => 'p',
+        Kind::Knight => 'n',
+        Kind::Bishop => 'b',
+        Kind::Rook => 'r',
+        Kind::Queen => 'q',
+        Kind::King => 'k',
+    }
+}
+
+impl Move {
+    pub fn new(from: Square, to: Square) -> Self {
+        Self { from, to, promotion: None }
+    }
+
+    pub fn promote(from: Square, to: Square, kind: Kind) -> Self {
+        Self { from, to, promotion: Some(kind) }
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", square_name(self.from), square_name(self.to))?;
+
+        if let Some(kind) = self.promotion {
+            write!(f, "{}", kind_char(kind))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Move {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseError(format!("invalid move `{s}`"));
+
+        if !s.is_ascii() || !(4..=5).contains(&s.len()) {
+            return Err(err());
+        }
+
+        let from = parse_square(&s[0..2]).ok_or_else(err)?;
+        let to = parse_square(&s[2..4]).ok_or_else(err)?;
+        let promotion = match s[4..].chars().next() {
+            Some(c) => Some(kind_from_char(c).filter(|k| PROMOTIONS.contains(k)).ok_or_else(err)?),
+            None => None,
+        };
+
+        Ok(Self { from, to, promotion })
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Outcome {
+    /// Result as written in PGN.
+    pub fn result(self) -> &'static str {
+        match self {
+            Self::Checkmate(PieceColor::White) | Self::Timeout(PieceColor::White) | Self::Resignation(PieceColor::White) => "1-0",
+            Self::Checkmate(PieceColor::Black) | Self::Timeout(PieceColor::Black) | Self::Resignation(PieceColor::Black) => "0-1",
+            _ => "1/2-1/2",
+        }
+    }
+}
+
+impl Castling {
+    pub fn get(&self, color: PieceColor, king_side: bool) -> bool {
+        match (color, king_side) {
+            (PieceColor::White, true) => self.white_king,
+            (PieceColor::White, false) => self.white_queen,
+            (PieceColor::Black, true) => self.black_king,
+            (PieceColor::Black, false) => self.black_queen,
+        }
+    }
+
+    pub fn set(&mut self, color: PieceColor, king_side: bool, value: bool) {
+        match (color, king_side) {
+            (PieceColor::White, true) => self.white_king = value,
+            (PieceColor::White, false) => self.white_queen = value,
+            (PieceColor::Black, true) => self.black_king = value,
+            (PieceColor::Black, false) => self.black_queen = value,
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::from_fen(START_FEN).unwrap()
+    }
+}
+
+impl Position {
+    pub fn empty() -> Self {
+        Self {
+            squares: [None; 64],
+            turn: PieceColor::White,
+            castling: Castling::default(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Self, ParseError> {
+        let err = |what: &str| ParseError(format!("invalid fen `{fen}`: {what}"));
+        let mut fields = fen.split_whitespace();
+        let mut pos = Self::empty();
+
+        let placement = fields.next().ok_or_else(|| err("missing placement"))?;
+        let ranks: Vec<&str> = placement.split('/').collect();
+
+        if ranks.len() != 8 {
+            return Err(err("expected 8 ranks"));
+        }
+
+        for (i, rank) in ranks.iter().enumerate() {
+            let r = 7 - i as u8;
+            let mut f = 0u8;
+
+            for c in rank.chars() {
+                if let Some(n) = c.to_digit(10) {
+                    if !(1..=8).contains(&n) {
+                        return Err(err("bad empty-square count"));
+                    }
+                    f = f.checked_add(n as u8).filter(|&f| f <= 8).ok_or_else(|| err("rank too long"))?;
+                    continue;
+                }
+
+                let kind = kind_from_char(c).ok_or_else(|| err("unknown piece"))?;
+                let color = if c.is_ascii_uppercase() { PieceColor::White } else { PieceColor::Black };
+
+                if f > 7 {
+                    return Err(err("rank too long"));
+                }
+
+                pos.squares[idx((f, r))] = Some((color, kind));
+                f += 1;
+            }
+
+            if f != 8 {
+                return Err(err("rank has wrong length"));
+            }
+        }
+
+        pos.turn = match fields.next().unwrap_or("w") {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            _ => return Err(err("bad side to move")),
+        };
+
+        for c in fields.next().unwrap_or("-").chars() {
+            match c {
+                'K' => pos.castling.white_king = true,
+                'Q' => pos.castling.white_queen = true,
+                'k' => pos.castling.black_king = true,
+                'q' => pos.castling.black_queen = true,
+                '-' => {}
+                _ => return Err(err("bad castling rights")),
+            }
+        }
+
+        pos.en_passant = match fields.next().unwrap_or("-") {
+            "-" => None,
+            sq => Some(parse_square(sq).ok_or_else(|| err("bad en passant square"))?),
+        };
+
+        pos.halfmove_clock = fields.next().unwrap_or("0").parse().map_err(|_| err("bad halfmove clock"))?;
+        pos.fullmove_number = fields.next().unwrap_or("1").parse().map_err(|_| err("bad fullmove number"))?;
+
+        Ok(pos)
+    }
+
+    pub fn fen(&self) -> String {
+        let mut fen = String::new();
+
+        for r in (0..8).rev() {
+            let mut empty = 0;
+
+            for f in 0..8 {
+                match self.piece_at((f, r)) {
+                    Some((color, kind)) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+
+                        let c = kind_char(kind);
+                        fen.push(if color == PieceColor::White { c.to_ascii_uppercase() } else { c });
+                    }
+                    None => empty += 1,
+                }
+            }
+
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+
+            if r > 0 {
+                fen.push('/');
+            }
+        }
+
+        let castling: String = [
+            (self.castling.white_king, 'K'),
+            (self.castling.white_queen, 'Q'),
+            (self.castling.black_king, 'k'),
+            (self.castling.black_queen, 'q'),
+        ]
+        .iter()
+        .filter_map(|&(set, c)| set.then_some(c))
+        .collect();
+
+        format!(
+            "{} {} {} {} {} {}",
+            fen,
+            if self.turn == PieceColor::White { "w" } else { "b" },
+            if castling.is_empty() { "-".to_string() } else { castling },
+            self.en_passant.map_or("-".to_string(), square_name),
+            self.halfmove_clock,
+            self.fullmove_number,
+        )
+    }
+
+    pub fn piece_at(&self, sq: Square) -> Option<(PieceColor, Kind)> {
+        self.squares[idx(sq)]
+    }
+
+    pub fn set_piece(&mut self, sq: Square, piece: Option<(PieceColor, Kind)>) {
+        self.squares[idx(sq)] = piece;
+    }
+
+    pub fn pieces(&self) -> impl Iterator<Item = (Square, PieceColor, Kind)> + '_ {
+        self.squares
+            .iter()
+            .enumerate()
+            .filter_map(|(i, p)| p.map(|(color, kind)| (((i % 8) as u8, (i / 8) as u8), color, kind)))
+    }
+
+    pub fn king(&self, color: PieceColor) -> Option<Square> {
+        self.pieces().find(|&(_, c, k)| c == color && k == Kind::King).map(|(sq, _, _)| sq)
+    }
+
+    pub fn in_check(&self) -> bool {
+        self.king(self.turn).is_some_and(|sq| self.is_attacked(sq, self.turn.other()))
+    }
+
+    pub fn is_attacked(&self, sq: Square, by: PieceColor) -> bool {
+        let holds = |s: Option<Square>, kinds: &[Kind]| {
+            s.and_then(|s| self.piece_at(s)).is_some_and(|(c, k)| c == by && kinds.contains(&k))
+        };
+
+        if holds(step(sq, (-1, -forward(by))), &[Kind::Pawn]) || holds(step(sq, (1, -forward(by))), &[Kind::Pawn]) {
+            return true;
+        }
+
+        if KNIGHT_STEPS.iter().any(|&d| holds(step(sq, d), &[Kind::Knight]))
+            || KING_STEPS.iter().any(|&d| holds(step(sq, d), &[Kind::King]))
+        {
+            return true;
+        }
+
+        let slides = |dirs: &[(i8, i8)], kinds: &[Kind]| {
+            dirs.iter().any(|&d| {
+                let mut cur = step(sq, d);
+
+                while let Some(s) = cur {
+                    if self.piece_at(s).is_some() {
+                        return holds(Some(s), kinds);
+                    }
+                    cur = step(s, d);
+                }
+
+                false
+            })
+        };
+
+        slides(&ROOK_DIRS, &[Kind::Rook, Kind::Queen]) || slides(&BISHOP_DIRS, &[Kind::Bishop, Kind::Queen])
+    }
+
+    fn pseudo_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(48);
+        let us = self.turn;
+
+        for (from, color, kind) in self.pieces() {
+            if color != us {
+                continue;
+            }
+
+            match kind {
+                Kind::Pawn => self.pawn_moves(from, &mut moves),
+                Kind::Knight => self.step_moves(from, &KNIGHT_STEPS, &mut moves),
+                Kind::Bishop => self.slide_moves(from, &BISHOP_DIRS, &mut moves),
+                Kind::Rook => self.slide_moves(from, &ROOK_DIRS, &mut moves),
+                Kind::Queen => {
+                    self.slide_moves(from, &ROOK_DIRS, &mut moves);
+                    self.slide_moves(from, &BISHOP_DIRS, &mut moves);
+                }
+                Kind::King => {
+                    self.step_moves(from, &KING_STEPS, &mut moves);
+                    self.castling_moves(from, &mut moves);
+                }
+            }
+        }
+
+        moves
+    }
+
+    fn pawn_moves(&self, from: Square, moves: &mut Vec<Move>) {
+        let dir = forward(self.turn);
+        let last = back_rank(self.turn.other());
+        let mut push = |to: Square| {
+            if to.1 == last {
+                moves.extend(PROMOTIONS.iter().map(|&k| Move::promote(from, to, k)));
+            } else {
+                moves.push(Move::new(from, to));
+            }
+        };
+
+        if let Some(one) = step(from, (0, dir)).filter(|&s| self.piece_at(s).is_none()) {
+            push(one);
+
+            let start = (back_rank(self.turn) as i8 + dir) as u8;
+            if let Some(two) = step(one, (0, dir)).filter(|&s| from.1 == start && self.piece_at(s).is_none()) {
+                push(two);
+            }
+        }
+
+        for df in [-1, 1] {
+            let Some(to) = step(from, (df, dir)) else { continue; };
+
+            let enemy = self.piece_at(to).is_some_and(|(c, _)| c != self.turn);
+            if enemy || self.en_passant == Some(to) {
+                push(to);
+            }
+        }
+    }
+
+    fn step_moves(&self, from: Square, steps: &[(i8, i8)], moves: &mut Vec<Move>) {
+        for &d in steps {
+            if let Some(to) = step(from, d) {
+                if self.piece_at(to).is_none_or(|(c, _)| c != self.turn) {
+                    moves.push(Move::new(from, to));
+                }
+            }
+        }
+    }
+
+    fn slide_moves(&self, from: Square, dirs: &[(i8, i8)], moves: &mut Vec<Move>) {
+        for &d in dirs {
+            let mut cur = step(from, d);
+
+            while let Some(to) = cur {
+                match self.piece_at(to) {
+                    None => moves.push(Move::new(from, to)),
+                    Some((c, _)) => {
+                        if c != self.turn {
+                            moves.push(Move::new(from, to));
+                        }
+                        break;
+                    }
+                }
+                cur = step(to, d);
+            }
+        }
+    }
+
+    fn castling_moves(&self, from: Square, moves: &mut Vec<Move>) {
+        let rank = back_rank(self.turn);
+        let them = self.turn.other();
+
+        if from != (4, rank) || self.is_attacked(from, them) {
+            return;
+        }
+
+        for (king_side, rook_file, empty, safe) in [(true, 7, 5..7, [5, 6]), (false, 0, 1..4, [3, 2])] {
+            if !self.castling.get(self.turn, king_side)
+                || self.piece_at((rook_file, rank)) != Some((self.turn, Kind::Rook))
+                || empty.clone().any(|f| self.piece_at((f, rank)).is_some())
+                || safe.iter().any(|&f| self.is_attacked((f, rank), them))
+            {
+                continue;
+            }
+
+            moves.push(Move::new(from, (safe[1], rank)));
+        }
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.successors().into_iter().map(|(mv, _)| mv).collect()
+    }
+
+    /// Every legal move together with the position it leads to.
+    pub fn successors(&self) -> Vec<(Move, Position)> {
+        let us = self.turn;
+
+        self.pseudo_moves()
+            .into_iter()
+            .filter_map(|mv| {
+                let mut next = self.clone();
+                next.make_move(mv);
+                next.king(us).is_none_or(|k| !next.is_attacked(k, us.other())).then_some((mv, next))
+            })
+            .collect()
+    }
+
+    pub fn is_legal(&self, mv: Move) -> bool {
+        self.legal_moves().contains(&mv)
+    }
+
+    /// Square of the piece removed by `mv`, which differs from `mv.to` for en passant.
+    pub fn captured_square(&self, mv: Move) -> Option<Square> {
+        if self.piece_at(mv.to).is_some() {
+            return Some(mv.to);
+        }
+
+        let pawn = self.piece_at(mv.from).is_some_and(|(_, k)| k == Kind::Pawn);
+        (pawn && self.en_passant == Some(mv.to) && mv.from.0 != mv.to.0).then_some((mv.to.0, mv.from.1))
+    }
+
+    /// Rook travel `(from, to)` when `mv` is a castling move.
+    pub fn castling_rook(&self, mv: Move) -> Option<(Square, Square)> {
+        let king = self.piece_at(mv.from).is_some_and(|(_, k)| k == Kind::King);
+
+        if !king || mv.from.0.abs_diff(mv.to.0) != 2 {
+            return None;
+        }
+
+        let rank = mv.from.1;
+        Some(if mv.to.0 == 6 { ((7, rank), (5, rank)) } else { ((0, rank), (3, rank)) })
+    }
+
+    pub fn make_move(&mut self, mv: Move) {
+        let Some((color, kind)) = self.piece_at(mv.from) else { return; };
+        let captured = self.captured_square(mv);
+
+        if let Some(sq) = captured {
+            self.set_piece(sq, None);
+        }
+
+        if let Some((rook_from, rook_to)) = self.castling_rook(mv) {
+            let rook = self.piece_at(rook_from);
+            self.set_piece(rook_from, None);
+            self.set_piece(rook_to, rook);
+        }
+
+        self.set_piece(mv.from, None);
+        self.set_piece(mv.to, Some((color, mv.promotion.unwrap_or(kind))));
+
+        self.en_passant = (kind == Kind::Pawn && mv.from.1.abs_diff(mv.to.1) == 2)
+            .then_some((mv.from.0, (mv.from.1 + mv.to.1) / 2));
+
+        if kind == Kind::King {
+            self.castling.set(color, true, false);
+            self.castling.set(color, false, false);
+        }
+
+        for (corner, c, king_side) in [
+            ((7, 0), PieceColor::White, true),
+            ((0, 0), PieceColor::White, false),
+            ((7, 7), PieceColor::Black, true),
+            ((0, 7), PieceColor::Black, false),
+        ] {
+            if mv.from == corner || mv.to == corner {
+                self.castling.set(c, king_side, false);
+            }
+        }
+
+        self.halfmove_clock = if kind == Kind::Pawn || captured.is_some() { 0 } else { self.halfmove_clock + 1 };
+
+        if color == PieceColor::Black {
+            self.fullmove_number += 1;
+        }
+
+        self.turn = color.other();
+    }
+
+    /// Key identifying the position for repetition purposes, ignoring the move counters.
+    pub fn key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        self.squares.hash(&mut hasher);
+        self.turn.hash(&mut hasher);
+        self.castling.hash(&mut hasher);
+        self.en_passant
+            .filter(|&ep| self.pseudo_moves().iter().any(|&mv| mv.to == ep && self.captured_square(mv).is_some()))
+            .hash(&mut hasher);
+
+        hasher.finish()
+    }
+
+    /// Dead positions the rules draw automatically: bare kings, a single minor piece,
+    /// or bishops that all live on squares of the same color.
+    pub fn insufficient_material(&self) -> bool {
+        let others: Vec<(Square, Kind)> = self
+            .pieces()
+            .filter(|&(_, _, k)| k != Kind::King)
+            .map(|(sq, _, k)| (sq, k))
+            .collect();
+
+        match others.as_slice() {
+            [] => true,
+            [(_, kind)] => matches!(kind, Kind::Knight | Kind::Bishop),
+            _ => {
+                let shade = (others[0].0 .0 + others[0].0 .1) % 2;
+                others.iter().all(|&((f, r), k)| k == Kind::Bishop && (f + r) % 2 == shade)
+            }
+        }
+    }
+
+    /// Whether `color` could still mate by some sequence of legal moves, as the flag rule asks.
+    /// A lone minor piece, or bishops all on one shade, needs the opponent's men to box the king in.
+    pub fn has_mating_material(&self, color: PieceColor) -> bool {
+        let own: Vec<(Square, Kind)> = self
+            .pieces()
+            .filter(|&(_, c, k)| c == color && k != Kind::King)
+            .map(|(sq, _, k)| (sq, k))
+            .collect();
+        let helpers = self.pieces().any(|(_, c, k)| c != color && k != Kind::King);
+
+        if own.iter().any(|&(_, k)| matches!(k, Kind::Pawn | Kind::Rook | Kind::Queen)) {
+            return true;
+        }
+
+        let Some(&((f, r), _)) = own.first() else { return false; };
+        let one_shade = own.iter().all(|&((f2, r2), k)| k == Kind::Bishop && (f2 + r2) % 2 == (f + r) % 2);
+
+        if own.len() == 1 || one_shade {
+            helpers
+        } else {
+            true
+        }
+    }
+
+    /// Whether the king and rook still stand where castling to that side needs them.
+    pub fn castling_possible(&self, color: PieceColor, king_side: bool) -> bool {
+        let rank = if color == PieceColor::White { 0 } else { 7 };
+        let rook = if king_side { 7 } else { 0 };
+
+        self.piece_at((4, rank)) == Some((color, Kind::King)) && self.piece_at((rook, rank)) == Some((color, Kind::Rook))
+    }
+
+    /// Squares a pawn of the side not to move could just have skipped with a double step.
+    pub fn en_passant_candidates(&self) -> Vec<Square> {
+        let mover = self.turn.other();
+        let (skipped, landed, from) = if mover == PieceColor::White { (2, 3, 1) } else { (5, 4, 6) };
+
+        (0..8)
+            .filter(|&f| {
+                self.piece_at((f, landed)) == Some((mover, Kind::Pawn))
+                    && self.piece_at((f, skipped)).is_none()
+                    && self.piece_at((f, from)).is_none()
+            })
+            .map(|f| (f, skipped))
+            .collect()
+    }
+
+    /// Checks the position could come up in a game: one king a side, no pawns on the first or
+    /// last rank, the side that just moved not left in check, and castling and en passant rights
+    /// that fit the board.
+    pub fn validate(&self) -> Result<(), ParseError> {
+        for color in [PieceColor::White, PieceColor::Black] {
+            if self.pieces().filter(|&(_, c, k)| c == color && k == Kind::King).count() != 1 {
+                return Err(ParseError(format!("{color:?} needs exactly one king")));
+            }
+        }
+
+        if self.pieces().any(|((_, r), _, k)| k == Kind::Pawn && (r == 0 || r == 7)) {
+            return Err(ParseError(String::from("pawns cannot stand on the first or last rank")));
+        }
+
+        let waiting = self.turn.other();
+        if self.king(waiting).is_some_and(|sq| self.is_attacked(sq, self.turn)) {
+            return Err(ParseError(format!("{waiting:?} is in check but it is not their move")));
+        }
+
+        for color in [PieceColor::White, PieceColor::Black] {
+            for king_side in [true, false] {
+                if self.castling.get(color, king_side) && !self.castling_possible(color, king_side) {
+                    return Err(ParseError(format!("{color:?} cannot castle: king or rook has left its square")));
+                }
+            }
+        }
+
+        match self.en_passant {
+            Some(ep) if !self.en_passant_candidates().contains(&ep) => {
+                Err(ParseError(format!("no pawn can be taken en passant on {}", square_name(ep))))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Outcome decided by this position alone; repetitions need the game history.
+    pub fn outcome(&self) -> Option<Outcome> {
+        if self.legal_moves().is_empty() {
+            return Some(if self.in_check() { Outcome::Checkmate(self.turn.other()) } else { Outcome::Stalemate });
+        }
+
+        if self.insufficient_material() {
+            return Some(Outcome::InsufficientMaterial);
+        }
+
+        (self.halfmove_clock >= 100).then_some(Outcome::FiftyMoves)
+    }
+}
@@ -0,0 +1,132 @@
+use crate::piece::{Kind, PieceColor};
+use crate::rules::{kind_char, square_name, Move, Position};
+
+/// Standard algebraic notation of a legal move, e.g. `Nbd2`, `exd6`, `e8=Q+` or `O-O#`.
+pub fn san(pos: &Position, mv: Move) -> String {
+    let Some((_, kind)) = pos.piece_at(mv.from) else { return mv.to_string(); };
+
+    let mut san = if pos.castling_rook(mv).is_some() {
+        String::from(if mv.to.0 == 6 { "O-O" } else { "O-O-O" })
+    } else {
+        let mut san = String::new();
+        let capture = pos.captured_square(mv).is_some();
+
+        if kind == Kind::Pawn {
+            if capture {
+                san.push((b'a' + mv.from.0) as char);
+            }
+        } else {
+            san.push(kind_char(kind).to_ascii_uppercase());
+            san.push_str(&disambiguation(pos, mv, kind));
+        }
+
+        if capture {
+            san.push('x');
+        }
+
+        san.push_str(&square_name(mv.to));
+
+        if let Some(promotion) = mv.promotion {
+            san.push('=');
+            san.push(kind_char(promotion).to_ascii_uppercase());
+        }
+
+        san
+    };
+
+    let mut next = pos.clone();
+    next.make_move(mv);
+
+    if next.in_check() {
+        san.push(if next.legal_moves().is_empty() { '#' } else { '+' });
+    }
+
+    san
+}
+
+fn disambiguation(pos: &Position, mv: Move, kind: Kind) -> String {
+    let rivals: Vec<Move> = pos
+        .legal_moves()
+        .into_iter()
+        .filter(|other| other.to == mv.to && other.from != mv.from)
+        .filter(|other| pos.piece_at(other.from).is_some_and(|(_, k)| k == kind))
+        .collect();
+
+    if rivals.is_empty() {
+        String::new()
+    } else if rivals.iter().all(|other| other.from.0 != mv.from.0) {
+        ((b'a' + mv.from.0) as char).to_string()
+    } else if rivals.iter().all(|other| other.from.1 != mv.from.1) {
+        (mv.from.1 + 1).to_string()
+    } else {
+        square_name(mv.from)
+    }
+}
+
+/// Legal move written as `text` in SAN. Check marks, annotation glyphs and the `=`
+/// of a promotion are optional, and castling may be written with zeros.
+pub fn parse(pos: &Position, text: &str) -> Option<Move> {
+    let mut text = text.trim_end_matches(['+', '#', '!', '?']).replace('0', "O");
+
+    if let [.., rank, piece] = text.as_bytes() {
+        if rank.is_ascii_digit() && b"QRBN".contains(piece) {
+            text.insert(text.len() - 1, '=');
+        }
+    }
+
+    pos.legal_moves()
+        .into_iter()
+        .find(|&mv| san(pos, mv).trim_end_matches(['+', '#']) == text)
+}
+
+/// Legal moves whose SAN or coordinates start with `text`, as typed into the move box.
+/// Capture and check signs and the `=` of a promotion may be left out.
+pub fn complete(pos: &Position, text: &str) -> Vec<Move> {
+    let bare = |s: &str| s.replace('0', "O").chars().filter(|c| !"x+#=!?".contains(*c)).collect::<String>();
+    let typed = bare(text.trim());
+    let coordinates = text.trim().to_ascii_lowercase();
+
+    if typed.is_empty() {
+        return Vec::new();
+    }
+
+    pos.legal_moves()
+        .into_iter()
+        .filter(|&mv| bare(&san(pos, mv)).starts_with(&typed) || mv.to_string().starts_with(&coordinates))
+        .collect()
+}
+
+/// The move meant by `text`: SAN, coordinates like `e2e4`, or the start of exactly one of them.
+pub fn read(pos: &Position, text: &str) -> Option<Move> {
+    let coordinates = text.trim().to_ascii_lowercase().parse::<Move>().ok().filter(|&mv| pos.is_legal(mv));
+
+    parse(pos, text.trim()).or(coordinates).or_else(|| match complete(pos, text).as_slice() {
+        &[mv] => Some(mv),
+        _ => None,
+    })
+}
+
+/// SAN of each move of `moves` played in order from `pos`, stopping at the first illegal one.
+pub fn line(pos: &Position, moves: &[Move]) -> Vec<String> {
+    let mut pos = pos.clone();
+    let mut sans = Vec::with_capacity(moves.len());
+
+    for &mv in moves {
+        if !pos.is_legal(mv) {
+            break;
+        }
+
+        sans.push(san(&pos, mv));
+        pos.make_move(mv);
+    }
+
+    sans
+}
+
+/// Move number prefix for the move about to be played in `pos`: `12.` for White, `12...` for Black.
+pub fn move_number(pos: &Position) -> String {
+    match pos.turn {
+        PieceColor::White => format!("{}.", pos.fullmove_number),
+        PieceColor::Black => format!("{}...", pos.fullmove_number),
+    }
+}
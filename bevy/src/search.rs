@@ -0,0 +1,463 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::piece::{Kind, PieceColor};
+use crate::rules::{Move, Position};
+use crate::uci::{Info, Score};
+
+pub const MATE: i32 = 100_000;
+const INF: i32 = 1_000_000;
+const MAX_PLY: u32 = 64;
+
+/// Moves a game is assumed to last beyond the current one when the clock doesn't say.
+const MOVES_TO_GO: u32 = 30;
+/// Kept back from every budget for the GUI and the operating system.
+const OVERHEAD: Duration = Duration::from_millis(30);
+/// Best move unchanged for this many iterations lets the search stop early.
+const STABLE_ITERATIONS: u32 = 3;
+/// Score drop, in centipawns, between iterations that earns the search extra time.
+const FAIL_LOW: i32 = 30;
+
+// Piece-square tables from White's point of view, a8 first.
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const KING_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+/// When to give up deepening; everything `None` searches until stopped.
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    pub depth: Option<u32>,
+    pub movetime: Option<Duration>,
+    pub nodes: Option<u64>,
+    /// Share of a clock, spent flexibly depending on how the search goes.
+    pub budget: Option<TimeBudget>,
+}
+
+/// Time for one move under a clock: `optimum` normally, up to `maximum` when the search is in trouble.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TimeBudget {
+    pub optimum: Duration,
+    pub maximum: Duration,
+}
+
+/// Search running on its own thread, reporting each finished depth.
+pub struct SearchThread {
+    stop: Arc<AtomicBool>,
+    reports: Receiver<Info>,
+    handle: Option<JoinHandle<Option<Move>>>,
+}
+
+struct Searcher<'a> {
+    stop: &'a AtomicBool,
+    limits: &'a Limits,
+    start: Instant,
+    nodes: u64,
+    history: Vec<u64>,
+    aborted: bool,
+}
+
+pub fn value(kind: Kind) -> i32 {
+    match kind {
+        Kind::Pawn => 100,
+        Kind::Knight => 320,
+        Kind::Bishop => 330,
+        Kind::Rook => 500,
+        Kind::Queen => 900,
+        Kind::King => 0,
+    }
+}
+
+/// Static evaluation in centipawns from the side to move's point of view.
+pub fn evaluate(pos: &Position) -> i32 {
+    let mut score = 0;
+
+    for ((f, r), color, kind) in pos.pieces() {
+        let table = match kind {
+            Kind::Pawn => &PAWN_TABLE,
+            Kind::Knight => &KNIGHT_TABLE,
+            Kind::Bishop => &BISHOP_TABLE,
+            Kind::Rook => &ROOK_TABLE,
+            Kind::Queen => &QUEEN_TABLE,
+            Kind::King => &KING_TABLE,
+        };
+        let row = if color == PieceColor::White { 7 - r } else { r };
+        let piece = value(kind) + table[8 * row as usize + f as usize];
+
+        score += if color == pos.turn { piece } else { -piece };
+    }
+
+    score
+}
+
+impl TimeBudget {
+    /// Splits `remaining` evenly over the moves left, `moves_to_go` if the time
+    /// control says, plus most of the increment.
+    pub fn new(remaining: Duration, increment: Duration, moves_to_go: Option<u32>) -> Self {
+        let usable = remaining.saturating_sub(OVERHEAD);
+        let moves = moves_to_go.unwrap_or(MOVES_TO_GO).max(1);
+
+        let optimum = (usable / moves + increment * 3 / 4).min(usable / 2);
+        let maximum = (optimum * 4).min(usable / 3).max(optimum);
+
+        Self { optimum, maximum }
+    }
+
+    /// Whether another iteration is worth starting after `elapsed`. A best move that
+    /// held for a while halves the target, a score that fell doubles it.
+    pub fn keep_going(&self, elapsed: Duration, stable: u32, fall: i32) -> bool {
+        let mut target = self.optimum;
+
+        if fall >= FAIL_LOW {
+            target *= 2;
+        } else if stable >= STABLE_ITERATIONS {
+            target /= 2;
+        }
+
+        // The next iteration takes several times as long as this one, so don't start what can't finish.
+        elapsed < target.min(self.maximum) / 2
+    }
+}
+
+pub fn uci_score(score: i32) -> Score {
+    if score.abs() < MATE - MAX_PLY as i32 {
+        return Score::Cp(score);
+    }
+
+    let moves = (MATE - score.abs() + 1) / 2;
+    Score::Mate(if score > 0 { moves } else { -moves })
+}
+
+/// Iterative deepening over the `multipv` best root moves. `history` holds the
+/// repetition keys of the positions leading up to `pos`. Returns the best move.
+pub fn search(
+    pos: &Position,
+    history: &[u64],
+    limits: &Limits,
+    multipv: usize,
+    stop: &AtomicBool,
+    mut report: impl FnMut(Info),
+) -> Option<Move> {
+    let mut root = pos.successors();
+    let mut searcher = Searcher {
+        stop,
+        limits,
+        start: Instant::now(),
+        nodes: 0,
+        history: history.to_vec(),
+        aborted: false,
+    };
+
+    let mut best = root.first()?.0;
+    let mut stable = 0;
+    let mut last_score = None;
+    let multipv = multipv.clamp(1, root.len());
+    let max_depth = limits.depth.unwrap_or(MAX_PLY).min(MAX_PLY);
+
+    searcher.history.push(pos.key());
+    order(pos, &mut root);
+
+    for depth in 1..=max_depth {
+        let mut lines: Vec<(i32, Vec<Move>)> = Vec::with_capacity(multipv);
+
+        while lines.len() < multipv {
+            let mut alpha = -INF;
+            let mut line = None;
+
+            for (mv, child) in &root {
+                if lines.iter().any(|(_, pv)| pv[0] == *mv) {
+                    continue;
+                }
+
+                let mut pv = Vec::new();
+                let score = -searcher.negamax(child, depth - 1, 1, -INF, -alpha, &mut pv);
+
+                if searcher.aborted {
+                    break;
+                }
+
+                if score > alpha {
+                    alpha = score;
+                    pv.insert(0, *mv);
+                    line = Some((score, pv));
+                }
+            }
+
+            match line {
+                Some(line) if !searcher.aborted => lines.push(line),
+                _ => break,
+            }
+        }
+
+        // A depth cut short is not trustworthy, unless it is all we have.
+        if searcher.aborted && depth > 1 {
+            break;
+        }
+
+        let Some(&(score, ref pv)) = lines.first() else { break; };
+
+        stable = if pv[0] == best { stable + 1 } else { 0 };
+        let fall = last_score.map_or(0, |last: i32| last - score);
+        best = pv[0];
+        last_score = Some(score);
+
+        for (_, pv) in lines.iter().rev() {
+            if let Some(at) = root.iter().position(|(mv, _)| *mv == pv[0]) {
+                let entry = root.remove(at);
+                root.insert(0, entry);
+            }
+        }
+
+        let time = searcher.start.elapsed().as_millis() as u64;
+        for (i, (score, pv)) in lines.into_iter().enumerate() {
+            report(Info {
+                depth: Some(depth),
+                multipv: Some(i as u32 + 1),
+                score: Some(uci_score(score)),
+                nodes: Some(searcher.nodes),
+                nps: Some(searcher.nodes * 1000 / time.max(1)),
+                time: Some(time),
+                pv,
+                ..Info::default()
+            });
+        }
+
+        if searcher.aborted || searcher.out_of_budget() {
+            break;
+        }
+
+        if limits.budget.is_some_and(|b| !b.keep_going(searcher.start.elapsed(), stable, fall)) {
+            break;
+        }
+    }
+
+    Some(best)
+}
+
+/// Captures first, most valuable victim by least valuable attacker.
+fn order(pos: &Position, moves: &mut [(Move, Position)]) {
+    moves.sort_by_cached_key(|(mv, _)| {
+        let victim = pos.captured_square(*mv).and_then(|sq| pos.piece_at(sq)).map_or(0, |(_, k)| value(k).max(100));
+        let attacker = pos.piece_at(mv.from).map_or(0, |(_, k)| value(k));
+        let promotion = mv.promotion.map_or(0, value);
+
+        -(victim * 10 - attacker / 10 + promotion)
+    });
+}
+
+impl Searcher<'_> {
+    fn out_of_budget(&self) -> bool {
+        let hard = self.limits.movetime.into_iter().chain(self.limits.budget.map(|b| b.maximum)).min();
+
+        hard.is_some_and(|t| self.start.elapsed() >= t)
+            || self.limits.nodes.is_some_and(|n| self.nodes >= n)
+    }
+
+    fn visit(&mut self) -> bool {
+        self.nodes += 1;
+
+        if self.nodes & 1023 == 0 && (self.stop.load(Ordering::Relaxed) || self.out_of_budget()) {
+            self.aborted = true;
+        }
+
+        !self.aborted
+    }
+
+    fn negamax(&mut self, pos: &Position, depth: u32, ply: u32, mut alpha: i32, beta: i32, pv: &mut Vec<Move>) -> i32 {
+        if !self.visit() {
+            return 0;
+        }
+
+        let key = pos.key();
+        if pos.halfmove_clock >= 100 || self.history.contains(&key) {
+            return 0;
+        }
+
+        let in_check = pos.in_check();
+        let depth = if in_check && ply < MAX_PLY { depth + 1 } else { depth };
+
+        if depth == 0 || ply >= MAX_PLY {
+            return self.quiesce(pos, ply, alpha, beta);
+        }
+
+        let mut children = pos.successors();
+        if children.is_empty() {
+            return if in_check { -MATE + ply as i32 } else { 0 };
+        }
+
+        order(pos, &mut children);
+        self.history.push(key);
+
+        for (mv, child) in &children {
+            let mut child_pv = Vec::new();
+            let score = -self.negamax(child, depth - 1, ply + 1, -beta, -alpha, &mut child_pv);
+
+            if self.aborted {
+                break;
+            }
+
+            if score >= beta {
+                alpha = beta;
+                break;
+            }
+
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(*mv);
+                pv.append(&mut child_pv);
+            }
+        }
+
+        self.history.pop();
+        alpha
+    }
+
+    fn quiesce(&mut self, pos: &Position, ply: u32, mut alpha: i32, beta: i32) -> i32 {
+        if !self.visit() {
+            return 0;
+        }
+
+        let stand_pat = evaluate(pos);
+        if stand_pat >= beta {
+            return beta;
+        }
+        alpha = alpha.max(stand_pat);
+
+        if ply >= MAX_PLY * 2 {
+            return alpha;
+        }
+
+        let mut captures: Vec<(Move, Position)> = pos
+            .successors()
+            .into_iter()
+            .filter(|(mv, _)| mv.promotion.is_some() || pos.captured_square(*mv).is_some())
+            .collect();
+        order(pos, &mut captures);
+
+        for (_, child) in &captures {
+            let score = -self.quiesce(child, ply + 1, -beta, -alpha);
+
+            if self.aborted {
+                break;
+            }
+
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+
+        alpha
+    }
+}
+
+impl SearchThread {
+    pub fn spawn(pos: Position, history: Vec<u64>, limits: Limits, multipv: usize) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (tx, reports) = mpsc::channel();
+        let flag = stop.clone();
+
+        let handle = thread::spawn(move || {
+            search(&pos, &history, &limits, multipv, &flag, |info| {
+                let _ = tx.send(info);
+            })
+        });
+
+        Self { stop, reports, handle: Some(handle) }
+    }
+
+    pub fn poll(&self) -> Vec<Info> {
+        self.reports.try_iter().collect()
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// The move the search settled on, once it has finished; taken only once.
+    pub fn best_move(&mut self) -> Option<Move> {
+        let handle = self.handle.take_if(|h| h.is_finished())?;
+        handle.join().ok().flatten()
+    }
+}
+
+impl Drop for SearchThread {
+    fn drop(&mut self) {
+        self.stop();
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! The game in progress, saved when the app closes and carried on from the main menu. The file
+//! holds the moves from the start position rather than the board, so loading replays them and
+//! sets the board up as for any new game.
+
+use std::path::{Path, PathBuf};
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::EngineProcess;
+use crate::game::{Game, Player, Players};
+use crate::marks::{forget_marks, Mark, Marks};
+use crate::piece::PieceColor;
+use crate::rules::{Move, Position};
+use crate::settings::config_dir;
+use crate::setup::{GameConfig, NewGameEvent};
+use crate::states::{setup_menu, AppState};
+use crate::timer::{ClockHistory, ResumeClocks, Timer};
+
+const FILE_NAME: &str = "last-game.ron";
+/// Bumped whenever `SavedGame` changes shape; files of any other version are not read.
+pub const SAVE_VERSION: u32 = 2;
+
+pub struct SessionPlugin;
+
+impl Plugin for SessionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(LastGame::load())
+            .init_resource::<Resuming>()
+            .add_event::<ContinueGameEvent>()
+            .add_systems(OnEnter(AppState::MainMenu), keep_last_game.before(setup_menu))
+            .add_systems(Update, (continue_game, restore_marks.after(continue_game).after(forget_marks)))
+            .add_systems(Last, autosave);
+    }
+}
+
+/// Asks for the saved game to be carried on.
+#[derive(Event)]
+pub struct ContinueGameEvent;
+
+/// Everything needed to carry a game on: how it started, what was played, who plays it and how,
+/// and what the clocks and marks showed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedGame {
+    pub version: u32,
+    /// FEN of the position the game started from.
+    pub start: String,
+    /// Moves played, in coordinate notation.
+    pub moves: Vec<String>,
+    pub white: Player,
+    pub black: Player,
+    pub config: GameConfig,
+    /// Both clocks as they stood; none in a game without a clock.
+    pub clocks: Vec<Timer>,
+    /// Each clock after every move its side made, by the plies made then, for taking moves back.
+    pub clock_history: Vec<(usize, Timer)>,
+    /// Marks by the number of moves played before the position they were drawn on.
+    pub marks: Vec<(usize, Vec<Mark>)>,
+    pub draw_offer: Option<PieceColor>,
+}
+
+/// Read first, so a file of another version is turned down for that rather than for its fields.
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+/// Where the game is saved, and the game that can be carried on: the one found there when the
+/// app started, or the one left for the main menu.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct LastGame {
+    pub path: Option<PathBuf>,
+    pub saved: Option<SavedGame>,
+    /// Whether a game was set up in this run; until then the saved one is left as it is.
+    pub started: bool,
+}
+
+/// The game being carried on, until its marks are back on the board.
+#[derive(Resource, Default)]
+struct Resuming(Option<SavedGame>);
+
+impl SavedGame {
+    pub fn new<'a>(game: &Game, players: &Players, config: &GameConfig, clocks: impl IntoIterator<Item = &'a Timer>, history: &ClockHistory, marks: &Marks) -> Self {
+        let mut pos = game.start.clone();
+        let mut marked = Vec::new();
+
+        for ply in 0..=game.moves.len() {
+            if ply > 0 {
+                pos.make_move(game.moves[ply - 1]);
+            }
+
+            // A position met again shares its marks with the first time.
+            let drawn = marks.get(&pos);
+            if !drawn.is_empty() && !game.keys()[..ply].contains(&pos.key()) {
+                marked.push((ply, drawn.to_vec()));
+            }
+        }
+
+        Self {
+            version: SAVE_VERSION,
+            start: game.start.fen(),
+            moves: game.moves.iter().map(Move::to_string).collect(),
+            white: players.white,
+            black: players.black,
+            config: config.clone(),
+            clocks: clocks.into_iter().copied().collect(),
+            clock_history: history.0.clone(),
+            marks: marked,
+            draw_offer: game.draw_offer(),
+        }
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        let header: SaveHeader = ron::from_str(text).map_err(|e| e.to_string())?;
+        if header.version != SAVE_VERSION {
+            return Err(format!("saved in format version {}, only version {SAVE_VERSION} can be read", header.version));
+        }
+
+        ron::from_str(text).map_err(|e| e.to_string())
+    }
+
+    pub fn to_ron(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).expect("saved game serializes")
+    }
+
+    /// The game replayed from its start; fails on a move that is not legal where it was played.
+    pub fn game(&self) -> Result<Game, String> {
+        let start = Position::from_fen(&self.start).map_err(|e| format!("start position: {e}"))?;
+        start.validate().map_err(|e| format!("start position: {e}"))?;
+        let mut game = Game::new(start);
+
+        for (i, text) in self.moves.iter().enumerate() {
+            let mv: Move = text.parse().map_err(|e| format!("move {}: {e}", i + 1))?;
+            if !game.position.is_legal(mv) {
+                return Err(format!("move {}: {text} is not legal", i + 1));
+            }
+            game.play(mv);
+        }
+
+        if let Some(color) = self.draw_offer {
+            game.offer_draw(color);
+        }
+
+        Ok(game)
+    }
+
+    pub fn players(&self) -> Players {
+        Players { white: self.white, black: self.black }
+    }
+
+    /// Draws the saved marks again on `game`, the game this was saved from.
+    pub fn restore_marks(&self, game: &Game, marks: &mut Marks) {
+        for (ply, drawn) in &self.marks {
+            let pos = game.position_at(*ply);
+
+            for &mark in drawn {
+                if !marks.get(&pos).contains(&mark) {
+                    marks.toggle(&pos, mark);
+                }
+            }
+        }
+    }
+}
+
+/// The game saved at `path`, none if there is no file. A game that does not replay counts as unreadable.
+pub fn read(path: &Path) -> Result<Option<SavedGame>, String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.to_string()),
+    };
+
+    let saved = SavedGame::from_ron(&text)?;
+    saved.game()?;
+    Ok(Some(saved))
+}
+
+impl LastGame {
+    /// Looks in the config directory next to the settings.
+    pub fn load() -> Self {
+        let path = config_dir().map(|dir| dir.join(FILE_NAME));
+        let Some(file) = &path else { return Self::default(); };
+
+        match read(file) {
+            Ok(saved) => Self { path, saved, started: false },
+            Err(err) => {
+                warn!("the last game cannot be carried on, {} could not be read: {err}", file.display());
+                Self { path, saved: None, started: false }
+            }
+        }
+    }
+
+    fn save(&self, saved: &SavedGame) {
+        let Some(path) = &self.path else { return; };
+        let written = path.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|()| std::fs::write(path, saved.to_ron()));
+
+        match written {
+            Ok(()) => info!("game saved to {}", path.display()),
+            Err(err) => error!("failed to save the game to {}: {err}", path.display()),
+        }
+    }
+
+    /// A finished game is nothing to carry on.
+    fn forget(&self) {
+        let Some(path) = &self.path else { return; };
+
+        if let Err(err) = std::fs::remove_file(path) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                error!("failed to remove {}: {err}", path.display());
+            }
+        }
+    }
+}
+
+/// Sets the saved game up as a new game would be, then puts its clocks back; an engine game with
+/// no engine running goes to the setup screen instead.
+fn continue_game(
+    mut last: ResMut<LastGame>,
+    engine: Option<Res<EngineProcess>>,
+    mut game: ResMut<Game>,
+    mut players: ResMut<Players>,
+    mut config: ResMut<GameConfig>,
+    mut clocks: ResMut<ResumeClocks>,
+    mut resuming: ResMut<Resuming>,
+    mut next: ResMut<NextState<AppState>>,
+    mut continue_ev: EventReader<ContinueGameEvent>,
+    mut new_game_wr: EventWriter<NewGameEvent>,
+) {
+    if continue_ev.read().count() == 0 {
+        return;
+    }
+
+    let Some(saved) = last.saved.take() else { return; };
+    *config = saved.config.clone();
+
+    let restored = match saved.game() {
+        Ok(restored) => restored,
+        Err(err) => {
+            warn!("the last game cannot be carried on: {err}");
+            next.set(AppState::Setup);
+            return;
+        }
+    };
+
+    let saved_players = saved.players();
+    if engine.is_none() && [saved_players.white, saved_players.black].contains(&Player::Engine) {
+        warn!("the last game was against an engine and none is running; start one with --engine");
+        next.set(AppState::Setup);
+        return;
+    }
+
+    info!("carrying on the last game after {} moves", restored.moves.len());
+    *game = restored;
+    *players = saved_players;
+    clocks.timers.clone_from(&saved.clocks);
+    clocks.history.clone_from(&saved.clock_history);
+    resuming.0 = Some(saved);
+
+    new_game_wr.send(NewGameEvent);
+    next.set(AppState::Playing);
+}
+
+/// Puts the marks back once the new game has cleared the old ones away.
+fn restore_marks(game: Res<Game>, mut resuming: ResMut<Resuming>, mut marks: ResMut<Marks>, mut new_game_ev: EventReader<NewGameEvent>) {
+    if new_game_ev.read().count() == 0 {
+        return;
+    }
+
+    if let Some(saved) = resuming.0.take() {
+        saved.restore_marks(&game, &mut marks);
+    }
+}
+
+/// A game left for the main menu is the one to carry on from there, if it is still going.
+fn keep_last_game(
+    game: Res<Game>,
+    players: Res<Players>,
+    config: Res<GameConfig>,
+    marks: Res<Marks>,
+    timers: Query<&Timer>,
+    history: Res<ClockHistory>,
+    mut last: ResMut<LastGame>,
+) {
+    if last.started {
+        last.saved = game.outcome().is_none().then(|| SavedGame::new(&game, &players, &config, &timers, &history, &marks));
+    }
+}
+
+/// On the way out, saves the game played in this run if it is still going, and forgets the saved
+/// one if it has ended. Without a game played the last one is left for next time. Runs in `Last`:
+/// closing the window only turns into an `AppExit` in `PostUpdate`, and the app is gone before
+/// the next `Update`.
+fn autosave(
+    game: Res<Game>,
+    players: Res<Players>,
+    config: Res<GameConfig>,
+    marks: Res<Marks>,
+    mut last: ResMut<LastGame>,
+    timers: Query<&Timer>,
+    history: Res<ClockHistory>,
+    mut new_game_ev: EventReader<NewGameEvent>,
+    mut exit: EventReader<AppExit>,
+) {
+    if new_game_ev.read().count() > 0 {
+        last.started = true;
+    }
+
+    if exit.read().count() == 0 || !last.started {
+        return;
+    }
+
+    if game.outcome().is_some() {
+        last.forget();
+    } else {
+        last.save(&SavedGame::new(&game, &players, &config, &timers, &history, &marks));
+    }
+}
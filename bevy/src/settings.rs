@@ -0,0 +1,451 @@
+//! Preferences kept between runs in `settings.ron` in the user's config directory, and the
+//! screen that edits them. A missing or unreadable file means the defaults.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+use bevy::window::WindowResized;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::{text_style, MOVE_COLOR, MOVE_HOVER_COLOR};
+use crate::board::BoardSettings;
+use crate::consts::{CHOSEN_COLOR, HEIGHT, IDLE_TEXT_COLOR, PANEL_COLOR, PANEL_WIDTH, WIDTH};
+use crate::engine::{EngineMode, EngineSettings};
+use crate::piece::{AnimationSettings, PieceColor};
+use crate::setup::{preset_label, GameConfig, CLOCK_PRESETS};
+use crate::states::{AppState, OVERLAY_COLOR};
+use crate::theme::Appearance;
+use crate::uci::EngineConfig;
+
+const FILE_NAME: &str = "settings.ron";
+/// Changes are written once they have settled for this long, not on every step of a resize.
+const SAVE_DELAY: f32 = 0.5;
+/// Slide durations offered, in milliseconds.
+const ANIMATION_PRESETS: [(u64, &str); 4] = [(0, "Off"), (100, "Fast"), (200, "Normal"), (400, "Slow")];
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Settings>()
+            .init_resource::<SettingsFile>()
+            .add_systems(Startup, report_problem)
+            .add_systems(OnEnter(AppState::Settings), setup_settings_screen)
+            .add_systems(Update, (choose_setting, show_settings.after(choose_setting)).run_if(in_state(AppState::Settings)))
+            .add_systems(Update, (apply_settings.after(choose_setting), track_settings.after(apply_settings)))
+            .add_systems(Last, save_settings);
+    }
+}
+
+/// Everything kept between runs. Fields missing from the file take their defaults, so older
+/// files still load.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Folder name of the board theme.
+    pub theme: String,
+    /// Folder name of the piece set.
+    pub pieces: String,
+    /// How long a move takes to slide, in milliseconds; 0 for no animation.
+    pub animation_ms: u64,
+    pub coordinates: bool,
+    /// Pawns reaching the last rank become queens without asking.
+    pub auto_queen: bool,
+    /// Moves may be queued while the opponent is thinking.
+    pub premoves: bool,
+    /// Time control new games start with, in `--clock` notation; empty for none.
+    pub time_control: String,
+    /// UCI engines known to the settings screen.
+    pub engines: Vec<PathBuf>,
+    /// Engine started when none is given with `--engine`.
+    pub engine: Option<PathBuf>,
+    /// Window size when it was last closed.
+    pub window: (f32, f32),
+}
+
+/// Where the settings live, and why they could not be read if the defaults are in use.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct SettingsFile {
+    pub path: Option<PathBuf>,
+    pub problem: Option<String>,
+}
+
+#[derive(Component, Clone, Debug, PartialEq)]
+enum SettingsOption {
+    Theme(String),
+    Pieces(String),
+    Animation(u64),
+    Coordinates(bool),
+    AutoQueen(bool),
+    Premoves(bool),
+    Clock(&'static str),
+    Engine(Option<PathBuf>),
+    Reset,
+    Back,
+}
+
+#[derive(Component)]
+struct SettingsCard;
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: String::from("brown"),
+            pieces: String::from("classic"),
+            animation_ms: AnimationSettings::default().duration.as_millis() as u64,
+            coordinates: true,
+            auto_queen: true,
+            premoves: true,
+            time_control: String::new(),
+            engines: Vec::new(),
+            engine: None,
+            window: (WIDTH + PANEL_WIDTH, HEIGHT),
+        }
+    }
+}
+
+impl Settings {
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        ron::from_str(text).map_err(|e| e.to_string())
+    }
+
+    pub fn to_ron(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).expect("settings serialize")
+    }
+
+    pub fn animation(&self) -> Duration {
+        Duration::from_millis(self.animation_ms)
+    }
+
+    /// The engine to start, playing Black, when the command line names none.
+    pub fn engine_settings(&self) -> Option<EngineSettings> {
+        self.engine.as_ref().map(|path| EngineSettings {
+            config: EngineConfig::new(path.clone()),
+            mode: EngineMode::Play(PieceColor::Black),
+            movetime: Duration::from_secs(1),
+            ponder: false,
+        })
+    }
+
+    fn chosen(&self, option: &SettingsOption) -> bool {
+        match option {
+            SettingsOption::Theme(id) => self.theme == *id,
+            SettingsOption::Pieces(id) => self.pieces == *id,
+            SettingsOption::Animation(ms) => self.animation_ms == *ms,
+            SettingsOption::Coordinates(on) => self.coordinates == *on,
+            SettingsOption::AutoQueen(on) => self.auto_queen == *on,
+            SettingsOption::Premoves(on) => self.premoves == *on,
+            SettingsOption::Clock(preset) => self.time_control == *preset,
+            SettingsOption::Engine(path) => self.engine == *path,
+            SettingsOption::Reset | SettingsOption::Back => false,
+        }
+    }
+}
+
+/// Settings from `path`; a file that is not there yet gives the defaults.
+pub fn read(path: &Path) -> Result<Settings, String> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => Settings::from_ron(&text),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Settings::default()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Where settings are kept: the platform's config directory, in a `chess` folder.
+pub fn config_dir() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+
+    let base = if cfg!(windows) {
+        var("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        var("XDG_CONFIG_HOME").or_else(|| var("HOME").map(|home| home.join(".config")))
+    };
+
+    base.map(|base| base.join("chess"))
+}
+
+impl SettingsFile {
+    /// Reads the user's settings, falling back to the defaults with a warning.
+    pub fn load() -> (Self, Settings) {
+        let path = config_dir().map(|dir| dir.join(FILE_NAME));
+        let Some(file) = &path else { return (Self::default(), Settings::default()); };
+
+        match read(file) {
+            Ok(settings) => (Self { path, problem: None }, settings),
+            Err(err) => (Self { path, problem: Some(format!("{FILE_NAME} could not be read, the defaults are in use: {err}")) }, Settings::default()),
+        }
+    }
+
+    /// Writes `settings` out. A file that could not be read is moved aside to `settings.ron.bak`
+    /// first: it may hold engines added by hand that the defaults in use know nothing of.
+    pub fn save(&mut self, settings: &Settings) {
+        let Some(path) = &self.path else { return; };
+
+        if self.problem.is_some() {
+            let backup = path.with_extension("ron.bak");
+
+            match std::fs::rename(path, &backup) {
+                Ok(()) => warn!("the settings that could not be read were moved to {}", backup.display()),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => {
+                    error!("settings not saved, {} could not be moved to {}: {err}", path.display(), backup.display());
+                    return;
+                }
+            }
+            self.problem = None;
+        }
+
+        let written = path.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|()| std::fs::write(path, settings.to_ron()));
+
+        if let Err(err) = written {
+            error!("failed to save settings to {}: {err}", path.display());
+        }
+    }
+}
+
+/// Logging only starts with the app, after the file was read.
+fn report_problem(file: Res<SettingsFile>) {
+    if let (Some(path), Some(problem)) = (&file.path, &file.problem) {
+        warn!("{}: {problem}", path.display());
+    }
+}
+
+/// Carries settings changed on the screen over to the parts of the app they are about. Only
+/// what changed is applied, so a new theme leaves the time control picked for the next game alone.
+fn apply_settings(
+    settings: Res<Settings>,
+    mut applied: Local<Option<Settings>>,
+    mut appearance: ResMut<Appearance>,
+    mut board: ResMut<BoardSettings>,
+    mut animation: ResMut<AnimationSettings>,
+    mut config: ResMut<GameConfig>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    // Everything started out from the settings already, or from the command line over them.
+    let Some(last) = applied.replace(settings.clone()) else { return; };
+
+    if last.theme != settings.theme && appearance.theme().id != settings.theme {
+        appearance.choose_theme(&settings.theme);
+    }
+    if last.pieces != settings.pieces && appearance.pieces().id != settings.pieces {
+        appearance.choose_pieces(&settings.pieces);
+    }
+    if last.coordinates != settings.coordinates && board.coordinates != settings.coordinates {
+        board.coordinates = settings.coordinates;
+    }
+    if last.auto_queen != settings.auto_queen {
+        board.auto_queen = settings.auto_queen;
+    }
+    if last.premoves != settings.premoves {
+        board.premoves = settings.premoves;
+    }
+    if last.animation_ms != settings.animation_ms {
+        animation.duration = settings.animation();
+    }
+    if last.time_control != settings.time_control {
+        config.clock.clone_from(&settings.time_control);
+    }
+}
+
+/// Keeps what was changed with a key or by resizing the window, so it is saved too.
+fn track_settings(
+    appearance: Res<Appearance>,
+    board: Res<BoardSettings>,
+    mut resized: EventReader<WindowResized>,
+    mut settings: ResMut<Settings>,
+) {
+    if appearance.is_changed() && appearance.theme().id != settings.theme {
+        settings.theme.clone_from(&appearance.theme().id);
+    }
+    if appearance.is_changed() && appearance.pieces().id != settings.pieces {
+        settings.pieces.clone_from(&appearance.pieces().id);
+    }
+    if board.is_changed() && board.coordinates != settings.coordinates {
+        settings.coordinates = board.coordinates;
+    }
+
+    if let Some(window) = resized.read().last() {
+        let size = (window.width, window.height);
+        if settings.window != size {
+            settings.window = size;
+        }
+    }
+}
+
+/// Writes the file once changes have settled, and straight away when the app is closing. Runs in
+/// `Last`: closing the window only turns into an `AppExit` in `PostUpdate`, and the app is gone
+/// before the next `Update`.
+fn save_settings(time: Res<Time>, settings: Res<Settings>, mut file: ResMut<SettingsFile>, mut exit: EventReader<AppExit>, mut wait: Local<Option<f32>>) {
+    if settings.is_changed() && !settings.is_added() {
+        *wait = Some(SAVE_DELAY);
+    }
+
+    let Some(left) = wait.as_mut() else { return; };
+    *left -= time.delta_seconds();
+
+    if *left > 0. && exit.is_empty() {
+        return;
+    }
+
+    exit.clear();
+    *wait = None;
+    file.save(&settings);
+}
+
+fn setup_settings_screen(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: OVERLAY_COLOR.into(),
+                focus_policy: FocusPolicy::Block,
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            StateScoped(AppState::Settings),
+        ))
+        .with_children(|overlay| {
+            overlay.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        max_width: Val::Px(560.),
+                        padding: UiRect::all(Val::Px(16.)),
+                        row_gap: Val::Px(8.),
+                        ..default()
+                    },
+                    background_color: PANEL_COLOR.into(),
+                    ..default()
+                },
+                SettingsCard,
+            ));
+        });
+}
+
+/// Escape goes back as well.
+fn choose_setting(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<Settings>,
+    mut next: ResMut<NextState<AppState>>,
+    mut interactions: Query<(&Interaction, &SettingsOption, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        next.set(AppState::MainMenu);
+    }
+
+    for (interaction, option, mut color) in &mut interactions {
+        match *interaction {
+            Interaction::Pressed => match option {
+                SettingsOption::Theme(id) => settings.theme.clone_from(id),
+                SettingsOption::Pieces(id) => settings.pieces.clone_from(id),
+                SettingsOption::Animation(ms) => settings.animation_ms = *ms,
+                SettingsOption::Coordinates(on) => settings.coordinates = *on,
+                SettingsOption::AutoQueen(on) => settings.auto_queen = *on,
+                SettingsOption::Premoves(on) => settings.premoves = *on,
+                SettingsOption::Clock(preset) => settings.time_control = preset.to_string(),
+                SettingsOption::Engine(path) => settings.engine.clone_from(path),
+                SettingsOption::Reset => {
+                    // Engines listed by hand and the window size are not preferences to reset.
+                    *settings = Settings { engines: settings.engines.clone(), window: settings.window, ..Settings::default() };
+                }
+                SettingsOption::Back => next.set(AppState::MainMenu),
+            },
+            Interaction::Hovered => *color = MOVE_HOVER_COLOR.into(),
+            Interaction::None => *color = if settings.chosen(option) { CHOSEN_COLOR } else { MOVE_COLOR }.into(),
+        }
+    }
+}
+
+/// Lays the card out again whenever a setting changes.
+fn show_settings(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    file: Res<SettingsFile>,
+    appearance: Res<Appearance>,
+    card: Query<(Entity, Ref<SettingsCard>)>,
+) {
+    let Ok((card, fresh)) = card.get_single() else { return; };
+
+    if !(settings.is_changed() || fresh.is_added()) {
+        return;
+    }
+
+    commands.entity(card).despawn_descendants();
+
+    let on_off = |option: fn(bool) -> SettingsOption| vec![(option(true), String::from("On")), (option(false), String::from("Off"))];
+    let note = |text: String| TextBundle::from_section(text, TextStyle { color: IDLE_TEXT_COLOR, ..text_style(&asset_server, 14.) });
+
+    commands.entity(card).with_children(|card| {
+        card.spawn(TextBundle::from_section("Settings", text_style(&asset_server, 28.)));
+
+        spawn_section(card, &asset_server, &settings, "Board", appearance.themes.iter().map(|t| (SettingsOption::Theme(t.id.clone()), t.name.clone())).collect());
+        spawn_section(card, &asset_server, &settings, "Pieces", appearance.piece_sets.iter().map(|p| (SettingsOption::Pieces(p.id.clone()), p.name.clone())).collect());
+        spawn_section(card, &asset_server, &settings, "Animation", ANIMATION_PRESETS.iter().map(|&(ms, label)| (SettingsOption::Animation(ms), String::from(label))).collect());
+        spawn_section(card, &asset_server, &settings, "Coordinates", on_off(SettingsOption::Coordinates));
+        spawn_section(card, &asset_server, &settings, "Always promote to a queen", on_off(SettingsOption::AutoQueen));
+        spawn_section(card, &asset_server, &settings, "Premoves", on_off(SettingsOption::Premoves));
+
+        let clocks = [""].into_iter().chain(CLOCK_PRESETS).map(|p| (SettingsOption::Clock(p), if p.is_empty() { String::from("None") } else { preset_label(p) }));
+        spawn_section(card, &asset_server, &settings, "Time control for new games", clocks.collect());
+
+        let mut engines = vec![(SettingsOption::Engine(None), String::from("None"))];
+        engines.extend(settings.engines.iter().map(|path| {
+            let name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned());
+            (SettingsOption::Engine(Some(path.clone())), name)
+        }));
+        spawn_section(card, &asset_server, &settings, "Engine", engines);
+
+        if let Some(path) = &file.path {
+            card.spawn(note(format!("Engines are listed under `engines` in {}; the one chosen starts with the next launch.", path.display())));
+        }
+
+        if let Some(problem) = &file.problem {
+            card.spawn(TextBundle::from_section(problem.clone(), TextStyle { color: Color::srgb(0.9, 0.4, 0.4), ..text_style(&asset_server, 14.) }));
+        }
+
+        spawn_section(card, &asset_server, &settings, "", vec![
+            (SettingsOption::Reset, String::from("Reset to defaults")),
+            (SettingsOption::Back, String::from("Back")),
+        ]);
+    });
+}
+
+fn spawn_section(card: &mut ChildBuilder, asset_server: &AssetServer, settings: &Settings, title: &str, options: Vec<(SettingsOption, String)>) {
+    card.spawn(TextBundle::from_section(title, text_style(asset_server, 16.)));
+    card.spawn(NodeBundle {
+        style: Style { flex_wrap: FlexWrap::Wrap, column_gap: Val::Px(6.), row_gap: Val::Px(6.), ..default() },
+        ..default()
+    })
+    .with_children(|row| {
+        for (option, label) in options {
+            row.spawn((
+                ButtonBundle {
+                    style: Style { padding: UiRect::axes(Val::Px(8.), Val::Px(4.)), ..default() },
+                    background_color: if settings.chosen(&option) { CHOSEN_COLOR } else { MOVE_COLOR }.into(),
+                    ..default()
+                },
+                option,
+            ))
+            .with_children(|button| {
+                button.spawn(TextBundle::from_section(label, text_style(asset_server, 16.)));
+            });
+        }
+    });
+}
@@ -0,0 +1,525 @@
+//! The new-game screen and the configuration it hands to the board, clock and engines.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::{setup_panel, text_style, PanelToolbar, MOVE_COLOR, MOVE_HOVER_COLOR};
+use crate::consts::{CHOSEN_COLOR, IDLE_TEXT_COLOR, PANEL_COLOR, TEXT_COLOR};
+use crate::editor::PositionEditor;
+use crate::engine::EngineProcess;
+use crate::game::{Game, Player, Players};
+use crate::piece::PieceColor;
+use crate::rules::{ParseError, Position};
+use crate::states::{AppState, OVERLAY_COLOR};
+use crate::timer::{ClockSettings, Timing};
+
+const FIELD_COLOR: Color = Color::srgb(0.05, 0.05, 0.05);
+/// Time controls offered with one click, in `--clock` notation.
+pub const CLOCK_PRESETS: [&str; 6] = ["60", "180+2", "300", "600+5", "900+10", "1800"];
+
+pub struct SetupPlugin;
+
+impl Plugin for SetupPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<GameConfig>()
+            .init_resource::<SetupScreen>()
+            .add_event::<NewGameEvent>()
+            .add_systems(Startup, setup_new_game_button.after(setup_panel))
+            .add_systems(OnEnter(AppState::Setup), setup_screen)
+            .add_systems(Update, new_game_button)
+            .add_systems(
+                Update,
+                (choose_option, type_text, start_game.after(choose_option).after(type_text), show_setup.after(start_game))
+                    .run_if(in_state(AppState::Setup)),
+            );
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Opponent {
+    /// Both sides played on this screen.
+    Human,
+    /// The built-in search.
+    Computer,
+    /// The UCI engine given on the command line.
+    Engine,
+    Network,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SideChoice {
+    White,
+    Black,
+    Random,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum StartChoice {
+    Standard,
+    Fen,
+}
+
+/// How the next game is played, as chosen on the setup screen.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameConfig {
+    pub opponent: Opponent,
+    /// Side the local player takes against an opponent.
+    pub side: SideChoice,
+    /// Time control in `--clock` notation, empty for none.
+    pub clock: String,
+    pub timing: Timing,
+    pub start: StartChoice,
+    pub fen: String,
+    pub rated: bool,
+}
+
+/// The setup screen's own state, kept while it is up.
+#[derive(Resource, Default)]
+pub struct SetupScreen {
+    /// Where Cancel leads back to, if there is a game to go back to.
+    pub back: Option<AppState>,
+    focus: Option<Field>,
+    error: Option<String>,
+}
+
+/// A game has been set up: `Game` and `Players` hold it, the rest follows the config.
+#[derive(Event)]
+pub struct NewGameEvent;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Field {
+    Clock,
+    Fen,
+}
+
+#[derive(Component, Clone, Debug, PartialEq)]
+enum SetupOption {
+    Opponent(Opponent),
+    Side(SideChoice),
+    Clock(&'static str),
+    CustomClock,
+    Start(StartChoice),
+    /// Opens the position editor on the chosen start.
+    Edit,
+    Rated(bool),
+    Field(Field),
+    Play,
+    Cancel,
+}
+
+#[derive(Component)]
+struct SetupCard;
+
+#[derive(Component)]
+struct NewGameButton;
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            opponent: Opponent::Human,
+            side: SideChoice::White,
+            clock: String::new(),
+            timing: Timing::default(),
+            start: StartChoice::Standard,
+            fen: String::new(),
+            rated: false,
+        }
+    }
+}
+
+impl GameConfig {
+    pub fn clock(&self) -> Result<Option<ClockSettings>, ParseError> {
+        if self.clock.trim().is_empty() {
+            return Ok(None);
+        }
+
+        let clock: ClockSettings = self.clock.trim().parse()?;
+        Ok(Some(ClockSettings { timing: self.timing, ..clock }))
+    }
+
+    pub fn start_position(&self) -> Result<Position, ParseError> {
+        match self.start {
+            StartChoice::Standard => Ok(Position::default()),
+            StartChoice::Fen => {
+                let position = Position::from_fen(self.fen.trim())?;
+                position.validate()?;
+                Ok(position)
+            }
+        }
+    }
+
+    /// For a rematch: the local player takes the colour the opponent had in `players`, even if the
+    /// last one was drawn at random. Between two players here there is nothing to swap.
+    pub fn swap_sides(&mut self, players: &Players) {
+        let played = [PieceColor::White, PieceColor::Black].into_iter().find(|&color| players.get(color) == Player::Human && players.get(color.other()) != Player::Human);
+
+        self.side = match played {
+            Some(PieceColor::White) => SideChoice::Black,
+            Some(PieceColor::Black) => SideChoice::White,
+            None => self.side,
+        };
+    }
+
+    /// Who plays each side, with a random side drawn by `coin`.
+    pub fn players(&self, coin: bool) -> Players {
+        let opponent = match self.opponent {
+            Opponent::Human | Opponent::Network => return Players::default(),
+            Opponent::Computer => Player::Computer,
+            Opponent::Engine => Player::Engine,
+        };
+
+        let human = match self.side {
+            SideChoice::White => PieceColor::White,
+            SideChoice::Black => PieceColor::Black,
+            SideChoice::Random if coin => PieceColor::White,
+            SideChoice::Random => PieceColor::Black,
+        };
+
+        let mut players = Players::default();
+        players.set(human.other(), opponent);
+        players
+    }
+
+    fn color(&self, option: &SetupOption) -> Color {
+        match option {
+            SetupOption::Field(_) => FIELD_COLOR,
+            _ if self.chosen(option) => CHOSEN_COLOR,
+            _ => MOVE_COLOR,
+        }
+    }
+
+    fn chosen(&self, option: &SetupOption) -> bool {
+        match option {
+            SetupOption::Opponent(o) => self.opponent == *o,
+            SetupOption::Side(s) => self.side == *s,
+            SetupOption::Clock(preset) => self.clock == *preset,
+            SetupOption::CustomClock => !self.clock.is_empty() && !CLOCK_PRESETS.contains(&self.clock.as_str()),
+            SetupOption::Start(s) => self.start == *s,
+            SetupOption::Rated(r) => self.rated == *r,
+            SetupOption::Field(_) | SetupOption::Edit | SetupOption::Play | SetupOption::Cancel => false,
+        }
+    }
+}
+
+/// Game clock notation for a preset, e.g. `3+2` for 180 seconds plus 2.
+pub fn preset_label(preset: &str) -> String {
+    let (base, increment) = preset.split_once('+').unwrap_or((preset, "0"));
+    let minutes = base.parse::<u32>().unwrap_or(0) / 60;
+    format!("{minutes}+{increment}")
+}
+
+fn setup_screen(mut commands: Commands, mut screen: ResMut<SetupScreen>) {
+    screen.focus = None;
+    screen.error = None;
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: OVERLAY_COLOR.into(),
+                focus_policy: FocusPolicy::Block,
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            StateScoped(AppState::Setup),
+        ))
+        .with_children(|overlay| {
+            overlay.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(16.)),
+                        row_gap: Val::Px(8.),
+                        ..default()
+                    },
+                    background_color: PANEL_COLOR.into(),
+                    ..default()
+                },
+                SetupCard,
+            ));
+        });
+}
+
+fn setup_new_game_button(mut commands: Commands, asset_server: Res<AssetServer>, toolbar: Query<Entity, With<PanelToolbar>>) {
+    commands.entity(toolbar.single()).with_children(|toolbar| {
+        toolbar
+            .spawn((
+                ButtonBundle {
+                    style: Style { padding: UiRect::axes(Val::Px(8.), Val::Px(4.)), ..default() },
+                    background_color: MOVE_COLOR.into(),
+                    ..default()
+                },
+                NewGameButton,
+            ))
+            .with_children(|button| {
+                button.spawn(TextBundle::from_section("New game", text_style(&asset_server, 16.)));
+            });
+    });
+}
+
+fn new_game_button(
+    mut screen: ResMut<SetupScreen>,
+    state: Res<State<AppState>>,
+    mut next: ResMut<NextState<AppState>>,
+    mut interactions: Query<(&Interaction, &NewGameButton, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    for (interaction, _, mut color) in &mut interactions {
+        match *interaction {
+            Interaction::Pressed if *state.get() != AppState::Setup => {
+                screen.back = Some(*state.get());
+                next.set(AppState::Setup);
+            }
+            Interaction::Pressed => {}
+            Interaction::Hovered => *color = MOVE_HOVER_COLOR.into(),
+            Interaction::None => *color = MOVE_COLOR.into(),
+        }
+    }
+}
+
+fn choose_option(
+    mut config: ResMut<GameConfig>,
+    mut screen: ResMut<SetupScreen>,
+    mut editor: ResMut<PositionEditor>,
+    mut next: ResMut<NextState<AppState>>,
+    mut interactions: Query<(&Interaction, &SetupOption, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    for (interaction, option, mut color) in &mut interactions {
+        match *interaction {
+            Interaction::Pressed => {
+                screen.focus = None;
+
+                match option {
+                    SetupOption::Opponent(o) => config.opponent = *o,
+                    SetupOption::Side(s) => config.side = *s,
+                    SetupOption::Clock(preset) => config.clock = preset.to_string(),
+                    SetupOption::CustomClock => screen.focus = Some(Field::Clock),
+                    SetupOption::Start(s) => {
+                        config.start = *s;
+                        if *s == StartChoice::Fen {
+                            screen.focus = Some(Field::Fen);
+                        }
+                    }
+                    SetupOption::Edit => {
+                        editor.back = AppState::Setup;
+                        next.set(AppState::Editor);
+                    }
+                    SetupOption::Rated(r) => config.rated = *r,
+                    SetupOption::Field(field) => screen.focus = Some(*field),
+                    // Handled by `start_game`.
+                    SetupOption::Play | SetupOption::Cancel => {}
+                }
+            }
+            Interaction::Hovered => *color = MOVE_HOVER_COLOR.into(),
+            Interaction::None => *color = config.color(option).into(),
+        }
+    }
+}
+
+/// Typing into the focused field: characters, Backspace, and Enter to start.
+fn type_text(mut config: ResMut<GameConfig>, screen: Res<SetupScreen>, mut keys: EventReader<KeyboardInput>) {
+    let Some(field) = screen.focus else {
+        keys.clear();
+        return;
+    };
+
+    for key in keys.read().filter(|k| k.state == ButtonState::Pressed) {
+        let text = match field {
+            Field::Clock => &mut config.clock,
+            Field::Fen => &mut config.fen,
+        };
+
+        match &key.logical_key {
+            Key::Character(s) => text.push_str(s),
+            Key::Space => text.push(' '),
+            Key::Backspace => {
+                text.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn start_game(
+    keys: Res<ButtonInput<KeyCode>>,
+    config: Res<GameConfig>,
+    engine: Option<Res<EngineProcess>>,
+    mut screen: ResMut<SetupScreen>,
+    mut game: ResMut<Game>,
+    mut players: ResMut<Players>,
+    mut next: ResMut<NextState<AppState>>,
+    options: Query<(&Interaction, &SetupOption), Changed<Interaction>>,
+    mut new_game_wr: EventWriter<NewGameEvent>,
+) {
+    let pressed = |wanted: SetupOption| options.iter().any(|(i, o)| *i == Interaction::Pressed && *o == wanted);
+
+    if let Some(back) = screen.back.filter(|_| pressed(SetupOption::Cancel)) {
+        next.set(back);
+        return;
+    }
+
+    if !(pressed(SetupOption::Play) || keys.just_pressed(KeyCode::Enter)) {
+        return;
+    }
+
+    if let Err(err) = begin_game(&config, engine.is_some(), &mut game, &mut players) {
+        screen.error = Some(err);
+        return;
+    }
+
+    screen.back = None;
+    next.set(AppState::Playing);
+    new_game_wr.send(NewGameEvent);
+}
+
+/// Checks `config` and sets `game` and `players` up from it; the caller sends the `NewGameEvent`.
+pub fn begin_game(config: &GameConfig, engine: bool, game: &mut Game, players: &mut Players) -> Result<(), String> {
+    config.clock().map_err(|e| format!("Time control: {e}"))?;
+    let start = config.start_position().map_err(|e| format!("Position: {e}"))?;
+
+    match config.opponent {
+        Opponent::Engine if !engine => return Err(String::from("No engine is running; start one with --engine")),
+        Opponent::Network => return Err(String::from("Network play is not available yet")),
+        _ => {}
+    }
+
+    let coin = SystemTime::now().duration_since(UNIX_EPOCH).is_ok_and(|d| d.subsec_nanos() & 1 == 0);
+
+    *game = Game::new(start);
+    *players = config.players(coin);
+
+    info!("new game: {:?} against {:?}, clock {:?}", config.side, config.opponent, config.clock);
+    Ok(())
+}
+
+/// Lays the card out again whenever a choice changes.
+fn show_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    screen: Res<SetupScreen>,
+    engine: Option<Res<EngineProcess>>,
+    card: Query<Entity, With<SetupCard>>,
+) {
+    if !(config.is_changed() || screen.is_changed()) {
+        return;
+    }
+
+    let Ok(card) = card.get_single() else { return; };
+    commands.entity(card).despawn_descendants();
+
+    let engine = engine.is_some();
+    let clock_label = |preset: &str| if preset.is_empty() { String::from("None") } else { preset_label(preset) };
+
+    commands.entity(card).with_children(|card| {
+        card.spawn(TextBundle::from_section("New game", text_style(&asset_server, 28.)));
+
+        spawn_section(card, &asset_server, &config, "Opponent", vec![
+            (SetupOption::Opponent(Opponent::Human), String::from("Human"), true),
+            (SetupOption::Opponent(Opponent::Computer), String::from("Computer"), true),
+            (SetupOption::Opponent(Opponent::Engine), String::from("Engine"), engine),
+            (SetupOption::Opponent(Opponent::Network), String::from("Network"), false),
+        ]);
+
+        let against = config.opponent != Opponent::Human;
+        spawn_section(card, &asset_server, &config, "Play as", vec![
+            (SetupOption::Side(SideChoice::White), String::from("White"), against),
+            (SetupOption::Side(SideChoice::Black), String::from("Black"), against),
+            (SetupOption::Side(SideChoice::Random), String::from("Random"), against),
+        ]);
+
+        let mut clocks: Vec<_> = [""].into_iter().chain(CLOCK_PRESETS).map(|p| (SetupOption::Clock(p), clock_label(p), true)).collect();
+        clocks.push((SetupOption::CustomClock, String::from("Custom"), true));
+        spawn_section(card, &asset_server, &config, "Time control", clocks);
+
+        if screen.focus == Some(Field::Clock) || config.chosen(&SetupOption::CustomClock) {
+            spawn_field(card, &asset_server, Field::Clock, &config.clock, screen.focus == Some(Field::Clock));
+        }
+
+        spawn_section(card, &asset_server, &config, "Start from", vec![
+            (SetupOption::Start(StartChoice::Standard), String::from("Standard"), true),
+            (SetupOption::Start(StartChoice::Fen), String::from("FEN"), true),
+            (SetupOption::Edit, String::from("Edit..."), true),
+        ]);
+
+        if config.start == StartChoice::Fen {
+            spawn_field(card, &asset_server, Field::Fen, &config.fen, screen.focus == Some(Field::Fen));
+        }
+
+        spawn_section(card, &asset_server, &config, "Mode", vec![
+            (SetupOption::Rated(false), String::from("Casual"), true),
+            (SetupOption::Rated(true), String::from("Rated"), true),
+        ]);
+
+        if let Some(error) = &screen.error {
+            card.spawn(TextBundle::from_section(error.clone(), TextStyle { color: Color::srgb(0.9, 0.4, 0.4), ..text_style(&asset_server, 14.) }));
+        }
+
+        let mut buttons = vec![(SetupOption::Play, String::from("Play"), true)];
+        if screen.back.is_some() {
+            buttons.push((SetupOption::Cancel, String::from("Cancel"), true));
+        }
+        spawn_section(card, &asset_server, &config, "", buttons);
+    });
+}
+
+/// A title over a row of options, each with its label and whether it can be taken.
+fn spawn_section(card: &mut ChildBuilder, asset_server: &AssetServer, config: &GameConfig, title: &str, options: Vec<(SetupOption, String, bool)>) {
+    card.spawn(TextBundle::from_section(title, text_style(asset_server, 16.)));
+    card.spawn(NodeBundle {
+        style: Style { flex_wrap: FlexWrap::Wrap, column_gap: Val::Px(6.), row_gap: Val::Px(6.), ..default() },
+        ..default()
+    })
+    .with_children(|row| {
+        for (option, label, enabled) in options {
+            spawn_option(row, asset_server, config, option, &label, enabled);
+        }
+    });
+}
+
+fn spawn_option(parent: &mut ChildBuilder, asset_server: &AssetServer, config: &GameConfig, option: SetupOption, label: &str, enabled: bool) {
+    let mut button = parent.spawn(ButtonBundle {
+        style: Style { padding: UiRect::axes(Val::Px(8.), Val::Px(4.)), ..default() },
+        background_color: if enabled { config.color(&option) } else { MOVE_COLOR }.into(),
+        ..default()
+    });
+
+    // Options that cannot be taken show greyed out and do nothing when clicked.
+    if enabled {
+        button.insert(option);
+    }
+
+    button.with_children(|button| {
+        let style = TextStyle { color: if enabled { TEXT_COLOR } else { IDLE_TEXT_COLOR }, ..text_style(asset_server, 16.) };
+        button.spawn(TextBundle::from_section(label, style));
+    });
+}
+
+fn spawn_field(parent: &mut ChildBuilder, asset_server: &AssetServer, field: Field, value: &str, focused: bool) {
+    let text = if focused { format!("{value}|") } else { value.to_string() };
+
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style { min_height: Val::Px(24.), padding: UiRect::axes(Val::Px(6.), Val::Px(3.)), ..default() },
+                background_color: FIELD_COLOR.into(),
+                ..default()
+            },
+            SetupOption::Field(field),
+        ))
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section(text, text_style(asset_server, 14.)));
+        });
+}
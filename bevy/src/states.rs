@@ -0,0 +1,341 @@
+//! Where the app is, from the main menu through a game to reviewing it, and the screens shown
+//! over the board on the way. Each screen's entities belong to its state and go with it.
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+
+use crate::analysis::{text_style, MOVE_COLOR, MOVE_HOVER_COLOR};
+use crate::consts::{PANEL_COLOR, PANEL_WIDTH};
+use crate::editor::PositionEditor;
+use crate::engine::EngineProcess;
+use crate::game::{Game, Players};
+use crate::keyboard::MoveEntry;
+use crate::marks::Marks;
+use crate::review::{export, start_review, ReviewState};
+use crate::rules::Outcome;
+use crate::session::{ContinueGameEvent, LastGame};
+use crate::setup::{begin_game, GameConfig, NewGameEvent};
+
+pub const OVERLAY_COLOR: Color = Color::srgba(0., 0., 0., 0.6);
+
+pub struct StatesPlugin;
+
+impl Plugin for StatesPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_state::<AppState>()
+            .enable_state_scoped_entities::<AppState>()
+            .add_systems(OnEnter(AppState::MainMenu), setup_menu)
+            .add_systems(OnEnter(AppState::Paused), setup_pause)
+            .add_systems(OnEnter(AppState::GameOver), setup_game_over)
+            .add_systems(Update, menu_buttons.run_if(in_state(AppState::MainMenu)))
+            .add_systems(Update, toggle_pause.run_if(in_state(AppState::Playing).or_else(in_state(AppState::Paused))))
+            .add_systems(Update, end_game.run_if(in_state(AppState::Playing)))
+            .add_systems(Update, game_over_buttons.run_if(in_state(AppState::GameOver)))
+            .add_systems(Update, main_menu_button.run_if(in_state(AppState::Paused).or_else(in_state(AppState::GameOver))))
+            .add_systems(Update, resume_game.run_if(in_state(AppState::GameOver).or_else(in_state(AppState::Review))));
+    }
+}
+
+#[derive(States, Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum AppState {
+    #[default]
+    MainMenu,
+    Setup,
+    /// Setting up a position by hand.
+    Editor,
+    Playing,
+    /// Clocks stopped and the board out of reach until play resumes.
+    Paused,
+    /// The result is shown over the board.
+    GameOver,
+    /// Going through a finished game.
+    Review,
+    Settings,
+}
+
+#[derive(Component, Copy, Clone, Eq, PartialEq)]
+enum MenuButton {
+    Continue,
+    NewGame,
+    Edit,
+    Settings,
+    Quit,
+}
+
+#[derive(Component, Copy, Clone, Eq, PartialEq)]
+enum GameOverButton {
+    Rematch,
+    Analyze,
+    Export,
+    Close,
+}
+
+#[derive(Component)]
+struct ResumeButton;
+
+/// Leaves the game for the main menu, on the pause and game-over cards.
+#[derive(Component)]
+struct MainMenuButton;
+
+/// Whether a game is on the board to look at: being played, just over, or under review.
+pub fn in_game(state: Res<State<AppState>>) -> bool {
+    matches!(state.get(), AppState::Playing | AppState::GameOver | AppState::Review)
+}
+
+/// How a game ended, in words.
+pub fn describe(outcome: Outcome) -> String {
+    match outcome {
+        Outcome::Checkmate(winner) => format!("{winner:?} wins by checkmate"),
+        Outcome::Timeout(winner) => format!("{winner:?} wins on time"),
+        Outcome::Stalemate => String::from("Draw by stalemate"),
+        Outcome::InsufficientMaterial => String::from("Draw by insufficient material"),
+        Outcome::FiftyMoves => String::from("Draw by the fifty-move rule"),
+        Outcome::Repetition => String::from("Draw by threefold repetition"),
+        Outcome::TimeoutVsInsufficientMaterial => String::from("Draw: time out against insufficient material"),
+        Outcome::Resignation(winner) => format!("{winner:?} wins by resignation"),
+        Outcome::Agreement => String::from("Draw by agreement"),
+    }
+}
+
+fn button(parent: &mut ChildBuilder, asset_server: &AssetServer, label: &str, marker: impl Component) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    padding: UiRect::axes(Val::Px(12.), Val::Px(6.)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: MOVE_COLOR.into(),
+                ..default()
+            },
+            marker,
+        ))
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section(label, text_style(asset_server, 18.)));
+        });
+}
+
+/// Covers the whole window and takes every click, with a card in the middle.
+fn spawn_screen(commands: &mut Commands, state: AppState, background: Color, card: impl FnOnce(&mut ChildBuilder)) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: background.into(),
+                focus_policy: FocusPolicy::Block,
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            StateScoped(state),
+        ))
+        .with_children(|screen| {
+            screen
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(24.)),
+                        row_gap: Val::Px(10.),
+                        ..default()
+                    },
+                    background_color: PANEL_COLOR.into(),
+                    ..default()
+                })
+                .with_children(card);
+        });
+}
+
+pub fn setup_menu(mut commands: Commands, asset_server: Res<AssetServer>, last: Res<LastGame>) {
+    spawn_screen(&mut commands, AppState::MainMenu, PANEL_COLOR, |card| {
+        card.spawn(TextBundle::from_section("Chess", text_style(&asset_server, 48.)));
+        if last.saved.is_some() {
+            button(card, &asset_server, "Continue last game", MenuButton::Continue);
+        }
+        button(card, &asset_server, "New game", MenuButton::NewGame);
+        button(card, &asset_server, "Edit position", MenuButton::Edit);
+        button(card, &asset_server, "Settings", MenuButton::Settings);
+        button(card, &asset_server, "Quit", MenuButton::Quit);
+    });
+}
+
+fn menu_buttons(
+    mut editor: ResMut<PositionEditor>,
+    mut next: ResMut<NextState<AppState>>,
+    mut exit: EventWriter<AppExit>,
+    mut continue_wr: EventWriter<ContinueGameEvent>,
+    mut interactions: Query<(&Interaction, &MenuButton, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    for (interaction, button, mut color) in &mut interactions {
+        match *interaction {
+            Interaction::Pressed => match button {
+                MenuButton::Continue => {
+                    continue_wr.send(ContinueGameEvent);
+                }
+                MenuButton::NewGame => next.set(AppState::Setup),
+                MenuButton::Edit => {
+                    editor.back = AppState::MainMenu;
+                    next.set(AppState::Editor);
+                }
+                MenuButton::Settings => next.set(AppState::Settings),
+                MenuButton::Quit => {
+                    exit.send(AppExit::Success);
+                }
+            },
+            Interaction::Hovered => *color = MOVE_HOVER_COLOR.into(),
+            Interaction::None => *color = MOVE_COLOR.into(),
+        }
+    }
+}
+
+fn setup_pause(mut commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_screen(&mut commands, AppState::Paused, OVERLAY_COLOR, |card| {
+        card.spawn(TextBundle::from_section("Paused", text_style(&asset_server, 36.)));
+        button(card, &asset_server, "Resume", ResumeButton);
+        button(card, &asset_server, "Main menu", MainMenuButton);
+    });
+}
+
+/// P pauses and resumes; so does the button on the pause screen.
+fn toggle_pause(
+    keys: Res<ButtonInput<KeyCode>>,
+    entry: Res<MoveEntry>,
+    state: Res<State<AppState>>,
+    mut next: ResMut<NextState<AppState>>,
+    mut interactions: Query<(&Interaction, &ResumeButton, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    let mut toggle = keys.just_pressed(KeyCode::KeyP) && !entry.focused;
+
+    for (interaction, _, mut color) in &mut interactions {
+        match *interaction {
+            Interaction::Pressed => toggle = true,
+            Interaction::Hovered => *color = MOVE_HOVER_COLOR.into(),
+            Interaction::None => *color = MOVE_COLOR.into(),
+        }
+    }
+
+    if toggle {
+        next.set(if *state.get() == AppState::Paused { AppState::Playing } else { AppState::Paused });
+    }
+}
+
+fn end_game(game: Res<Game>, mut next: ResMut<NextState<AppState>>) {
+    if let Some(outcome) = game.outcome() {
+        info!("game over: {}", describe(outcome));
+        next.set(AppState::GameOver);
+    }
+}
+
+fn main_menu_button(mut next: ResMut<NextState<AppState>>, mut interactions: Query<(&Interaction, &MainMenuButton, &mut BackgroundColor), Changed<Interaction>>) {
+    for (interaction, _, mut color) in &mut interactions {
+        match *interaction {
+            Interaction::Pressed => next.set(AppState::MainMenu),
+            Interaction::Hovered => *color = MOVE_HOVER_COLOR.into(),
+            Interaction::None => *color = MOVE_COLOR.into(),
+        }
+    }
+}
+
+/// A move taken back after the end, or a new game, means play goes on.
+fn resume_game(game: Res<Game>, mut next: ResMut<NextState<AppState>>) {
+    if game.is_changed() && game.outcome().is_none() {
+        next.set(AppState::Playing);
+    }
+}
+
+/// The result above the board, leaving the board itself in view.
+fn setup_game_over(mut commands: Commands, asset_server: Res<AssetServer>, game: Res<Game>) {
+    let result = game.outcome().map_or(String::new(), |outcome| format!("{}  {}", outcome.result(), describe(outcome)));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(16.),
+                    left: Val::Px(0.),
+                    right: Val::Px(PANEL_WIDTH),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+            StateScoped(AppState::GameOver),
+        ))
+        .with_children(|row| {
+            row.spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    padding: UiRect::all(Val::Px(12.)),
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: PANEL_COLOR.into(),
+                focus_policy: FocusPolicy::Block,
+                ..default()
+            })
+            .with_children(|card| {
+                card.spawn(TextBundle::from_section(result, text_style(&asset_server, 22.)));
+                card.spawn(NodeBundle { style: Style { column_gap: Val::Px(6.), ..default() }, ..default() }).with_children(|buttons| {
+                    button(buttons, &asset_server, "Rematch", GameOverButton::Rematch);
+                    button(buttons, &asset_server, "Analyze", GameOverButton::Analyze);
+                    button(buttons, &asset_server, "Export PGN", GameOverButton::Export);
+                    button(buttons, &asset_server, "Close", GameOverButton::Close);
+                    button(buttons, &asset_server, "Main menu", MainMenuButton);
+                });
+            });
+        });
+}
+
+fn game_over_buttons(
+    mut game: ResMut<Game>,
+    mut config: ResMut<GameConfig>,
+    mut players: ResMut<Players>,
+    engine: Option<Res<EngineProcess>>,
+    mut review: ResMut<ReviewState>,
+    marks: Res<Marks>,
+    mut next: ResMut<NextState<AppState>>,
+    mut interactions: Query<(&Interaction, &GameOverButton, &mut BackgroundColor), Changed<Interaction>>,
+    mut new_game_wr: EventWriter<NewGameEvent>,
+) {
+    for (interaction, button, mut color) in &mut interactions {
+        match *interaction {
+            Interaction::Pressed => match button {
+                GameOverButton::Rematch => {
+                    // Same game again with the sides swapped.
+                    config.swap_sides(&players);
+
+                    match begin_game(&config, engine.is_some(), &mut game, &mut players) {
+                        Ok(()) => {
+                            new_game_wr.send(NewGameEvent);
+                            next.set(AppState::Playing);
+                        }
+                        Err(err) => {
+                            warn!("no rematch: {err}");
+                            next.set(AppState::Setup);
+                        }
+                    }
+                }
+                GameOverButton::Analyze => {
+                    start_review(&game, &mut review);
+                    next.set(AppState::Review);
+                }
+                GameOverButton::Export => export(&game, &config, &marks, &mut review),
+                GameOverButton::Close => next.set(AppState::Review),
+            },
+            Interaction::Hovered => *color = MOVE_HOVER_COLOR.into(),
+            Interaction::None => *color = MOVE_COLOR.into(),
+        }
+    }
+}
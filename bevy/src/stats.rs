@@ -0,0 +1,98 @@
+/// Results from the first engine's point of view.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Tally {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+/// Sequential probability ratio test of `elo0` (H0) against `elo1` (H1).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SprtStatus {
+    Continue,
+    AcceptH0,
+    AcceptH1,
+}
+
+/// Expected score of a player `elo` points stronger than the opponent.
+pub fn expected_score(elo: f64) -> f64 {
+    1. / (1. + 10f64.powf(-elo / 400.))
+}
+
+pub fn elo_from_score(score: f64) -> f64 {
+    -400. * (1. / score - 1.).log10()
+}
+
+impl Tally {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Points per game, draws counting half.
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.) / self.games().max(1) as f64
+    }
+
+    /// Variance of a single game's result around the mean score.
+    fn variance(&self) -> f64 {
+        let n = self.games().max(1) as f64;
+        let s = self.score();
+
+        (self.wins as f64 * (1. - s).powi(2) + self.draws as f64 * (0.5 - s).powi(2) + self.losses as f64 * s.powi(2)) / n
+    }
+
+    /// Elo difference and its 95% error margin; `None` while the score is still 0% or 100%.
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let s = self.score();
+        if self.games() == 0 || s <= 0. || s >= 1. {
+            return None;
+        }
+
+        let deviation = 1.959964 * (self.variance() / self.games() as f64).sqrt();
+        let low = elo_from_score((s - deviation).max(1e-6));
+        let high = elo_from_score((s + deviation).min(1. - 1e-6));
+
+        Some((elo_from_score(s), (high - low) / 2.))
+    }
+}
+
+impl Sprt {
+    /// Log-likelihood ratio bounds: H0 is accepted below the first, H1 above the second.
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1. - self.alpha)).ln(), ((1. - self.beta) / self.alpha).ln())
+    }
+
+    /// Generalised SPRT log-likelihood ratio under the normal approximation.
+    pub fn llr(&self, tally: &Tally) -> f64 {
+        let variance = tally.variance();
+        if tally.games() == 0 || variance == 0. {
+            return 0.;
+        }
+
+        let s0 = expected_score(self.elo0);
+        let s1 = expected_score(self.elo1);
+
+        tally.games() as f64 * (s1 - s0) * (2. * tally.score() - s0 - s1) / (2. * variance)
+    }
+
+    pub fn status(&self, tally: &Tally) -> SprtStatus {
+        let (lower, upper) = self.bounds();
+        let llr = self.llr(tally);
+
+        if llr <= lower {
+            SprtStatus::AcceptH0
+        } else if llr >= upper {
+            SprtStatus::AcceptH1
+        } else {
+            SprtStatus::Continue
+        }
+    }
+}
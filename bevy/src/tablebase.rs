@@ -0,0 +1,308 @@
+//! Win/draw/loss for every position with three men, built by retrograde analysis
+//! the first time a material combination is probed.
+
+use std::sync::OnceLock;
+
+use crate::piece::{Kind, PieceColor};
+use crate::rules::{Position, Square};
+
+/// Game-theoretic value for the side to move, ignoring the fifty-move rule.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Wdl {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// Tables are stored with the extra man on White's side; Black's are probed mirrored.
+#[derive(Default)]
+pub struct Tablebase {
+    queen: OnceLock<Vec<u8>>,
+    rook: OnceLock<Vec<u8>>,
+    pawn: OnceLock<Vec<u8>>,
+}
+
+/// White king, black king and White's extra man, as square indices `8 * rank + file`.
+#[derive(Copy, Clone)]
+struct Men {
+    white_to_move: bool,
+    wk: u8,
+    bk: u8,
+    x: u8,
+}
+
+const SIZE: usize = 2 * 64 * 64 * 64;
+
+const UNKNOWN: u8 = 0;
+const WIN: u8 = 1;
+const LOSS: u8 = 2;
+const DRAW: u8 = 3;
+const ILLEGAL: u8 = 4;
+
+const KING_STEPS: [(i8, i8); 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+const ROOK_DIRS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+impl Tablebase {
+    /// `None` unless the position has at most three men.
+    pub fn probe(&self, pos: &Position) -> Option<Wdl> {
+        let mut others = pos.pieces().filter(|&(_, _, k)| k != Kind::King);
+
+        let Some((sq, color, kind)) = others.next() else { return Some(Wdl::Draw); };
+        if others.next().is_some() {
+            return None;
+        }
+
+        let table = match kind {
+            Kind::Queen => self.queen(),
+            Kind::Rook => self.rook(),
+            Kind::Pawn => self.pawn(),
+            Kind::Knight | Kind::Bishop => return Some(Wdl::Draw),
+            Kind::King => unreachable!(),
+        };
+
+        let flip = |(f, r): Square| if color == PieceColor::White { 8 * r + f } else { 8 * (7 - r) + f };
+        let men = Men {
+            white_to_move: pos.turn == color,
+            wk: flip(pos.king(color)?),
+            bk: flip(pos.king(color.other())?),
+            x: flip(sq),
+        };
+
+        match table[men.index()] {
+            WIN => Some(Wdl::Win),
+            LOSS => Some(Wdl::Loss),
+            DRAW => Some(Wdl::Draw),
+            _ => None,
+        }
+    }
+
+    fn queen(&self) -> &[u8] {
+        self.queen.get_or_init(|| generate(Kind::Queen, None))
+    }
+
+    fn rook(&self) -> &[u8] {
+        self.rook.get_or_init(|| generate(Kind::Rook, None))
+    }
+
+    fn pawn(&self) -> &[u8] {
+        self.pawn.get_or_init(|| generate(Kind::Pawn, Some((self.queen(), self.rook()))))
+    }
+}
+
+impl Men {
+    fn index(self) -> usize {
+        (((self.white_to_move as usize * 64 + self.wk as usize) * 64 + self.bk as usize) * 64) + self.x as usize
+    }
+
+    fn from_index(i: usize) -> Self {
+        Self {
+            white_to_move: i / (64 * 64 * 64) == 1,
+            wk: (i / (64 * 64) % 64) as u8,
+            bk: (i / 64 % 64) as u8,
+            x: (i % 64) as u8,
+        }
+    }
+}
+
+/// Moves and attacks for one material combination, with the attack sets precomputed.
+struct Geometry {
+    kind: Kind,
+    kings: [u64; 64],
+    /// Squares White's man on `x` attacks with White's king on `wk`, at `64 * x + wk`.
+    attacks: Vec<u64>,
+}
+
+impl Geometry {
+    fn new(kind: Kind) -> Self {
+        let mut kings = [0; 64];
+        for (sq, mask) in kings.iter_mut().enumerate() {
+            *mask = steps(sq as u8, &KING_STEPS).fold(0, |mask, to| mask | bit(to));
+        }
+
+        let attacks = (0..64 * 64).map(|i| attack_mask(kind, (i / 64) as u8, (i % 64) as u8)).collect();
+
+        Self { kind, kings, attacks }
+    }
+
+    fn attacked(&self, men: Men) -> u64 {
+        self.attacks[64 * men.x as usize + men.wk as usize]
+    }
+
+    fn legal(&self, men: Men) -> bool {
+        if men.wk == men.bk || men.wk == men.x || men.bk == men.x || self.kings[men.wk as usize] & bit(men.bk) != 0 {
+            return false;
+        }
+
+        if self.kind == Kind::Pawn && !(1..7).contains(&(men.x / 8)) {
+            return false;
+        }
+
+        // Black can't be left in check with White to move.
+        !men.white_to_move || !self.in_check(men)
+    }
+
+    fn in_check(&self, men: Men) -> bool {
+        self.attacked(men) & bit(men.bk) != 0
+    }
+
+    /// Legal black king moves, taking White's man if it is left undefended.
+    fn black_moves(&self, men: Men) -> u32 {
+        let guarded = self.kings[men.wk as usize] | bit(men.wk);
+        let quiet = self.kings[men.bk as usize] & !guarded & !self.attacked(men) & !bit(men.x);
+        let capture = self.kings[men.bk as usize] & !guarded & bit(men.x);
+
+        (quiet | capture).count_ones()
+    }
+
+    /// White-to-move positions whose move leads here.
+    fn white_predecessors(&self, men: Men, mut visit: impl FnMut(Men)) {
+        let empty = |sq: u8| sq != men.wk && sq != men.bk && sq != men.x;
+        let mut pred = |men: Men| {
+            if self.legal(men) {
+                visit(men);
+            }
+        };
+
+        for from in steps(men.wk, &KING_STEPS).filter(|&sq| empty(sq)) {
+            pred(Men { white_to_move: true, wk: from, ..men });
+        }
+
+        if self.kind == Kind::Pawn {
+            let (f, r) = (men.x % 8, men.x / 8);
+
+            if r >= 2 && empty(men.x - 8) {
+                pred(Men { white_to_move: true, x: men.x - 8, ..men });
+
+                if r == 3 && empty(men.x - 16) {
+                    pred(Men { white_to_move: true, x: 8 + f, ..men });
+                }
+            }
+            return;
+        }
+
+        for &dir in dirs(self.kind) {
+            for from in ray(men.x, dir).take_while(|&sq| empty(sq)) {
+                pred(Men { white_to_move: true, x: from, ..men });
+            }
+        }
+    }
+
+    /// Black-to-move positions whose king move leads here.
+    fn black_predecessors(&self, men: Men, mut visit: impl FnMut(Men)) {
+        for from in steps(men.bk, &KING_STEPS).filter(|&sq| sq != men.wk && sq != men.x) {
+            let pred = Men { white_to_move: false, bk: from, ..men };
+
+            if self.legal(pred) {
+                visit(pred);
+            }
+        }
+    }
+}
+
+fn generate(kind: Kind, promotions: Option<(&[u8], &[u8])>) -> Vec<u8> {
+    let geometry = Geometry::new(kind);
+    let mut table = vec![UNKNOWN; SIZE];
+    let mut replies = vec![0u8; SIZE];
+    let mut queue = Vec::new();
+
+    for (i, entry) in table.iter_mut().enumerate() {
+        let men = Men::from_index(i);
+
+        if !geometry.legal(men) {
+            *entry = ILLEGAL;
+        } else if !men.white_to_move {
+            replies[i] = geometry.black_moves(men) as u8;
+
+            if replies[i] == 0 {
+                *entry = if geometry.in_check(men) { LOSS } else { DRAW };
+            }
+        } else if let Some((queen, rook)) = promotions {
+            // Promotions leave this table, so their values are looked up instead of propagated.
+            let to = men.x + 8;
+            if men.x / 8 == 6 && to != men.wk && to != men.bk {
+                let after = Men { white_to_move: false, x: to, ..men }.index();
+
+                if queen[after] == LOSS || rook[after] == LOSS {
+                    *entry = WIN;
+                }
+            }
+        }
+
+        if *entry == WIN || *entry == LOSS {
+            queue.push(i);
+        }
+    }
+
+    while let Some(i) = queue.pop() {
+        let men = Men::from_index(i);
+
+        if men.white_to_move {
+            geometry.black_predecessors(men, |pred| {
+                let j = pred.index();
+                if table[j] != UNKNOWN {
+                    return;
+                }
+
+                replies[j] -= 1;
+                if replies[j] == 0 {
+                    table[j] = LOSS;
+                    queue.push(j);
+                }
+            });
+        } else {
+            geometry.white_predecessors(men, |pred| {
+                let j = pred.index();
+                if table[j] == UNKNOWN {
+                    table[j] = WIN;
+                    queue.push(j);
+                }
+            });
+        }
+    }
+
+    // A lone king never wins, so whatever could not be forced is a draw.
+    for entry in &mut table {
+        if *entry == UNKNOWN {
+            *entry = DRAW;
+        }
+    }
+
+    table
+}
+
+fn bit(sq: u8) -> u64 {
+    1 << sq
+}
+
+fn step(sq: u8, (df, dr): (i8, i8)) -> Option<u8> {
+    let f = (sq % 8) as i8 + df;
+    let r = (sq / 8) as i8 + dr;
+
+    ((0..8).contains(&f) && (0..8).contains(&r)).then(|| (8 * r + f) as u8)
+}
+
+fn steps(sq: u8, deltas: &'static [(i8, i8)]) -> impl Iterator<Item = u8> {
+    deltas.iter().filter_map(move |&d| step(sq, d))
+}
+
+fn ray(sq: u8, dir: (i8, i8)) -> impl Iterator<Item = u8> {
+    std::iter::successors(step(sq, dir), move |&sq| step(sq, dir))
+}
+
+fn dirs(kind: Kind) -> &'static [(i8, i8)] {
+    match kind {
+        Kind::Rook => &ROOK_DIRS,
+        _ => &KING_STEPS,
+    }
+}
+
+/// Squares White's `kind` on `from` attacks, with White's king as the only blocker.
+fn attack_mask(kind: Kind, from: u8, blocker: u8) -> u64 {
+    match kind {
+        Kind::Pawn => steps(from, &[(1, 1), (-1, 1)]).fold(0, |mask, sq| mask | bit(sq)),
+        _ => dirs(kind)
+            .iter()
+            .flat_map(|&dir| ray(from, dir).take_while(move |&sq| sq != blocker))
+            .fold(0, |mask, sq| mask | bit(sq)),
+    }
+}
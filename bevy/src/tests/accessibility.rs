@@ -0,0 +1,33 @@
+#[cfg(test)]
+mod test {
+    use crate::accessibility::*;
+    use crate::rules::*;
+
+    fn said(fen: &str, mv: &str) -> String {
+        describe_move(&Position::from_fen(fen).unwrap(), mv.parse().unwrap())
+    }
+
+    #[test]
+    fn moves_in_words() {
+        assert_eq!(said(START_FEN, "g1f3"), "White knight g1 to f3");
+        assert_eq!(said("4k3/8/8/4p3/8/5N2/8/4K3 w - - 0 1", "f3e5"), "White knight f3 takes e5");
+        assert_eq!(said("4k3/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"), "White castles kingside");
+        assert_eq!(said("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2", "e5d6"), "White pawn e5 takes d6 en passant");
+        assert_eq!(said("8/1P2k3/8/8/8/8/8/4K3 w - - 0 1", "b7b8q"), "White pawn b7 to b8, promotes to queen");
+        assert_eq!(said("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8"), "White rook a1 to a8, check");
+        assert_eq!(said("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2", "d8h4"), "Black queen d8 to h4, checkmate");
+    }
+
+    #[test]
+    fn positions_in_words() {
+        let pos = Position::from_fen("4k3/8/8/8/8/8/3PP3/4K2R b K - 0 1").unwrap();
+
+        assert_eq!(describe_square(&pos, (4, 0)), "e1, white king");
+        assert_eq!(describe_square(&pos, (4, 3)), "e4, empty");
+        assert_eq!(
+            describe_by_rank(&pos),
+            "Rank 8: black king e8. Rank 2: white pawn d2, white pawn e2. Rank 1: white king e1, white rook h1. Black to move.",
+        );
+        assert_eq!(describe_by_piece(&pos), "White: king e1, rook h1, pawns d2 e2. Black: king e8. Black to move.");
+    }
+}
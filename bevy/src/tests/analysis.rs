@@ -0,0 +1,30 @@
+#[cfg(test)]
+mod test {
+    use crate::analysis::*;
+    use crate::piece::PieceColor;
+    use crate::uci::Score;
+
+    #[test]
+    fn scores_are_shown_from_whites_side() {
+        assert_eq!(white_score(PieceColor::Black, Score::Cp(50)), Score::Cp(-50));
+        assert_eq!(white_score(PieceColor::Black, Score::Mate(-2)), Score::Mate(2));
+        assert_eq!(white_score(PieceColor::White, Score::Cp(50)), Score::Cp(50));
+    }
+
+    #[test]
+    fn eval_bar_share() {
+        assert_eq!(white_share(Score::Cp(0)), 0.5);
+        assert!(white_share(Score::Cp(300)) > 0.7);
+        assert!(white_share(Score::Cp(-300)) < 0.3);
+        assert_eq!(white_share(Score::Mate(3)), 1.);
+        assert_eq!(white_share(Score::Mate(-1)), 0.);
+    }
+
+    #[test]
+    fn score_labels() {
+        assert_eq!(score_text(Score::Cp(35)), "+0.35");
+        assert_eq!(score_text(Score::Cp(-120)), "-1.20");
+        assert_eq!(score_text(Score::Mate(3)), "#3");
+        assert_eq!(score_text(Score::Mate(-2)), "-#2");
+    }
+}
@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::AtomicBool;
+
+    use crate::annotate::*;
+    use crate::piece::PieceColor;
+    use crate::rules::*;
+    use crate::search::Limits;
+    use crate::uci::Score;
+
+    fn moves(list: &[&str]) -> Vec<Move> {
+        list.iter().map(|m| m.parse().unwrap()).collect()
+    }
+
+    fn eval(cp: i32, best: &str) -> PositionEval {
+        PositionEval { score: Score::Cp(cp), best: Some(best.parse().unwrap()) }
+    }
+
+    /// 2...e5 throws away four pawns by these made-up numbers, 2...c5 was the move.
+    fn blunder() -> GameReview {
+        let evals = vec![eval(20, "e2e4"), eval(-20, "c7c5"), eval(400, "d2d4"), eval(-390, "b8c6")];
+        review(&Position::default(), &moves(&["e2e4", "e7e5", "g1f3"]), evals)
+    }
+
+    #[test]
+    fn judgements() {
+        assert_eq!(judge(0.5, 0.48), None);
+        assert_eq!(judge(0.5, 0.44), Some(Judgement::Inaccuracy));
+        assert_eq!(judge(0.5, 0.39), Some(Judgement::Mistake));
+        assert_eq!(judge(0.5, 0.2), Some(Judgement::Blunder));
+        assert_eq!(judge(0.3, 0.9), None);
+    }
+
+    #[test]
+    fn accuracy_falls_with_the_drop() {
+        assert!((move_accuracy(0.5, 0.5) - 100.).abs() < 0.01);
+        assert!((move_accuracy(0.4, 0.6) - 100.).abs() < 0.01);
+        assert!(move_accuracy(0.5, 0.4) < 70.);
+        assert_eq!(move_accuracy(1., 0.), 0.);
+    }
+
+    #[test]
+    fn flags_the_blunder() {
+        let review = blunder();
+
+        assert_eq!(review.moves.len(), 3);
+        assert_eq!(review.moves[0].judgement, None);
+        assert_eq!(review.moves[1].judgement, Some(Judgement::Blunder));
+        assert_eq!(review.moves[2].judgement, None);
+
+        assert!(review.accuracy(PieceColor::White).unwrap() > 95.);
+        assert!(review.accuracy(PieceColor::Black).unwrap() < 40.);
+    }
+
+    #[test]
+    fn best_moves_are_never_flagged() {
+        let evals = vec![eval(0, "e2e4"), eval(-300, "e7e5")];
+        let review = review(&Position::default(), &moves(&["e2e4"]), evals);
+
+        assert_eq!(review.moves[0].judgement, None);
+    }
+
+    #[test]
+    fn annotated_pgn() {
+        let game = blunder().pgn("*");
+
+        assert_eq!(game.annotations[0].comment.as_deref(), Some("[%eval 0.20]"));
+        assert_eq!(game.annotations[1].nags, [4]);
+        assert_eq!(game.annotations[1].comment.as_deref(), Some("[%eval 4.00] Blunder. c5 was best."));
+        assert_eq!(game.annotations[2].comment.as_deref(), Some("[%eval 3.90]"));
+        assert!(game.tag("BlackAccuracy").is_some());
+
+        let text = crate::pgn::write(&game);
+        assert!(text.contains("1. e4 {[%eval 0.20]} 1... e5 $4 {[%eval 4.00] Blunder. c5 was best.} 2. Nf3"), "{text}");
+    }
+
+    #[test]
+    fn evaluates_through_mate() {
+        let fools_mate = moves(&["f2f3", "e7e5", "g2g4", "d8h4"]);
+        let limits = Limits { depth: Some(2), ..Limits::default() };
+        let mut done = Vec::new();
+
+        let evals = evaluate(&Position::default(), &fools_mate, &limits, &AtomicBool::new(false), |n| done.push(n)).unwrap();
+
+        assert_eq!(done, [1, 2, 3, 4, 5]);
+        assert_eq!(evals[3].best, Some("d8h4".parse().unwrap()));
+        assert_eq!(evals[4], PositionEval { score: Score::Mate(0), best: None });
+
+        let review = review(&Position::default(), &fools_mate, evals);
+        assert_eq!(review.moves[2].judgement, Some(Judgement::Blunder));
+        assert_eq!(review.moves[3].after, 1.);
+        assert_eq!(review.pgn("0-1").annotations[3].comment, None);
+    }
+
+    #[test]
+    fn stopped_evaluation() {
+        assert_eq!(evaluate(&Position::default(), &[], &Limits::default(), &AtomicBool::new(true), |_| {}), None);
+    }
+
+    #[test]
+    fn eval_comments() {
+        assert_eq!(eval_text(Score::Cp(-135)), "-1.35");
+        assert_eq!(eval_text(Score::Mate(3)), "#3");
+        assert_eq!(eval_text(Score::Mate(-2)), "#-2");
+    }
+}
@@ -0,0 +1,41 @@
+#[cfg(test)]
+mod test {
+    use bevy::math::{Vec2, Vec3};
+
+    use crate::board::*;
+
+    #[test]
+    fn squares_map_to_the_screen_and_back() {
+        for (flipped, square_size) in [(false, 75.), (true, 75.), (false, 41.5)] {
+            let view = BoardView { flipped, square_size };
+
+            for sq in [(0, 0), (7, 7), (4, 1), (2, 6)] {
+                let centre = view.to_world(sq, 0.).truncate();
+                let corner = centre + Vec2::splat(square_size / 2. - 1.);
+
+                assert_eq!(view.square_at(centre), Some(sq));
+                assert_eq!(view.square_at(corner), Some(sq));
+            }
+        }
+    }
+
+    #[test]
+    fn flipped_board_puts_black_at_the_bottom() {
+        let view = BoardView::default();
+        let flipped = BoardView { flipped: true, ..BoardView::default() };
+        let bottom_left = Vec2::splat(-3.9 * view.square_size);
+
+        assert_eq!(view.square_at(bottom_left), Some((0, 0)));
+        assert_eq!(flipped.square_at(bottom_left), Some((7, 7)));
+        assert_eq!(view.square_at(Vec2::new(4.1 * view.square_size, 0.)), None);
+    }
+
+    #[test]
+    fn board_is_centred_at_any_size() {
+        let view = BoardView { square_size: 100., ..BoardView::default() };
+
+        assert_eq!(view.to_world((0, 0), 1.), Vec3::new(-350., -350., 1.));
+        assert_eq!(view.to_world((7, 7), 1.), Vec3::new(350., 350., 1.));
+        assert_eq!(view.scale(), 100. / 75.);
+    }
+}
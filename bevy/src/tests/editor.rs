@@ -0,0 +1,44 @@
+#[cfg(test)]
+mod test {
+    use crate::editor::PositionEditor;
+    use crate::piece::{Kind, PieceColor};
+    use crate::rules::Position;
+
+    #[test]
+    fn painting_puts_pieces_down_and_takes_them_off() {
+        let mut editor = PositionEditor::default();
+        editor.position = Position::empty();
+        editor.brush = Some((PieceColor::Black, Kind::Queen));
+
+        editor.paint((3, 4));
+        assert_eq!(editor.position.piece_at((3, 4)), Some((PieceColor::Black, Kind::Queen)));
+
+        editor.brush = Some((PieceColor::White, Kind::Knight));
+        editor.paint((3, 4));
+        assert_eq!(editor.position.piece_at((3, 4)), Some((PieceColor::White, Kind::Knight)));
+
+        // The same piece again clears the square, as does the eraser.
+        editor.paint((3, 4));
+        assert_eq!(editor.position.piece_at((3, 4)), None);
+
+        editor.paint((2, 2));
+        editor.brush = None;
+        editor.paint((2, 2));
+        assert_eq!(editor.position.piece_at((2, 2)), None);
+    }
+
+    #[test]
+    fn edits_drop_rights_the_board_no_longer_allows() {
+        let mut editor = PositionEditor::default();
+        editor.position = Position::from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 2").unwrap();
+
+        editor.erase((7, 0));
+        assert!(!editor.position.castling.white_king);
+        assert!(editor.position.castling.white_queen && editor.position.castling.black_king);
+        assert_eq!(editor.position.en_passant, Some((3, 5)));
+
+        editor.erase((3, 4));
+        assert_eq!(editor.position.en_passant, None);
+        assert!(editor.position.validate().is_ok());
+    }
+}
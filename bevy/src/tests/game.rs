@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod test {
+    use crate::game::*;
+    use crate::piece::{Kind, PieceColor};
+    use crate::rules::*;
+    use crate::tray::material;
+
+    fn play(game: &mut Game, moves: &[&str]) {
+        for mv in moves {
+            game.play(mv.parse().unwrap());
+        }
+    }
+
+    #[test]
+    fn history_replays_to_any_ply() {
+        let mut game = Game::default();
+        play(&mut game, &["e2e4", "e7e5", "g1f3"]);
+
+        assert_eq!(game.position_at(0), game.start);
+        assert_eq!(game.position_at(3), game.position);
+        assert_eq!(game.position_at(2).fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2");
+    }
+
+    #[test]
+    fn flag_ends_the_game_once() {
+        let mut game = Game::default();
+        play(&mut game, &["e2e4"]);
+
+        game.flag(PieceColor::Black);
+        assert_eq!(game.outcome(), Some(Outcome::Timeout(PieceColor::White)));
+
+        game.flag(PieceColor::White);
+        assert_eq!(game.outcome(), Some(Outcome::Timeout(PieceColor::White)));
+    }
+
+    #[test]
+    fn undo_keeps_moves_for_redo() {
+        let mut game = Game::default();
+        play(&mut game, &["e2e4", "e7e5", "g1f3"]);
+
+        assert_eq!(game.undo(), Some("g1f3".parse().unwrap()));
+        assert_eq!(game.undo(), Some("e7e5".parse().unwrap()));
+        assert_eq!(game.position, game.position_at(1));
+        assert_eq!(game.keys().last(), Some(&game.position.key()));
+        assert_eq!(game.undone().collect::<Vec<_>>(), ["e7e5".parse().unwrap(), "g1f3".parse().unwrap()]);
+
+        play(&mut game, &["e7e5"]);
+        assert_eq!(game.undone().count(), 1);
+
+        play(&mut game, &["b1c3"]);
+        assert_eq!(game.undone().count(), 0);
+    }
+
+    #[test]
+    fn undo_lifts_a_flag() {
+        let mut game = Game::default();
+        play(&mut game, &["e2e4"]);
+        game.flag(PieceColor::Black);
+
+        game.undo();
+        assert_eq!(game.outcome(), None);
+        assert_eq!(game.undo(), None);
+    }
+
+    #[test]
+    fn resigning_and_draw_offers() {
+        let mut game = Game::default();
+        game.resign(PieceColor::White);
+        assert_eq!(game.outcome(), Some(Outcome::Resignation(PieceColor::Black)));
+        assert_eq!(game.outcome().unwrap().result(), "0-1");
+
+        // An offer stands through the offerer's own move and lapses when the other side moves on.
+        let mut game = Game::default();
+        assert!(!game.offer_draw(PieceColor::White));
+        play(&mut game, &["e2e4"]);
+        assert_eq!(game.draw_offer(), Some(PieceColor::White));
+        play(&mut game, &["e7e5"]);
+        assert_eq!(game.draw_offer(), None);
+
+        assert!(!game.offer_draw(PieceColor::White));
+        assert!(game.offer_draw(PieceColor::Black));
+        assert_eq!(game.outcome(), Some(Outcome::Agreement));
+
+        game.undo();
+        assert_eq!(game.outcome(), None);
+    }
+
+    #[test]
+    fn captures_and_material() {
+        let mut game = Game::default();
+        // Includes an en passant capture: the pawn taken is not on the target square.
+        play(&mut game, &["e2e4", "d7d5", "e4d5", "d8d5", "b1c3", "d5a5", "d2d4", "c7c5", "d4d5", "e7e5", "d5e6"]);
+
+        assert_eq!(game.captures(), [
+            (PieceColor::Black, Kind::Pawn),
+            (PieceColor::White, Kind::Pawn),
+            (PieceColor::Black, Kind::Pawn),
+        ]);
+        assert_eq!(material(&game, PieceColor::White) - material(&game, PieceColor::Black), 1);
+    }
+
+    #[test]
+    fn flag_against_a_bare_king_is_a_draw() {
+        let mut game = Game::new(Position::from_fen("8/8/4k3/8/8/8/3PK3/8 w - - 0 1").unwrap());
+
+        game.flag(PieceColor::Black);
+        assert_eq!(game.outcome(), Some(Outcome::Timeout(PieceColor::White)));
+
+        let mut game = Game::new(Position::from_fen("8/8/4k3/8/8/8/3PK3/8 w - - 0 1").unwrap());
+        game.flag(PieceColor::White);
+        assert_eq!(game.outcome(), Some(Outcome::TimeoutVsInsufficientMaterial));
+        assert_eq!(game.outcome().unwrap().result(), "1/2-1/2");
+    }
+}
@@ -0,0 +1,45 @@
+#[cfg(test)]
+mod test {
+    use crate::marks::*;
+    use crate::pgn::{self, PgnGame};
+    use crate::rules::*;
+
+    #[test]
+    fn marks_toggle_and_recolour() {
+        let pos = Position::default();
+        let mut marks = Marks::default();
+
+        marks.toggle(&pos, Mark::Circle(MarkColor::Green, (4, 3)));
+        marks.toggle(&pos, Mark::Arrow(MarkColor::Red, (6, 0), (5, 2)));
+        marks.toggle(&pos, Mark::Circle(MarkColor::Blue, (4, 3)));
+        assert_eq!(marks.get(&pos), [Mark::Circle(MarkColor::Blue, (4, 3)), Mark::Arrow(MarkColor::Red, (6, 0), (5, 2))]);
+
+        marks.toggle(&pos, Mark::Arrow(MarkColor::Red, (6, 0), (5, 2)));
+        assert_eq!(marks.get(&pos), [Mark::Circle(MarkColor::Blue, (4, 3))]);
+
+        marks.clear(&pos);
+        assert!(marks.get(&pos).is_empty());
+    }
+
+    #[test]
+    fn marks_go_into_pgn_comments() {
+        let mut game = PgnGame::new(Position::default());
+        let mut pos = Position::default();
+        let mut marks = Marks::default();
+
+        for mv in ["e2e4", "e7e5"] {
+            let mv: Move = mv.parse().unwrap();
+            pos.make_move(mv);
+            game.moves.push(mv);
+        }
+
+        marks.toggle(&pos, Mark::Circle(MarkColor::Red, (4, 4)));
+        marks.toggle(&pos, Mark::Circle(MarkColor::Green, (3, 3)));
+        marks.toggle(&pos, Mark::Arrow(MarkColor::Green, (6, 0), (5, 2)));
+        marks.annotate(&mut game);
+
+        assert_eq!(game.annotations[0].comment, None);
+        assert_eq!(game.annotations[1].comment.as_deref(), Some("[%csl Re5,Gd4][%cal Gg1f3]"));
+        assert!(pgn::write(&game).contains("1. e4 e5 {[%csl Re5,Gd4][%cal Gg1f3]}"));
+    }
+}
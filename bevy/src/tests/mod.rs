@@ -0,0 +1,21 @@
+mod accessibility;
+mod analysis;
+mod annotate;
+mod board;
+mod editor;
+mod game;
+mod marks;
+mod pgn;
+mod premove;
+mod rules;
+mod san;
+mod search;
+mod session;
+mod settings;
+mod setup;
+mod stats;
+mod tablebase;
+mod theme;
+mod timer;
+mod tournament;
+mod uci;
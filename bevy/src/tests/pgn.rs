@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod test {
+    use crate::pgn::*;
+    use crate::rules::*;
+
+    const GAME: &str = r#"[Event "Casual game"]
+[White "Anderssen"]
+[Black "Kieseritzky"]
+[Result "1-0"]
+
+1. e4 e5 2. f4 exf4 3. Bc4 Qh4+ {the queen comes out early} 4. Kf1 b5?! (4... Nf6 5. Nf3)
+5. Bxb5 $2 Nf6 ; a line comment
+6. Nf3 Qh6 1-0
+
+[Event "Second"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"]
+
+1. e4 Kd7 *
+"#;
+
+    fn moves(list: &[&str]) -> Vec<Move> {
+        list.iter().map(|m| m.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn reads_games() {
+        let games = read(GAME).unwrap();
+        assert_eq!(games.len(), 2);
+
+        let first = &games[0];
+        assert_eq!(first.tag("White"), Some("Anderssen"));
+        assert_eq!(first.result, "1-0");
+        assert_eq!(first.moves.len(), 12);
+        assert_eq!(first.moves[..3], moves(&["e2e4", "e7e5", "f2f4"]));
+        assert_eq!(first.annotations[5].comment.as_deref(), Some("the queen comes out early"));
+        assert_eq!(first.annotations[7].nags, [6]);
+        assert_eq!(first.annotations[8].nags, [2]);
+
+        let second = &games[1];
+        assert_eq!(second.start.fen(), "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        assert_eq!(second.moves, moves(&["e2e4", "e8d7"]));
+        assert_eq!(second.result, "*");
+    }
+
+    #[test]
+    fn rejects_illegal_moves() {
+        assert!(read("1. e4 e4 *").is_err());
+        assert!(read("[Event \"Broken\"\n1. e4").is_err());
+    }
+
+    #[test]
+    fn writes_export_format() {
+        let mut game = PgnGame::new(Position::default());
+        game.set_tag("White", "Engine A");
+        game.set_tag("Termination", "adjudication");
+        game.moves = moves(&["e2e4", "e7e5", "g1f3"]);
+        game.annotations = vec![
+            Annotation::default(),
+            Annotation { nags: vec![1], comment: Some(String::from("+0.20/12")) },
+        ];
+        game.result = String::from("1/2-1/2");
+
+        let text = write(&game);
+        let (tags, movetext) = text.split_once("\n\n").unwrap();
+
+        assert_eq!(tags.lines().next(), Some("[Event \"?\"]"));
+        assert!(tags.contains("[Date \"????.??.??\"]\n[Round \"?\"]\n[White \"Engine A\"]\n[Black \"?\"]\n[Result \"1/2-1/2\"]"));
+        assert!(tags.ends_with("[Termination \"adjudication\"]"));
+        assert_eq!(movetext, "1. e4 e5 $1 {+0.20/12} 2. Nf3 1/2-1/2\n");
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut game = PgnGame::new(Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 7").unwrap());
+        game.moves = moves(&["e8d7", "e2e4", "d7e6"]);
+        game.annotations = vec![Annotation { nags: vec![], comment: Some(String::from("a {brace}")) }];
+
+        let text = write(&game);
+        assert!(text.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 7\"]"));
+        assert!(text.contains("7... Kd7 {a {brace)} 8. e4 Ke6 *"));
+
+        let back = read(&text).unwrap().remove(0);
+        assert_eq!(back.start, game.start);
+        assert_eq!(back.moves, game.moves);
+        assert_eq!(back.annotations[0].comment.as_deref(), Some("a {brace)"));
+    }
+
+    #[test]
+    fn long_movetext_wraps() {
+        let mut game = PgnGame::new(Position::default());
+        game.moves = moves(&["g1f3", "g8f6", "f3g1", "f6g8"]).repeat(10);
+
+        assert!(write(&game).lines().all(|line| line.len() <= 80));
+    }
+
+    #[test]
+    fn reads_epd() {
+        let text = "# openings\nrnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 id \"e4\";\n\n4k3/8/8/8/8/8/4P3/4K3 w - - bm e4;\n";
+        let positions = read_epd(text).unwrap();
+
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[0].turn, crate::piece::PieceColor::Black);
+        assert_eq!(positions[1].fen(), "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+    }
+}
@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod test {
+    use crate::piece::{Kind, PieceColor};
+    use crate::premove::{premove_targets, Premoves};
+    use crate::rules::*;
+
+    fn after(moves: &[&str]) -> Position {
+        let mut pos = Position::default();
+        for mv in moves {
+            pos.make_move(mv.parse().unwrap());
+        }
+        pos
+    }
+
+    #[test]
+    fn premoves_queue_on_the_projected_board() {
+        // Black to move; White queues e4 and then e5 with the same pawn.
+        let pos = after(&["g1f3"]);
+        let mut premoves = Premoves::default();
+
+        assert!(premoves.queue(&pos, PieceColor::White, (4, 1), (4, 3)));
+        assert!(premoves.queue(&pos, PieceColor::White, (4, 3), (4, 4)));
+        assert_eq!(premoves.destination((4, 1)), (4, 4));
+        assert_eq!(premoves.origin((4, 4)), (4, 1));
+        assert_eq!(premoves.projected(&pos).piece_at((4, 4)), Some((PieceColor::White, Kind::Pawn)));
+        assert_eq!(premoves.projected(&pos).piece_at((4, 1)), None);
+
+        // Not from an emptied square, not with Black's pieces, not a knight going straight.
+        assert!(!premoves.queue(&pos, PieceColor::White, (4, 1), (4, 2)));
+        assert!(!premoves.queue(&pos, PieceColor::White, (4, 6), (4, 5)));
+        assert!(!premoves.queue(&pos, PieceColor::White, (5, 2), (5, 4)));
+        assert_eq!(premoves.moves.len(), 2);
+    }
+
+    #[test]
+    fn premoves_are_played_while_legal() {
+        let mut premoves = Premoves::default();
+        let pos = after(&["e2e4"]);
+        assert!(premoves.queue(&pos, PieceColor::White, (3, 0), (7, 4)));
+        assert!(premoves.queue(&pos, PieceColor::White, (7, 4), (5, 6)));
+
+        // ...e5 leaves Qh5 legal, the capture on f7 comes a move later.
+        let pos = after(&["e2e4", "e7e5"]);
+        assert_eq!(premoves.next(&pos), Some(Move::new((3, 0), (7, 4))));
+        assert_eq!(premoves.moves.len(), 1);
+
+        // ...g6 blocks the way to f7, so Qxf7 is not legal and the queue goes.
+        let pos = after(&["e2e4", "e7e5", "d1h5", "g7g6"]);
+        assert_eq!(premoves.next(&pos), None);
+        assert!(premoves.moves.is_empty());
+    }
+
+    #[test]
+    fn premove_geometry() {
+        let pos = Position::default();
+
+        let mut pawn = premove_targets(&pos, PieceColor::White, Kind::Pawn, (4, 1));
+        pawn.sort();
+        assert_eq!(pawn, [(3, 2), (4, 2), (4, 3), (5, 2)]);
+
+        // Sliders see through pieces that may have gone by then.
+        assert_eq!(premove_targets(&pos, PieceColor::White, Kind::Rook, (0, 0)).len(), 14);
+        assert!(premove_targets(&pos, PieceColor::White, Kind::Bishop, (2, 0)).contains(&(7, 5)));
+
+        // Castling only while the right is there.
+        assert!(premove_targets(&pos, PieceColor::White, Kind::King, (4, 0)).contains(&(6, 0)));
+        let no_rights = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w kq - 0 1").unwrap();
+        assert!(!premove_targets(&no_rights, PieceColor::White, Kind::King, (4, 0)).contains(&(2, 0)));
+        assert!(premove_targets(&no_rights, PieceColor::Black, Kind::King, (4, 7)).contains(&(2, 7)));
+
+        // A pawn queued onto the last rank becomes a queen.
+        let pos = Position::from_fen("k7/4P3/8/8/8/8/8/K7 b - - 0 1").unwrap();
+        let mut premoves = Premoves::default();
+        assert!(premoves.queue(&pos, PieceColor::White, (4, 6), (4, 7)));
+        assert_eq!(premoves.moves[0], Move::promote((4, 6), (4, 7), Kind::Queen));
+    }
+}
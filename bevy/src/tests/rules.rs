@@ -0,0 +1,205 @@
+#[cfg(test)]
+mod test {
+    use crate::game::Game;
+    use crate::piece::*;
+    use crate::rules::*;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    fn perft(pos: &Position, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        pos.legal_moves()
+            .into_iter()
+            .map(|mv| {
+                let mut next = pos.clone();
+                next.make_move(mv);
+                perft(&next, depth - 1)
+            })
+            .sum()
+    }
+
+    fn play(pos: &mut Position, moves: &[&str]) {
+        for mv in moves {
+            let mv: Move = mv.parse().unwrap();
+            assert!(pos.is_legal(mv), "{mv} should be legal in {}", pos.fen());
+            pos.make_move(mv);
+        }
+    }
+
+    #[test]
+    fn perft_start_position() {
+        let pos = Position::default();
+
+        assert_eq!(perft(&pos, 1), 20);
+        assert_eq!(perft(&pos, 2), 400);
+        assert_eq!(perft(&pos, 3), 8902);
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        let pos = Position::from_fen(KIWIPETE).unwrap();
+
+        assert_eq!(perft(&pos, 1), 48);
+        assert_eq!(perft(&pos, 2), 2039);
+    }
+
+    #[test]
+    fn perft_en_passant_and_promotions() {
+        let pos = Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        assert_eq!(perft(&pos, 3), 2812);
+
+        let pos = Position::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap();
+        assert_eq!(perft(&pos, 2), 264);
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        for fen in [START_FEN, KIWIPETE, "8/8/8/8/8/8/8/K6k b - - 12 40"] {
+            assert_eq!(Position::from_fen(fen).unwrap().fen(), fen);
+        }
+
+        assert!(Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1").is_err());
+        assert!(Position::from_fen("rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_err());
+        assert!(Position::from_fen("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_err());
+        assert!(Position::from_fen("rnbqkbnr/pppppppp/08/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_err());
+        assert!(Position::from_fen(&format!("{}/8/8/8/8/8/8/8 w - - 0 1", "8".repeat(40))).is_err());
+    }
+
+    #[test]
+    fn uci_move_notation() {
+        assert_eq!("e2e4".parse::<Move>().unwrap(), Move::new((4, 1), (4, 3)));
+        assert_eq!("a7a8q".parse::<Move>().unwrap(), Move::promote((0, 6), (0, 7), Kind::Queen));
+        assert_eq!(Move::promote((0, 6), (0, 7), Kind::Knight).to_string(), "a7a8n");
+        assert!("e2e9".parse::<Move>().is_err());
+        assert!("a7a8k".parse::<Move>().is_err());
+    }
+
+    #[test]
+    fn squares_are_lowercase() {
+        assert_eq!(parse_square("h8"), Some((7, 7)));
+        assert_eq!(parse_square("A1"), None);
+        assert_eq!(parse_square("E4"), None);
+        assert!("E2e4".parse::<Move>().is_err());
+        assert!(Position::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - D6 0 1").is_err());
+    }
+
+    #[test]
+    fn castling_moves_the_rook() {
+        let mut pos = Position::from_fen(KIWIPETE).unwrap();
+        let castle: Move = "e1g1".parse().unwrap();
+
+        assert_eq!(pos.castling_rook(castle), Some(((7, 0), (5, 0))));
+        pos.make_move(castle);
+
+        assert_eq!(pos.piece_at((5, 0)), Some((PieceColor::White, Kind::Rook)));
+        assert_eq!(pos.piece_at((6, 0)), Some((PieceColor::White, Kind::King)));
+        assert!(!pos.castling.white_king && !pos.castling.white_queen);
+        assert!(pos.castling.black_king && pos.castling.black_queen);
+    }
+
+    #[test]
+    fn castling_blocked_through_check() {
+        let pos = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K1r1 w Qkq - 0 1").unwrap();
+
+        assert!(!pos.is_legal("e1c1".parse().unwrap()));
+    }
+
+    #[test]
+    fn en_passant_removes_the_pawn() {
+        let mut pos = Position::default();
+        play(&mut pos, &["e2e4", "a7a6", "e4e5", "d7d5"]);
+
+        let ep: Move = "e5d6".parse().unwrap();
+        assert_eq!(pos.en_passant, Some((3, 5)));
+        assert_eq!(pos.captured_square(ep), Some((3, 4)));
+
+        pos.make_move(ep);
+        assert_eq!(pos.piece_at((3, 4)), None);
+    }
+
+    #[test]
+    fn promotion_replaces_the_pawn() {
+        let mut pos = Position::from_fen("8/P6k/8/8/8/8/8/K7 w - - 0 1").unwrap();
+
+        assert!(!pos.is_legal("a7a8".parse().unwrap()));
+        play(&mut pos, &["a7a8n"]);
+        assert_eq!(pos.piece_at((0, 7)), Some((PieceColor::White, Kind::Knight)));
+    }
+
+    #[test]
+    fn fools_mate_is_checkmate() {
+        let mut pos = Position::default();
+        play(&mut pos, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+
+        assert!(pos.in_check());
+        assert_eq!(pos.outcome(), Some(Outcome::Checkmate(PieceColor::Black)));
+    }
+
+    #[test]
+    fn stalemate_and_dead_positions() {
+        let stalemate = Position::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(stalemate.outcome(), Some(Outcome::Stalemate));
+
+        for fen in ["8/8/8/4k3/8/8/8/4K3 w - - 0 1", "8/8/8/4k3/8/8/8/3NK3 w - - 0 1", "8/8/2b5/4k3/8/8/8/3BK3 w - - 0 1"] {
+            assert!(Position::from_fen(fen).unwrap().insufficient_material(), "{fen}");
+        }
+
+        assert!(!Position::from_fen("8/8/8/2b1k3/8/8/8/3BK3 w - - 0 1").unwrap().insufficient_material());
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let mut game = Game::default();
+
+        for mv in ["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1"] {
+            game.play(mv.parse().unwrap());
+            assert_eq!(game.outcome(), None);
+        }
+
+        game.play("f6g8".parse().unwrap());
+        assert_eq!(game.outcome(), Some(Outcome::Repetition));
+    }
+
+    #[test]
+    fn mating_material_for_the_flag() {
+        let has = |fen: &str, color| Position::from_fen(fen).unwrap().has_mating_material(color);
+
+        assert!(has("8/8/8/4k3/8/8/4P3/4K3 w - - 0 1", PieceColor::White));
+        assert!(!has("8/8/8/4k3/8/8/4P3/4K3 w - - 0 1", PieceColor::Black));
+        assert!(!has("8/8/8/4k3/8/8/8/3NK3 w - - 0 1", PieceColor::White));
+        assert!(has("8/8/4p3/4k3/8/8/8/3NK3 w - - 0 1", PieceColor::White));
+        assert!(has("8/8/8/4k3/8/8/8/2BBK3 w - - 0 1", PieceColor::White));
+        assert!(!has("8/8/8/4k3/8/4B3/8/2B1K3 w - - 0 1", PieceColor::White));
+    }
+
+    #[test]
+    fn pgn_results() {
+        assert_eq!(Outcome::Checkmate(PieceColor::White).result(), "1-0");
+        assert_eq!(Outcome::Checkmate(PieceColor::Black).result(), "0-1");
+        assert_eq!(Outcome::Repetition.result(), "1/2-1/2");
+    }
+
+    #[test]
+    fn positions_are_validated() {
+        let valid = |fen: &str| Position::from_fen(fen).unwrap().validate().is_ok();
+
+        assert!(valid(START_FEN));
+        assert!(valid(KIWIPETE));
+        assert!(valid("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3"));
+
+        // Kings missing or doubled.
+        assert!(!valid("8/8/8/8/8/8/8/4K3 w - - 0 1"));
+        assert!(!valid("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"));
+        // A pawn on the back rank.
+        assert!(!valid("4k2P/8/8/8/8/8/8/4K3 w - - 0 1"));
+        // Black in check with White to move.
+        assert!(!valid("4k3/8/8/8/8/8/4R3/4K3 w - - 0 1"));
+        assert!(valid("4k3/8/8/8/8/8/4R3/4K3 b - - 0 1"));
+        // Castling without the rook, en passant without a pawn that just moved.
+        assert!(!valid("4k3/8/8/8/8/8/8/4K3 w K - 0 1"));
+        assert!(!valid("4k3/8/8/8/8/8/8/4K3 w - e3 0 1"));
+    }
+}
@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod test {
+    use crate::rules::*;
+    use crate::san::*;
+
+    fn sans(fen: &str, moves: &[&str]) -> Vec<String> {
+        let pos = Position::from_fen(fen).unwrap();
+        let moves: Vec<Move> = moves.iter().map(|m| m.parse().unwrap()).collect();
+
+        line(&pos, &moves)
+    }
+
+    #[test]
+    fn opening_moves() {
+        assert_eq!(sans(START_FEN, &["e2e4", "e7e5", "g1f3", "b8c6", "f1b5"]), ["e4", "e5", "Nf3", "Nc6", "Bb5"]);
+    }
+
+    #[test]
+    fn captures_checks_and_mate() {
+        assert_eq!(sans(START_FEN, &["e2e4", "d7d5", "e4d5", "d8d5"]), ["e4", "d5", "exd5", "Qxd5"]);
+        assert_eq!(sans(START_FEN, &["f2f3", "e7e5", "g2g4", "d8h4"]), ["f3", "e5", "g4", "Qh4#"]);
+        assert_eq!(sans(START_FEN, &["e2e4", "f7f6", "d1h5"]), ["e4", "f6", "Qh5+"]);
+    }
+
+    #[test]
+    fn castling_and_promotion() {
+        let fen = "r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1";
+
+        assert_eq!(sans(fen, &["e1g1"]), ["O-O"]);
+        assert_eq!(sans(fen, &["e1c1"]), ["O-O-O"]);
+        assert_eq!(sans(fen, &["b7a8q"]), ["bxa8=Q+"]);
+        assert_eq!(sans(fen, &["b7b8n"]), ["b8=N"]);
+    }
+
+    #[test]
+    fn disambiguation() {
+        assert_eq!(sans("k7/8/8/8/8/8/8/KN3N2 w - - 0 1", &["b1d2"]), ["Nbd2"]);
+        assert_eq!(sans("k7/8/8/8/8/8/8/KR6 w - - 0 1", &["b1b8"]), ["Rb8+"]);
+        assert_eq!(sans("7k/8/8/R7/8/8/8/R3K3 w - - 0 1", &["a1a3"]), ["R1a3"]);
+        assert_eq!(sans("8/8/1k6/8/4Q2Q/8/8/K6Q w - - 0 1", &["h4e1"]), ["Qh4e1"]);
+    }
+
+    #[test]
+    fn line_stops_at_illegal_move() {
+        assert_eq!(sans(START_FEN, &["e2e4", "e2e4"]), ["e4"]);
+    }
+
+    #[test]
+    fn move_numbers() {
+        let mut pos = Position::default();
+        assert_eq!(move_number(&pos), "1.");
+
+        pos.make_move("e2e4".parse().unwrap());
+        assert_eq!(move_number(&pos), "1...");
+    }
+
+    #[test]
+    fn parses_what_it_writes() {
+        let pos = Position::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+
+        for mv in pos.legal_moves() {
+            assert_eq!(parse(&pos, &san(&pos, mv)), Some(mv));
+        }
+    }
+
+    #[test]
+    fn parses_loose_notation() {
+        let pos = Position::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+
+        assert_eq!(parse(&pos, "0-0"), Some("e1g1".parse().unwrap()));
+        assert_eq!(parse(&pos, "O-O-O!?"), Some("e1c1".parse().unwrap()));
+        assert_eq!(parse(&pos, "bxa8Q"), Some("b7a8q".parse().unwrap()));
+        assert_eq!(parse(&pos, "b8=N"), Some("b7b8n".parse().unwrap()));
+        assert_eq!(parse(&pos, "Ke3"), None);
+        assert_eq!(parse(&Position::default(), "Nd2"), None);
+    }
+
+    #[test]
+    fn completes_typed_moves() {
+        let pos = Position::default();
+        let completed = |text: &str| {
+            let mut sans: Vec<String> = complete(&pos, text).into_iter().map(|mv| san(&pos, mv)).collect();
+            sans.sort();
+            sans
+        };
+
+        assert_eq!(completed("N"), ["Na3", "Nc3", "Nf3", "Nh3"]);
+        assert_eq!(completed("Nf"), ["Nf3"]);
+        assert_eq!(completed("g1"), ["Nf3", "Nh3"]);
+        assert!(completed("").is_empty());
+        assert!(completed("Qd4").is_empty());
+
+        assert_eq!(read(&pos, "Nf"), Some("g1f3".parse().unwrap()));
+        assert_eq!(read(&pos, "e2e4"), Some("e2e4".parse().unwrap()));
+        assert_eq!(read(&pos, "E2E4"), Some("e2e4".parse().unwrap()));
+        assert_eq!(read(&pos, "e4"), Some("e2e4".parse().unwrap()));
+        assert_eq!(read(&pos, "N"), None);
+
+        let pos = Position::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(read(&pos, "O-O"), Some("e1g1".parse().unwrap()));
+        assert_eq!(read(&pos, "0-0-0"), Some("e1c1".parse().unwrap()));
+        assert_eq!(complete(&pos, "b8").len(), 4);
+        assert_eq!(read(&pos, "bxa8Q"), Some("b7a8q".parse().unwrap()));
+    }
+}
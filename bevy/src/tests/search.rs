@@ -0,0 +1,111 @@
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::AtomicBool;
+    use std::time::{Duration, Instant};
+
+    use crate::rules::*;
+    use crate::search::*;
+    use crate::uci::{Info, Score};
+
+    fn run(fen: &str, depth: u32, multipv: usize) -> (Option<Move>, Vec<Info>) {
+        let pos = Position::from_fen(fen).unwrap();
+        let limits = Limits { depth: Some(depth), ..Limits::default() };
+        let mut infos = Vec::new();
+
+        let best = search(&pos, &[], &limits, multipv, &AtomicBool::new(false), |info| infos.push(info));
+        (best, infos)
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let (best, infos) = run("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 2, 1);
+
+        assert_eq!(best, "a1a8".parse().ok());
+        assert_eq!(infos.last().unwrap().score, Some(Score::Mate(1)));
+    }
+
+    #[test]
+    fn takes_hanging_queen() {
+        let (best, _) = run("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 3, 1);
+
+        assert_eq!(best, "d2d5".parse().ok());
+    }
+
+    #[test]
+    fn reports_every_depth_and_line() {
+        let (_, infos) = run(START_FEN, 2, 3);
+
+        assert_eq!(infos.len(), 6);
+        assert_eq!(infos.iter().map(|i| (i.depth, i.multipv)).collect::<Vec<_>>()[3..], [
+            (Some(2), Some(1)),
+            (Some(2), Some(2)),
+            (Some(2), Some(3)),
+        ]);
+
+        let roots: Vec<Move> = infos[3..].iter().map(|i| i.pv[0]).collect();
+        assert!(roots[0] != roots[1] && roots[1] != roots[2] && roots[0] != roots[2]);
+    }
+
+    #[test]
+    fn no_move_when_mated() {
+        let (best, infos) = run("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", 3, 1);
+
+        assert_eq!(best, None);
+        assert!(infos.is_empty());
+    }
+
+    #[test]
+    fn stop_flag_ends_the_search() {
+        let pos = Position::default();
+        let best = search(&pos, &[], &Limits::default(), 1, &AtomicBool::new(true), |_| {});
+
+        assert!(best.is_some_and(|mv| pos.is_legal(mv)));
+    }
+
+    #[test]
+    fn mate_scores() {
+        assert_eq!(uci_score(MATE - 1), Score::Mate(1));
+        assert_eq!(uci_score(MATE - 4), Score::Mate(2));
+        assert_eq!(uci_score(-MATE + 2), Score::Mate(-1));
+        assert_eq!(uci_score(35), Score::Cp(35));
+    }
+
+    #[test]
+    fn time_budget_splits_the_clock() {
+        let budget = TimeBudget::new(Duration::from_secs(60), Duration::from_secs(1), None);
+        assert_eq!(budget.optimum, Duration::from_millis(2749));
+        assert_eq!(budget.maximum, Duration::from_millis(10996));
+
+        // The last move before the time control may not use it all.
+        let budget = TimeBudget::new(Duration::from_secs(10), Duration::ZERO, Some(1));
+        assert_eq!(budget.optimum, Duration::from_millis(4985));
+        assert_eq!(budget.maximum, budget.optimum);
+
+        let budget = TimeBudget::new(Duration::from_millis(10), Duration::from_secs(2), None);
+        assert_eq!(budget.maximum, Duration::ZERO);
+    }
+
+    #[test]
+    fn time_budget_reacts_to_the_search() {
+        let budget = TimeBudget { optimum: Duration::from_secs(1), maximum: Duration::from_secs(4) };
+        let elapsed = Duration::from_millis(400);
+
+        assert!(budget.keep_going(elapsed, 0, 0));
+        assert!(!budget.keep_going(elapsed, 3, 0));
+        assert!(budget.keep_going(Duration::from_millis(900), 3, 50));
+        assert!(!budget.keep_going(Duration::from_millis(1500), 0, 50));
+    }
+
+    #[test]
+    fn search_keeps_to_its_budget() {
+        let pos = Position::default();
+        let budget = TimeBudget { optimum: Duration::from_millis(50), maximum: Duration::from_millis(200) };
+        let limits = Limits { budget: Some(budget), ..Limits::default() };
+
+        let started = Instant::now();
+        let best = search(&pos, &[], &limits, 1, &AtomicBool::new(false), |_| {});
+
+        assert!(best.is_some_and(|mv| pos.is_legal(mv)));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,163 @@
+#[cfg(test)]
+mod test {
+    use bevy::prelude::*;
+    use bevy::state::app::StatesPlugin;
+    use bevy::window::exit_on_all_closed;
+
+    use crate::game::{Game, Player, Players};
+    use crate::marks::{Mark, MarkColor, Marks};
+    use crate::piece::PieceColor;
+    use crate::rules::Position;
+    use crate::session::{read, LastGame, SavedGame, SessionPlugin, SAVE_VERSION};
+    use crate::setup::{GameConfig, NewGameEvent, Opponent, SideChoice};
+    use crate::states::AppState;
+    use crate::timer::{follow_moves, ClockHistory, ResumeClocks, Timer};
+
+    fn played(moves: &[&str]) -> Game {
+        let mut game = Game::default();
+        for mv in moves {
+            game.play(mv.parse().unwrap());
+        }
+        game
+    }
+
+    #[test]
+    fn saved_games_replay_with_clocks_and_marks() {
+        let game = played(&["e2e4", "e7e5", "g1f3", "b8c6"]);
+        let players = Players { white: Player::Human, black: Player::Computer };
+        let config = GameConfig { opponent: Opponent::Computer, side: SideChoice::White, clock: String::from("300+3"), ..GameConfig::default() };
+        let clocks = [
+            Timer { time: 287.5, color: PieceColor::White, moves: 2, stage: 0, stage_moves: 2, thinking: 4.25 },
+            Timer { time: 301., color: PieceColor::Black, moves: 2, stage: 0, stage_moves: 2, thinking: 0. },
+        ];
+
+        let mut marks = Marks::default();
+        let arrow = Mark::Arrow(MarkColor::Red, (5, 0), (1, 4));
+        marks.toggle(&game.position_at(2), Mark::Circle(MarkColor::Green, (4, 3)));
+        marks.toggle(&game.position, arrow);
+
+        let saved = SavedGame::new(&game, &players, &config, &clocks, &ClockHistory::default(), &marks);
+        let loaded = SavedGame::from_ron(&saved.to_ron()).unwrap();
+        assert_eq!(loaded, saved);
+        assert_eq!(loaded.version, SAVE_VERSION);
+        assert_eq!(loaded.clocks, clocks);
+        assert_eq!(loaded.config, config);
+        assert_eq!(loaded.players().black, Player::Computer);
+
+        let restored = loaded.game().unwrap();
+        assert_eq!(restored.moves, game.moves);
+        assert_eq!(restored.position, game.position);
+        assert_eq!(restored.keys(), game.keys());
+
+        let mut redrawn = Marks::default();
+        loaded.restore_marks(&restored, &mut redrawn);
+        assert_eq!(redrawn.get(&restored.position), &[arrow]);
+        assert_eq!(redrawn.commands(&restored.position_at(2)), "[%csl Ge4]");
+        assert!(redrawn.get(&restored.position_at(1)).is_empty());
+    }
+
+    #[test]
+    fn moves_taken_back_after_resuming_bring_their_clocks_back() {
+        let game = played(&["e2e4", "e7e5", "g1f3", "b8c6"]);
+        let config = GameConfig { clock: String::from("60+5"), ..GameConfig::default() };
+        let clock = config.clock().unwrap().unwrap();
+        let mut clocks = [Timer::new(PieceColor::White, &clock), Timer::new(PieceColor::Black, &clock)];
+        let mut history = ClockHistory::default();
+
+        for ply in 0..game.moves.len() {
+            for timer in &mut clocks {
+                timer.tick(&clock, if ply < 2 { 2. } else { 7. });
+                follow_moves(timer, &clock, &mut history.0, ply, ply + 1, game.position_at(ply + 1).turn);
+            }
+        }
+        let after_first = [history.0[0].1, history.0[1].1];
+
+        // Carried on, then the last pair taken back: both clocks read as after the first pair.
+        let saved = SavedGame::from_ron(&SavedGame::new(&game, &Players::default(), &config, &clocks, &history, &Marks::default()).to_ron()).unwrap();
+        let mut resumed = ClockHistory(saved.clock_history);
+        let mut clocks: Vec<Timer> = saved.clocks;
+        for timer in &mut clocks {
+            follow_moves(timer, &clock, &mut resumed.0, 4, 2, PieceColor::White);
+        }
+
+        assert_eq!(clocks, after_first);
+        assert_eq!(resumed.0.len(), 2);
+    }
+
+    #[test]
+    fn games_from_a_position_keep_their_start_and_draw_offer() {
+        let start = Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        let mut game = Game::new(start.clone());
+        game.play("e2e4".parse().unwrap());
+        game.offer_draw(PieceColor::Black);
+
+        let saved = SavedGame::new(&game, &Players::default(), &GameConfig::default(), [], &ClockHistory::default(), &Marks::default());
+        let restored = SavedGame::from_ron(&saved.to_ron()).unwrap().game().unwrap();
+
+        assert_eq!(restored.start, start);
+        assert_eq!(restored.position, game.position);
+        assert_eq!(restored.draw_offer(), Some(PieceColor::Black));
+    }
+
+    #[test]
+    fn other_versions_and_bad_games_are_turned_down() {
+        let game = played(&["d2d4"]);
+        let saved = SavedGame::new(&game, &Players::default(), &GameConfig::default(), [], &ClockHistory::default(), &Marks::default());
+
+        let newer = saved.to_ron().replacen(&format!("version: {SAVE_VERSION}"), "version: 99", 1);
+        assert!(SavedGame::from_ron(&newer).unwrap_err().contains("version 99"));
+        assert!(SavedGame::from_ron("(version: 99, something: \"else\")").unwrap_err().contains("version 99"));
+        assert!(SavedGame::from_ron("(moves: ").is_err());
+
+        let illegal = SavedGame { moves: vec![String::from("d2d4"), String::from("d4d5")], ..saved.clone() };
+        assert_eq!(illegal.game().err(), Some(String::from("move 2: d4d5 is not legal")));
+
+        let dir = std::env::temp_dir().join(format!("chess-session-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(read(&dir.join("missing.ron")), Ok(None));
+
+        let file = dir.join("last-game.ron");
+        std::fs::write(&file, saved.to_ron()).unwrap();
+        assert_eq!(read(&file), Ok(Some(saved)));
+
+        std::fs::write(&file, illegal.to_ron()).unwrap();
+        assert!(read(&file).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn closing_the_window_saves_the_game() {
+        let dir = std::env::temp_dir().join(format!("chess-autosave-{}", std::process::id()));
+        let path = dir.join("last-game.ron");
+
+        let mut app = App::new();
+        app.add_plugins(StatesPlugin)
+            .init_state::<AppState>()
+            .init_resource::<Game>()
+            .init_resource::<Players>()
+            .init_resource::<GameConfig>()
+            .init_resource::<Marks>()
+            .init_resource::<ResumeClocks>()
+            .init_resource::<ClockHistory>()
+            .add_event::<NewGameEvent>()
+            .add_plugins(SessionPlugin)
+            .insert_resource(LastGame { path: Some(path.clone()), ..default() });
+        app.update();
+        assert!(!path.exists());
+
+        // A game is set up, then the last window goes: the exit is only sent in `PostUpdate`.
+        app.world_mut().resource_mut::<Game>().play("e2e4".parse().unwrap());
+        app.world_mut().send_event(NewGameEvent);
+        app.update();
+        assert!(!path.exists());
+
+        app.add_systems(PostUpdate, exit_on_all_closed);
+        app.update();
+        let saved = read(&path).unwrap().unwrap();
+        assert_eq!(saved.moves, ["e2e4"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use crate::settings::{read, Settings, SettingsFile};
+
+    #[test]
+    fn settings_survive_a_round_trip() {
+        let settings = Settings {
+            theme: String::from("green"),
+            animation_ms: 0,
+            auto_queen: false,
+            time_control: String::from("180+2"),
+            engines: vec![PathBuf::from("/usr/bin/stockfish")],
+            engine: Some(PathBuf::from("/usr/bin/stockfish")),
+            window: (1200., 700.),
+            ..Settings::default()
+        };
+
+        assert_eq!(Settings::from_ron(&settings.to_ron()), Ok(settings));
+    }
+
+    #[test]
+    fn missing_and_broken_settings_fall_back() {
+        // Fields an older file does not have keep their defaults.
+        let settings = Settings::from_ron(r#"(theme: "blue", coordinates: false)"#).unwrap();
+        assert_eq!(settings.theme, "blue");
+        assert!(!settings.coordinates);
+        assert_eq!(settings.pieces, Settings::default().pieces);
+        assert!(settings.auto_queen);
+
+        assert!(Settings::from_ron("(theme: ").is_err());
+        assert!(Settings::from_ron(r#"(animation_ms: "slow")"#).is_err());
+
+        let dir = std::env::temp_dir().join(format!("chess-settings-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(read(&dir.join("missing.ron")), Ok(Settings::default()));
+
+        let broken = dir.join("broken.ron");
+        std::fs::write(&broken, "not settings").unwrap();
+        assert!(read(&broken).is_err());
+
+        // Saving over a file that could not be read keeps it as a backup.
+        let settings = dir.join("settings.ron");
+        std::fs::write(&settings, "(engines: [\"/opt/engine\"],").unwrap();
+        let mut file = SettingsFile { path: Some(settings.clone()), problem: Some(String::from("broken")) };
+        file.save(&Settings::default());
+
+        assert_eq!(std::fs::read_to_string(dir.join("settings.ron.bak")).unwrap(), "(engines: [\"/opt/engine\"],");
+        assert_eq!(read(&settings), Ok(Settings::default()));
+        assert_eq!(file.problem, None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
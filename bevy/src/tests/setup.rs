@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod test {
+    use crate::game::{Game, Player, Players};
+    use crate::piece::PieceColor;
+    use crate::setup::*;
+    use crate::timer::Timing;
+
+    #[test]
+    fn config_picks_the_players() {
+        let mut config = GameConfig { opponent: Opponent::Computer, side: SideChoice::Black, ..GameConfig::default() };
+        let players = config.players(true);
+        assert_eq!((players.white, players.black), (Player::Computer, Player::Human));
+
+        config.opponent = Opponent::Engine;
+        config.side = SideChoice::Random;
+        assert_eq!(config.players(true).get(PieceColor::Black), Player::Engine);
+        assert_eq!(config.players(false).get(PieceColor::White), Player::Engine);
+
+        config.opponent = Opponent::Human;
+        let players = config.players(true);
+        assert_eq!((players.white, players.black), (Player::Human, Player::Human));
+    }
+
+    #[test]
+    fn rematch_swaps_the_colour_played() {
+        let mut config = GameConfig { opponent: Opponent::Computer, side: SideChoice::Random, ..GameConfig::default() };
+
+        // The coin gave the human Black, so the rematch is with White, and back again.
+        config.swap_sides(&config.players(false));
+        assert_eq!(config.side, SideChoice::White);
+        config.swap_sides(&config.players(true));
+        assert_eq!(config.side, SideChoice::Black);
+
+        config.opponent = Opponent::Human;
+        config.swap_sides(&config.players(true));
+        assert_eq!(config.side, SideChoice::Black);
+    }
+
+    #[test]
+    fn config_checks_clock_and_position() {
+        let mut config = GameConfig::default();
+        assert!(config.clock().unwrap().is_none());
+
+        config.clock = String::from("300+3");
+        config.timing = Timing::Bronstein;
+        let clock = config.clock().unwrap().unwrap();
+        assert_eq!((clock.stage(0).base.as_secs(), clock.timing), (300, Timing::Bronstein));
+
+        config.clock = String::from("3 minutes");
+        assert!(config.clock().is_err());
+
+        config.start = StartChoice::Fen;
+        config.fen = String::from("8/8/4k3/8/8/8/3PK3/8 w - - 0 1");
+        assert_eq!(config.start_position().unwrap().fen(), config.fen);
+
+        config.fen = String::from("not a position");
+        assert!(config.start_position().is_err());
+    }
+
+    #[test]
+    fn begin_game_needs_a_playable_config() {
+        let mut game = Game::default();
+        game.play(game.position.legal_moves()[0]);
+        let mut players = Players::default();
+
+        let mut config = GameConfig { opponent: Opponent::Engine, side: SideChoice::White, ..GameConfig::default() };
+        assert!(begin_game(&config, false, &mut game, &mut players).is_err());
+        assert_eq!(game.moves.len(), 1);
+
+        config.opponent = Opponent::Network;
+        assert!(begin_game(&config, true, &mut game, &mut players).is_err());
+
+        config.opponent = Opponent::Computer;
+        assert!(begin_game(&config, false, &mut game, &mut players).is_ok());
+        assert!(game.moves.is_empty());
+        assert_eq!(players.get(PieceColor::Black), Player::Computer);
+    }
+}
@@ -0,0 +1,47 @@
+#[cfg(test)]
+mod test {
+    use crate::stats::*;
+
+    const SPRT: Sprt = Sprt { elo0: 0., elo1: 10., alpha: 0.05, beta: 0.05 };
+
+    #[test]
+    fn elo_conversions() {
+        assert_eq!(expected_score(0.), 0.5);
+        assert!((elo_from_score(expected_score(120.)) - 120.).abs() < 1e-9);
+        assert!((elo_from_score(0.75) - 190.85).abs() < 0.01);
+    }
+
+    #[test]
+    fn elo_with_error_bars() {
+        let even = Tally { wins: 30, draws: 40, losses: 30 };
+        let (elo, margin) = even.elo().unwrap();
+        assert!(elo.abs() < 1e-9);
+        assert!((40. ..60.).contains(&margin), "{margin}");
+
+        let (elo, _) = Tally { wins: 60, draws: 30, losses: 10 }.elo().unwrap();
+        assert!(elo > 150.);
+
+        assert_eq!(Tally { wins: 5, draws: 0, losses: 0 }.elo(), None);
+        assert_eq!(Tally::default().elo(), None);
+    }
+
+    #[test]
+    fn sprt_bounds() {
+        let (lower, upper) = SPRT.bounds();
+        assert!((lower + 2.944).abs() < 0.001);
+        assert!((upper - 2.944).abs() < 0.001);
+    }
+
+    #[test]
+    fn sprt_decisions() {
+        assert_eq!(SPRT.status(&Tally::default()), SprtStatus::Continue);
+        assert_eq!(SPRT.status(&Tally { wins: 10, draws: 10, losses: 8 }), SprtStatus::Continue);
+        assert_eq!(SPRT.status(&Tally { wins: 600, draws: 600, losses: 400 }), SprtStatus::AcceptH1);
+        assert_eq!(SPRT.status(&Tally { wins: 400, draws: 600, losses: 600 }), SprtStatus::AcceptH0);
+
+        // Halfway between the hypotheses the evidence is balanced.
+        let s = expected_score(5.);
+        let tally = Tally { wins: (1000. * s).round() as u32, draws: 0, losses: (1000. * (1. - s)).round() as u32 };
+        assert!(SPRT.llr(&tally).abs() < 0.1);
+    }
+}
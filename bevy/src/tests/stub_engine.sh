@@ -0,0 +1,31 @@
+#!/bin/sh
+# Scripted stand-in for a UCI engine, answers deterministically.
+# `crash` exits as soon as a search starts, `mute` never answers the handshake.
+# Pondering waits for `ponderhit` or `stop` like a real engine.
+mode="$1"
+
+while read -r line; do
+    case "$line" in
+        uci)
+            [ "$mode" = mute ] && continue
+            echo "id name Stub"
+            echo "id author Chess-Clone"
+            echo "option name Hash type spin default 16 min 1 max 1024"
+            echo "option name Skill Level type spin default 20 min 0 max 20"
+            echo "option name Clear Hash type button"
+            echo "uciok"
+            ;;
+        isready) echo "readyok" ;;
+        setoption*) echo "info string $line" ;;
+        "go infinite"*) echo "info depth 1 score cp 13 pv e2e4" ;;
+        "go ponder"*) echo "info depth 1 score cp -20 pv g1f3" ;;
+        ponderhit) echo "bestmove g1f3 ponder d7d5" ;;
+        go*)
+            [ "$mode" = crash ] && exit 3
+            echo "info depth 1 seldepth 2 multipv 1 score cp 20 nodes 42 pv e2e4 e7e5"
+            echo "bestmove e2e4 ponder e7e5"
+            ;;
+        stop) echo "bestmove d2d4" ;;
+        quit) exit 0 ;;
+    esac
+done
@@ -0,0 +1,44 @@
+#[cfg(test)]
+mod test {
+    use crate::rules::*;
+    use crate::tablebase::*;
+
+    fn probe(tablebase: &Tablebase, fen: &str) -> Option<Wdl> {
+        tablebase.probe(&Position::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn heavy_pieces() {
+        let tb = Tablebase::default();
+
+        assert_eq!(probe(&tb, "8/8/8/8/8/8/8/R3K2k w - - 0 1"), Some(Wdl::Win));
+        assert_eq!(probe(&tb, "7K/8/8/8/8/8/8/r3k3 w - - 0 1"), Some(Wdl::Loss));
+        // Stalemate, and a queen left hanging next to the king.
+        assert_eq!(probe(&tb, "k7/2Q5/1K6/8/8/8/8/8 b - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(probe(&tb, "8/8/8/8/8/8/1Q6/k6K b - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(probe(&tb, "8/8/8/8/8/8/1Q6/k1K5 b - - 0 1"), Some(Wdl::Loss));
+    }
+
+    #[test]
+    fn king_and_pawn() {
+        let tb = Tablebase::default();
+
+        // With the king on the sixth in front of the pawn it doesn't matter who moves.
+        assert_eq!(probe(&tb, "4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), Some(Wdl::Win));
+        assert_eq!(probe(&tb, "4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), Some(Wdl::Loss));
+        assert_eq!(probe(&tb, "8/8/8/8/8/4k3/4P3/4K3 w - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(probe(&tb, "4k3/4P3/4K3/8/8/8/8/8 b - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(probe(&tb, "8/8/8/8/4p3/4k3/8/4K3 w - - 0 1"), Some(Wdl::Loss));
+        // The rook pawn can't be won against a king in the corner.
+        assert_eq!(probe(&tb, "k7/8/8/8/8/8/P7/7K w - - 0 1"), Some(Wdl::Draw));
+    }
+
+    #[test]
+    fn other_material() {
+        let tb = Tablebase::default();
+
+        assert_eq!(probe(&tb, "8/8/8/8/8/8/8/K1k5 w - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(probe(&tb, "8/8/8/8/8/8/8/KNk5 w - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(probe(&tb, START_FEN), None);
+    }
+}
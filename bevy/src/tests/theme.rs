@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use bevy::color::Color;
+
+    use crate::consts::CURSOR_COLOR;
+    use crate::piece::{Kind, PieceColor};
+    use crate::theme::{light_square, parse_color, Appearance, PieceSet, Theme};
+
+    #[test]
+    fn manifests_are_read() {
+        let theme = Theme::from_manifest("wood", r##"(
+            name: "Wood",
+            light: "#ffffff",
+            dark: "#000000",
+            dark_texture: "dark.png",
+            selected: "#ff000080",
+        )"##).unwrap();
+
+        assert_eq!(theme.name, "Wood");
+        assert_eq!(theme.square_color((1, 0)), Color::srgb(1., 1., 1.));
+        assert_eq!(theme.square_color((0, 0)), Color::srgb(0., 0., 0.));
+        assert_eq!(theme.square_texture((0, 0)), Some("themes/wood/dark.png"));
+        assert_eq!(theme.square_texture((1, 0)), None);
+        assert_eq!(theme.selected, Color::srgba_u8(255, 0, 0, 128));
+        // Highlights left out keep the defaults.
+        assert_eq!(theme.cursor, CURSOR_COLOR);
+
+        assert!(Theme::from_manifest("bad", r##"(name: "Bad", light: "white", dark: "#000")"##).is_err());
+        assert!(Theme::from_manifest("bad", r#"(name: "Bad")"#).is_err());
+
+        let set = PieceSet::from_manifest("plain", r#"(name: "Plain", scale: (0.5, 0.5))"#).unwrap();
+        assert_eq!(set.path(PieceColor::Black, Kind::Knight), "pieces/plain/black/knight.png");
+    }
+
+    #[test]
+    fn colors_and_squares() {
+        assert_eq!(parse_color("#00ff00").unwrap(), Color::srgb(0., 1., 0.));
+        assert!(parse_color("00ff0").is_err());
+        assert!(!light_square((0, 0)));
+        assert!(light_square((7, 0)));
+        assert!(!light_square((7, 7)));
+    }
+
+    #[test]
+    fn shipped_themes_and_piece_sets_are_found() {
+        let mut appearance = Appearance::discover(Path::new("assets"));
+
+        assert_eq!(appearance.theme().id, "brown");
+        assert_eq!(appearance.pieces().id, "classic");
+        assert!(appearance.themes.len() >= 3);
+
+        assert!(appearance.choose_theme("green"));
+        assert_eq!(appearance.theme().name, "Green");
+        assert!(!appearance.choose_theme("missing"));
+        assert_eq!(appearance.theme().name, "Green");
+
+        // Nothing found leaves the built-in look.
+        let fallback = Appearance::discover(Path::new("no-such-folder"));
+        assert_eq!(fallback.theme().id, "brown");
+        assert_eq!(fallback.pieces().id, "classic");
+    }
+}
@@ -0,0 +1,111 @@
+#[cfg(test)]
+mod test {
+    use crate::piece::PieceColor;
+    use crate::timer::*;
+
+    fn clock(spec: &str, timing: Timing) -> ClockSettings {
+        ClockSettings { timing, ..spec.parse().unwrap() }
+    }
+
+    /// Thinks for `seconds` in steps of a tenth, then moves.
+    fn think(timer: &mut Timer, clock: &ClockSettings, seconds: f32) {
+        for _ in 0..(seconds * 10.).round() as u32 {
+            timer.tick(clock, 0.1);
+        }
+        timer.moved(clock);
+    }
+
+    #[test]
+    fn clock_stages_parse() {
+        let clock = clock("40/5400+30:1800+30", Timing::Increment);
+        assert_eq!(clock.stages.len(), 2);
+        assert_eq!(clock.stage(0).moves, Some(40));
+        assert_eq!(clock.stage(5).base.as_secs(), 1800);
+
+        assert!("60:40/60".parse::<ClockSettings>().is_err());
+        assert!("60:".parse::<ClockSettings>().is_err());
+    }
+
+    #[test]
+    fn increment_and_delays() {
+        let fischer = clock("60+5", Timing::Increment);
+        let mut timer = Timer::new(PieceColor::White, &fischer);
+        think(&mut timer, &fischer, 2.);
+        assert!((timer.time - 63.).abs() < 0.01, "{}", timer.time);
+
+        let bronstein = clock("60+5", Timing::Bronstein);
+        let mut timer = Timer::new(PieceColor::White, &bronstein);
+        think(&mut timer, &bronstein, 2.);
+        assert!((timer.time - 60.).abs() < 0.01, "{}", timer.time);
+        think(&mut timer, &bronstein, 8.);
+        assert!((timer.time - 57.).abs() < 0.01, "{}", timer.time);
+
+        let simple = clock("60+5", Timing::Simple);
+        let mut timer = Timer::new(PieceColor::White, &simple);
+        think(&mut timer, &simple, 2.);
+        assert!((timer.time - 60.).abs() < 0.01, "{}", timer.time);
+        think(&mut timer, &simple, 8.);
+        assert!((timer.time - 57.).abs() < 0.01, "{}", timer.time);
+    }
+
+    #[test]
+    fn stages_add_their_time() {
+        let clock = clock("2/60:30+1", Timing::Increment);
+        let mut timer = Timer::new(PieceColor::Black, &clock);
+
+        think(&mut timer, &clock, 10.);
+        assert_eq!(clock.go(PieceColor::Black, [&timer]).movestogo, Some(1));
+        think(&mut timer, &clock, 10.);
+        assert_eq!(timer.stage, 1);
+        assert!((timer.time - 70.).abs() < 0.01, "{}", timer.time);
+
+        think(&mut timer, &clock, 10.);
+        assert!((timer.time - 61.).abs() < 0.01, "{}", timer.time);
+        assert_eq!(clock.go(PieceColor::Black, [&timer]).binc, Some(1000));
+        assert_eq!(clock.go(PieceColor::Black, [&timer]).movestogo, None);
+    }
+
+    #[test]
+    fn plies_replayed_together_credit_both_clocks() {
+        let clock = clock("2/60+5:30", Timing::Increment);
+        let mut white = Timer::new(PieceColor::White, &clock);
+        let mut black = Timer::new(PieceColor::Black, &clock);
+        let mut history = Vec::new();
+
+        // Redo plays a move and its reply in the same frame: two plies, White to move again.
+        for timer in [&mut white, &mut black] {
+            follow_moves(timer, &clock, &mut history, 0, 2, PieceColor::White);
+        }
+        assert_eq!((white.moves, black.moves), (1, 1));
+        assert!((white.time - 65.).abs() < 0.01 && (black.time - 65.).abs() < 0.01);
+        assert_eq!(history.len(), 2);
+
+        // The second pair finishes the first stage for both.
+        for timer in [&mut white, &mut black] {
+            follow_moves(timer, &clock, &mut history, 2, 4, PieceColor::White);
+        }
+        assert_eq!((white.stage, black.stage), (1, 1));
+
+        // Taking back Black's last move brings back its clock after its first, White's stays.
+        let (white_after, black_after) = (white, history[1].1);
+        for timer in [&mut white, &mut black] {
+            follow_moves(timer, &clock, &mut history, 4, 3, PieceColor::Black);
+        }
+        assert_eq!(white, white_after);
+        assert_eq!(black, black_after);
+        assert_eq!(black.moves, 1);
+    }
+
+    #[test]
+    fn tenths_under_ten_seconds() {
+        let clock = clock("60", Timing::Increment);
+        let mut timer = Timer::new(PieceColor::White, &clock);
+        assert_eq!(timer.to_string(), "White 1:00");
+
+        timer.time = 9.87;
+        assert_eq!(timer.to_string(), "White 0:09.8");
+
+        timer.time = -1.;
+        assert_eq!(timer.to_string(), "White 0:00.0");
+    }
+}
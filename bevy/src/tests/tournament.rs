@@ -0,0 +1,169 @@
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::rules::*;
+    use crate::search::Limits;
+    use crate::stats::Tally;
+    use crate::tablebase::Tablebase;
+    use crate::tournament::*;
+    use crate::uci::EngineConfig;
+
+    const STUB: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/stub_engine.sh");
+
+    fn builtin(depth: u32) -> EngineSpec {
+        EngineSpec::Builtin { name: format!("depth{depth}"), limits: Limits { depth: Some(depth), ..Limits::default() } }
+    }
+
+    fn opening(fen: &str) -> Opening {
+        Opening { start: Position::from_fen(fen).unwrap(), moves: Vec::new() }
+    }
+
+    fn play(specs: [&EngineSpec; 2], opening: &Opening, adjudication: &Adjudication) -> PlayedGame {
+        let mut white = specs[0].start().unwrap();
+        let mut black = specs[1].start().unwrap();
+        let tc = "60+1".parse().unwrap();
+
+        play_game([&mut *white, &mut *black], opening, &tc, adjudication, &Tablebase::default())
+    }
+
+    #[test]
+    fn time_controls() {
+        let tc: TimeControl = "40/60+0.5".parse().unwrap();
+        assert_eq!(tc, TimeControl { moves: Some(40), base: Duration::from_secs(60), increment: Duration::from_millis(500) });
+        assert_eq!(tc.to_string(), "40/60+0.5");
+
+        let tc: TimeControl = "10+0.1".parse().unwrap();
+        assert_eq!((tc.moves, tc.base, tc.increment), (None, Duration::from_secs(10), Duration::from_millis(100)));
+        assert_eq!("5".parse::<TimeControl>().unwrap().to_string(), "5");
+
+        for bad in ["", "abc", "0/60", "10+", "-1"] {
+            assert!(bad.parse::<TimeControl>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn plays_to_checkmate() {
+        let engine = builtin(2);
+        let played = play([&engine, &engine], &opening("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"), &Adjudication::default());
+
+        assert_eq!(played.result, GameResult::WhiteWins);
+        assert_eq!(played.reason, "White mates");
+        assert_eq!(played.game.moves, ["a1a8".parse().unwrap()]);
+        assert_eq!(played.game.tag("Termination"), Some("White mates"));
+    }
+
+    #[test]
+    fn book_moves_are_played_first() {
+        let engine = builtin(1);
+        let book = Opening { start: Position::default(), moves: vec!["f2f3".parse().unwrap(), "e7e5".parse().unwrap(), "g2g4".parse().unwrap()] };
+        let played = play([&engine, &engine], &book, &Adjudication::default());
+
+        assert_eq!(played.game.moves[..4], ["f2f3".parse().unwrap(), "e7e5".parse().unwrap(), "g2g4".parse().unwrap(), "d8h4".parse().unwrap()]);
+        assert_eq!(played.game.annotations[0].comment.as_deref(), Some("book"));
+        assert_eq!(played.result, GameResult::BlackWins);
+    }
+
+    #[test]
+    fn adjudication() {
+        let engine = builtin(1);
+
+        let tablebase = Adjudication { tablebase: true, ..Adjudication::default() };
+        let played = play([&engine, &engine], &opening("8/8/8/8/8/8/8/R3K2k b - - 0 1"), &tablebase);
+        assert_eq!((played.result, played.reason.as_str()), (GameResult::WhiteWins, "tablebase win"));
+        assert!(played.game.moves.is_empty());
+
+        let resign = Adjudication { resign: Some(ScoreRule { cp: 500, moves: 2 }), ..Adjudication::default() };
+        let played = play([&engine, &engine], &opening("q3k3/8/8/8/8/8/8/4K3 w - - 0 1"), &resign);
+        assert_eq!((played.result, played.reason.as_str()), (GameResult::BlackWins, "White resigns"));
+        assert_eq!(played.game.moves.len(), 4);
+
+        let draw = Adjudication { draw: Some(ScoreRule { cp: 20, moves: 2 }), draw_after: 0, ..Adjudication::default() };
+        let played = play([&engine, &engine], &opening("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1"), &draw);
+        assert_eq!((played.result, played.reason.as_str()), (GameResult::Draw, "draw by adjudication"));
+
+        let limit = Adjudication { max_moves: Some(3), ..Adjudication::default() };
+        let played = play([&engine, &engine], &opening(START_FEN), &limit);
+        assert_eq!((played.result, played.game.moves.len()), (GameResult::Draw, 6));
+    }
+
+    #[test]
+    fn uci_contestant() {
+        let stub = EngineSpec::Uci { name: String::from("stub"), config: EngineConfig::new(STUB) };
+        let engine = builtin(1);
+        let limit = Adjudication { max_moves: Some(1), ..Adjudication::default() };
+        let played = play([&stub, &engine], &opening(START_FEN), &limit);
+
+        assert_eq!(played.game.moves[0], "e2e4".parse().unwrap());
+        assert!(played.game.annotations[0].comment.as_deref().unwrap().starts_with("+0.20/1 "));
+    }
+
+    #[test]
+    fn crashed_engine_forfeits() {
+        let mut config = EngineConfig::new(STUB);
+        config.args.push(String::from("crash"));
+        let crash = EngineSpec::Uci { name: String::from("crash"), config };
+        let played = play([&crash, &builtin(1)], &opening(START_FEN), &Adjudication::default());
+
+        assert_eq!(played.result, GameResult::BlackWins);
+        assert!(played.reason.starts_with("White disconnects"), "{}", played.reason);
+    }
+
+    #[test]
+    fn runs_a_match() {
+        let config = MatchConfig {
+            engines: [builtin(1), builtin(2)],
+            games: 4,
+            time_control: "10+0.1".parse().unwrap(),
+            openings: vec![opening(START_FEN), opening("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1")],
+            adjudication: Adjudication { max_moves: Some(4), ..Adjudication::default() },
+            sprt: None,
+            concurrency: 2,
+        };
+
+        let mut rounds = Vec::new();
+        let tally = run(&config, |record, _| {
+            let game = &record.played.game;
+            let (white, black) = if record.first_white { ("depth1", "depth2") } else { ("depth2", "depth1") };
+
+            assert_eq!(record.first_white, record.round % 2 == 0);
+            assert_eq!((game.tag("White"), game.tag("Black")), (Some(white), Some(black)));
+            assert_eq!(game.tag("Round"), Some((record.round + 1).to_string().as_str()));
+            assert_eq!(game.start, config.openings[record.round / 2].start);
+            rounds.push(record.round);
+        })
+        .unwrap();
+
+        rounds.sort();
+        assert_eq!(rounds, [0, 1, 2, 3]);
+        assert_eq!(tally.games(), 4);
+    }
+
+    #[test]
+    fn missing_engine_fails_the_match() {
+        let missing = EngineSpec::Uci { name: String::from("missing"), config: EngineConfig::new("/nonexistent/engine") };
+        let config = MatchConfig {
+            engines: [builtin(1), missing],
+            games: 2,
+            time_control: "1".parse().unwrap(),
+            openings: Vec::new(),
+            adjudication: Adjudication::default(),
+            sprt: None,
+            concurrency: 1,
+        };
+
+        assert!(run(&config, |_, _| {}).is_err());
+    }
+
+    #[test]
+    fn tally_counts_from_the_first_engine() {
+        let mut tally = Tally::default();
+        let engine = builtin(1);
+        let won = play([&engine, &engine], &opening("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"), &Adjudication::default());
+
+        tally.add(&GameRecord { round: 0, first_white: true, played: won.clone() });
+        tally.add(&GameRecord { round: 1, first_white: false, played: won });
+
+        assert_eq!(tally, Tally { wins: 1, draws: 0, losses: 1 });
+    }
+}
@@ -0,0 +1,213 @@
+#[cfg(test)]
+mod test {
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use crate::piece::PieceColor;
+    use crate::rules::*;
+    use crate::uci::*;
+
+    const STUB: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/stub_engine.sh");
+    const WAIT: Duration = Duration::from_secs(5);
+
+    fn stub(args: &[&str]) -> EngineConfig {
+        let mut config = EngineConfig::new(STUB);
+        config.args = args.iter().map(|a| a.to_string()).collect();
+        config.timeout = Duration::from_millis(500);
+        config
+    }
+
+    /// Polls until an event matching `done` arrives and returns everything seen up to it.
+    fn until(engine: &mut UciEngine, done: impl Fn(&Event) -> bool) -> Vec<Event> {
+        let deadline = Instant::now() + WAIT;
+        let mut seen = Vec::new();
+
+        while Instant::now() < deadline {
+            for event in engine.poll() {
+                let last = done(&event);
+                seen.push(event);
+
+                if last {
+                    return seen;
+                }
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        panic!("gave up waiting, saw {seen:?}");
+    }
+
+    fn wait_ready(engine: &mut UciEngine) -> Vec<Event> {
+        until(engine, |e| *e == Event::Ready)
+    }
+
+    #[test]
+    fn parse_info_line() {
+        let Message::Info(info) = parse_line("info depth 12 seldepth 18 multipv 2 score cp -35 upperbound nodes 1000 nps 5000 time 200 pv e2e4 e7e5 g1f3") else {
+            panic!("expected info");
+        };
+
+        assert_eq!(info.depth, Some(12));
+        assert_eq!(info.seldepth, Some(18));
+        assert_eq!(info.multipv, Some(2));
+        assert_eq!(info.score, Some(Score::Cp(-35)));
+        assert_eq!(info.bound, Bound::Upper);
+        assert_eq!(info.nodes, Some(1000));
+        assert_eq!(info.time, Some(200));
+        assert_eq!(info.pv.len(), 3);
+        assert_eq!(info.pv[2], Move::new((6, 0), (5, 2)));
+    }
+
+    #[test]
+    fn parse_option_and_bestmove() {
+        let Message::Option(spec) = parse_line("option name Skill Level type spin default 20 min 0 max 20") else {
+            panic!("expected option");
+        };
+
+        assert_eq!(spec.name, "Skill Level");
+        assert_eq!(spec.kind, "spin");
+        assert_eq!(spec.default.as_deref(), Some("20"));
+        assert_eq!((spec.min, spec.max), (Some(0), Some(20)));
+
+        assert_eq!(
+            parse_line("bestmove e7e8q ponder a2a1"),
+            Message::BestMove {
+                best: Some(Move::promote((4, 6), (4, 7), crate::piece::Kind::Queen)),
+                ponder: Some(Move::new((0, 1), (0, 0))),
+            }
+        );
+        assert_eq!(parse_line("bestmove (none)"), Message::BestMove { best: None, ponder: None });
+    }
+
+    #[test]
+    fn position_command_lists_moves() {
+        let moves: Vec<Move> = vec!["e2e4".parse().unwrap(), "c7c5".parse().unwrap()];
+
+        assert_eq!(position_command(&Position::default(), &moves), "position startpos moves e2e4 c7c5");
+        assert_eq!(
+            position_command(&Position::from_fen("8/8/8/4k3/8/8/8/4K3 w - - 0 1").unwrap(), &[]),
+            "position fen 8/8/8/4k3/8/8/8/4K3 w - - 0 1"
+        );
+    }
+
+    #[test]
+    fn handshake_forwards_known_options() {
+        let mut config = stub(&[]);
+        config.options = vec![("hash".into(), "64".into()), ("Bogus".into(), "1".into()), ("Clear Hash".into(), "".into())];
+
+        let mut engine = UciEngine::spawn(config).unwrap();
+        let seen = wait_ready(&mut engine);
+
+        assert_eq!(engine.name.as_deref(), Some("Stub"));
+        assert_eq!(engine.options.len(), 3);
+        assert!(seen.contains(&Event::UnknownOption("Bogus".into())));
+
+        let echoed: Vec<String> = seen
+            .iter()
+            .filter_map(|e| match e {
+                Event::Info(info) => info.string.clone(),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(echoed, ["setoption name Hash value 64", "setoption name Clear Hash"]);
+    }
+
+    #[test]
+    fn search_returns_bestmove() {
+        let mut engine = UciEngine::spawn(stub(&[])).unwrap();
+        wait_ready(&mut engine);
+
+        engine.go(&Position::default(), &[], &Go::movetime(Duration::from_millis(50)));
+        assert!(!engine.is_ready());
+
+        let seen = until(&mut engine, |e| matches!(e, Event::BestMove { .. }));
+        let Event::Info(info) = &seen[0] else { panic!("expected info, saw {seen:?}") };
+
+        assert_eq!(info.score, Some(Score::Cp(20)));
+        assert_eq!(seen[1], Event::BestMove { best: "e2e4".parse().ok(), ponder: "e7e5".parse().ok() });
+        assert!(engine.is_ready());
+    }
+
+    #[test]
+    fn stop_ends_infinite_search() {
+        let mut engine = UciEngine::spawn(stub(&[])).unwrap();
+        wait_ready(&mut engine);
+
+        engine.go(&Position::default(), &[], &Go::infinite());
+        until(&mut engine, |e| matches!(e, Event::Info(_)));
+
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(engine.poll(), []);
+
+        engine.stop();
+        let seen = until(&mut engine, |e| matches!(e, Event::BestMove { .. }));
+        assert_eq!(seen, [Event::BestMove { best: "d2d4".parse().ok(), ponder: None }]);
+    }
+
+    #[test]
+    fn ponderhit_turns_ponder_into_search() {
+        let mut engine = UciEngine::spawn(stub(&[])).unwrap();
+        wait_ready(&mut engine);
+
+        let moves = ["e2e4".parse().unwrap(), "e7e5".parse().unwrap()];
+        let go = Go { wtime: Some(1000), btime: Some(1000), ponder: true, ..Go::default() };
+        engine.go(&Position::default(), &moves, &go);
+        until(&mut engine, |e| matches!(e, Event::Info(_)));
+
+        // A pondering engine has no deadline however long the opponent takes.
+        thread::sleep(Duration::from_millis(600));
+        assert_eq!(engine.poll(), []);
+        assert_eq!(engine.state(), State::Pondering);
+
+        engine.ponderhit();
+        let seen = until(&mut engine, |e| matches!(e, Event::BestMove { .. }));
+        assert_eq!(seen, [Event::BestMove { best: "g1f3".parse().ok(), ponder: "d7d5".parse().ok() }]);
+        assert!(engine.is_ready());
+    }
+
+    #[test]
+    fn stop_ends_ponder() {
+        let mut engine = UciEngine::spawn(stub(&[])).unwrap();
+        wait_ready(&mut engine);
+
+        engine.go(&Position::default(), &["e2e4".parse().unwrap()], &Go { ponder: true, ..Go::infinite() });
+        until(&mut engine, |e| matches!(e, Event::Info(_)));
+
+        engine.stop();
+        until(&mut engine, |e| matches!(e, Event::BestMove { .. }));
+        assert!(engine.is_ready());
+    }
+
+    #[test]
+    fn clock_gives_a_time_budget() {
+        let go = Go { wtime: Some(60_000), btime: Some(1000), winc: Some(1000), ..Go::default() };
+
+        assert_eq!(go.time_budget(PieceColor::White).unwrap().optimum, Duration::from_millis(2749));
+        assert!(go.time_budget(PieceColor::Black).unwrap().optimum < Duration::from_millis(500));
+        assert_eq!(Go::movetime(Duration::from_secs(1)).time_budget(PieceColor::White), None);
+    }
+
+    #[test]
+    fn crash_is_reported() {
+        let mut engine = UciEngine::spawn(stub(&["crash"])).unwrap();
+        wait_ready(&mut engine);
+
+        engine.go(&Position::default(), &[], &Go::movetime(Duration::from_millis(50)));
+        until(&mut engine, |e| matches!(e, Event::Crashed(_)));
+
+        assert!(!engine.is_ready());
+    }
+
+    #[test]
+    fn silent_engine_times_out() {
+        let mut engine = UciEngine::spawn(stub(&["mute"])).unwrap();
+        let seen = until(&mut engine, |e| matches!(e, Event::TimedOut(_)));
+
+        assert_eq!(seen, [Event::TimedOut(State::Handshake)]);
+    }
+
+    #[test]
+    fn missing_binary_fails_to_spawn() {
+        assert!(UciEngine::spawn(EngineConfig::new("/nonexistent/engine")).is_err());
+    }
+}
@@ -0,0 +1,277 @@
+//! Board themes and piece sets. Each is a folder under `assets/themes` or `assets/pieces` with a
+//! `manifest.ron`; they are found when the app starts and switched while it runs. The choice is
+//! kept in the settings.
+
+use std::path::Path;
+
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::consts::{COLORS, CURSOR_COLOR, HINT_COLOR, LAST_MOVE_COLOR, PREMOVE_COLOR, SELECTED_COLOR};
+use crate::keyboard::typing;
+use crate::piece::{Kind, PieceColor};
+use crate::rules::Square;
+use crate::settings::Settings;
+use crate::states::{in_game, AppState};
+
+const MANIFEST: &str = "manifest.ron";
+const KINDS: [Kind; 6] = [Kind::Pawn, Kind::Knight, Kind::Bishop, Kind::Rook, Kind::Queen, Kind::King];
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        let mut appearance = Appearance::discover(&FileAssetReader::get_base_path().join("assets"));
+        if let Some(settings) = app.world().get_resource::<Settings>() {
+            appearance.choose_theme(&settings.theme);
+            appearance.choose_pieces(&settings.pieces);
+        }
+
+        app
+            .insert_resource(appearance)
+            .add_systems(Update, switch_appearance.run_if(in_game.or_else(in_state(AppState::Editor))).run_if(not(typing)));
+    }
+}
+
+/// Colours of the squares and of what is drawn over them.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    /// Name of the folder the theme came from.
+    pub id: String,
+    pub name: String,
+    pub light: Color,
+    pub dark: Color,
+    /// Asset paths of images drawn on the squares instead of the plain colours.
+    pub light_texture: Option<String>,
+    pub dark_texture: Option<String>,
+    pub selected: Color,
+    pub last_move: Color,
+    pub hint: Color,
+    pub cursor: Color,
+    /// Squares of moves queued while the opponent is to move.
+    pub premove: Color,
+}
+
+/// Piece images, one per kind and colour, in `white/` and `black/` of the set's folder.
+#[derive(Clone, Debug)]
+pub struct PieceSet {
+    pub id: String,
+    pub name: String,
+    /// Sprite scale at the default square size, across and up.
+    pub scale: Vec2,
+}
+
+/// Every theme and piece set found, and the ones in use.
+#[derive(Resource, Clone, Debug)]
+pub struct Appearance {
+    pub themes: Vec<Theme>,
+    pub piece_sets: Vec<PieceSet>,
+    theme: usize,
+    pieces: usize,
+}
+
+#[derive(Deserialize)]
+struct ThemeManifest {
+    name: String,
+    light: String,
+    dark: String,
+    #[serde(default)]
+    light_texture: Option<String>,
+    #[serde(default)]
+    dark_texture: Option<String>,
+    #[serde(default)]
+    selected: Option<String>,
+    #[serde(default)]
+    last_move: Option<String>,
+    #[serde(default)]
+    hint: Option<String>,
+    #[serde(default)]
+    cursor: Option<String>,
+    #[serde(default)]
+    premove: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PieceSetManifest {
+    name: String,
+    scale: (f32, f32),
+}
+
+/// Whether a square is a light one; a1 is dark.
+pub fn light_square((c, r): Square) -> bool {
+    (c + r) & 1 == 1
+}
+
+/// `#rrggbb` or `#rrggbbaa`.
+pub fn parse_color(text: &str) -> Result<Color, String> {
+    Srgba::hex(text).map(Color::from).map_err(|_| format!("not a colour: {text}"))
+}
+
+/// Optional fields are written plainly, without `Some(...)`.
+fn read_ron<T: serde::de::DeserializeOwned>(text: &str) -> Result<T, String> {
+    ron::Options::default().with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME).from_str(text).map_err(|e| e.to_string())
+}
+
+impl Theme {
+    /// Reads the manifest of the theme in folder `id`.
+    pub fn from_manifest(id: &str, text: &str) -> Result<Self, String> {
+        let manifest: ThemeManifest = read_ron(text)?;
+        let color = |text: Option<String>, default: Color| text.map_or(Ok(default), |text| parse_color(&text));
+        let texture = |file: Option<String>| file.map(|file| format!("themes/{id}/{file}"));
+
+        Ok(Self {
+            id: id.to_string(),
+            name: manifest.name,
+            light: parse_color(&manifest.light)?,
+            dark: parse_color(&manifest.dark)?,
+            light_texture: texture(manifest.light_texture),
+            dark_texture: texture(manifest.dark_texture),
+            selected: color(manifest.selected, SELECTED_COLOR)?,
+            last_move: color(manifest.last_move, LAST_MOVE_COLOR)?,
+            hint: color(manifest.hint, HINT_COLOR)?,
+            cursor: color(manifest.cursor, CURSOR_COLOR)?,
+            premove: color(manifest.premove, PREMOVE_COLOR)?,
+        })
+    }
+
+    pub fn square_color(&self, sq: Square) -> Color {
+        if light_square(sq) { self.light } else { self.dark }
+    }
+
+    pub fn square_texture(&self, sq: Square) -> Option<&str> {
+        if light_square(sq) { self.light_texture.as_deref() } else { self.dark_texture.as_deref() }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            id: String::from("brown"),
+            name: String::from("Brown"),
+            light: COLORS[1],
+            dark: COLORS[0],
+            light_texture: None,
+            dark_texture: None,
+            selected: SELECTED_COLOR,
+            last_move: LAST_MOVE_COLOR,
+            hint: HINT_COLOR,
+            cursor: CURSOR_COLOR,
+            premove: PREMOVE_COLOR,
+        }
+    }
+}
+
+impl PieceSet {
+    /// Reads the manifest of the piece set in folder `id`.
+    pub fn from_manifest(id: &str, text: &str) -> Result<Self, String> {
+        let manifest: PieceSetManifest = read_ron(text)?;
+
+        Ok(Self { id: id.to_string(), name: manifest.name, scale: manifest.scale.into() })
+    }
+
+    /// Asset path of the image for a piece.
+    pub fn path(&self, team: PieceColor, kind: Kind) -> String {
+        format!("pieces/{}/{}/{}.png", self.id, team.name(), kind.name())
+    }
+}
+
+impl Default for PieceSet {
+    fn default() -> Self {
+        Self { id: String::from("classic"), name: String::from("Classic"), scale: Vec2::new(0.4, 0.35) }
+    }
+}
+
+impl Default for Appearance {
+    fn default() -> Self {
+        Self { themes: vec![Theme::default()], piece_sets: vec![PieceSet::default()], theme: 0, pieces: 0 }
+    }
+}
+
+impl Appearance {
+    /// Everything under `assets` with a readable manifest, sorted by name. A piece set missing
+    /// any of its twelve images is left out. The built-in theme and set stand in when none is found.
+    pub fn discover(assets: &Path) -> Self {
+        let mut themes = read_manifests(&assets.join("themes"), Theme::from_manifest);
+        themes.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut piece_sets = read_manifests(&assets.join("pieces"), PieceSet::from_manifest);
+        piece_sets.retain(|set| {
+            let missing = KINDS.iter().flat_map(|&kind| [PieceColor::White, PieceColor::Black].map(|team| set.path(team, kind))).find(|path| !assets.join(path).is_file());
+            if let Some(path) = &missing {
+                warn!("piece set {} has no {path}", set.id);
+            }
+            missing.is_none()
+        });
+        piece_sets.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut appearance = Self::default();
+        if !themes.is_empty() {
+            appearance.themes = themes;
+        }
+        if !piece_sets.is_empty() {
+            appearance.piece_sets = piece_sets;
+        }
+
+        // The built-in look, if it was found, is where a first run starts.
+        appearance.choose_theme(&Theme::default().id);
+        appearance.choose_pieces(&PieceSet::default().id);
+        appearance
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.themes[self.theme]
+    }
+
+    pub fn pieces(&self) -> &PieceSet {
+        &self.piece_sets[self.pieces]
+    }
+
+    /// Picks the theme from folder `id`; false, and no change, if there is none.
+    pub fn choose_theme(&mut self, id: &str) -> bool {
+        self.themes.iter().position(|theme| theme.id == id).map(|i| self.theme = i).is_some()
+    }
+
+    pub fn choose_pieces(&mut self, id: &str) -> bool {
+        self.piece_sets.iter().position(|set| set.id == id).map(|i| self.pieces = i).is_some()
+    }
+
+    pub fn next_theme(&mut self) {
+        self.theme = (self.theme + 1) % self.themes.len();
+    }
+
+    pub fn next_pieces(&mut self) {
+        self.pieces = (self.pieces + 1) % self.piece_sets.len();
+    }
+}
+
+/// Parses the manifest in every subfolder of `dir`, logging the ones that fail.
+fn read_manifests<T>(dir: &Path, parse: impl Fn(&str, &str) -> Result<T, String>) -> Vec<T> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new(); };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let id = entry.file_name().to_str()?.to_string();
+            let path = entry.path().join(MANIFEST);
+            let text = std::fs::read_to_string(&path).ok()?;
+
+            parse(&id, &text).map_err(|err| warn!("skipping {}: {err}", path.display())).ok()
+        })
+        .collect()
+}
+
+/// T moves on to the next theme, Shift+T to the next piece set.
+fn switch_appearance(keys: Res<ButtonInput<KeyCode>>, mut appearance: ResMut<Appearance>) {
+    if !keys.just_pressed(KeyCode::KeyT) {
+        return;
+    }
+
+    if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        appearance.next_pieces();
+        info!("piece set: {}", appearance.pieces().name);
+    } else {
+        appearance.next_theme();
+        info!("board theme: {}", appearance.theme().name);
+    }
+}
@@ -0,0 +1,298 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::{text_style, PanelColumn};
+use crate::consts::{IDLE_TEXT_COLOR, TEXT_COLOR};
+use crate::game::Game;
+use crate::piece::PieceColor;
+use crate::rules::ParseError;
+use crate::setup::{GameConfig, NewGameEvent};
+use crate::states::AppState;
+use crate::tournament::TimeControl;
+use crate::uci::Go;
+
+pub struct TimerPlugin;
+
+impl Plugin for TimerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ResumeClocks>()
+            .init_resource::<ClockHistory>()
+            .add_systems(Update, reset_clock)
+            .add_systems(Update, update_timer.after(reset_clock).run_if(resource_exists::<ClockSettings>).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// How the `+` part of a time control is given back to a player.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Timing {
+    /// Fischer increment: added after every move.
+    #[default]
+    Increment,
+    /// Bronstein delay: the time used on a move is given back, up to the delay.
+    Bronstein,
+    /// Simple (US) delay: the clock waits that long before it starts running down.
+    Simple,
+}
+
+/// Time control both players' clocks run on in the current game.
+/// Stages follow each other like the periods of a PGN `TimeControl` tag, e.g. `40/5400+30:1800+30`;
+/// the last one lasts the rest of the game, or starts again if it has a move count.
+#[derive(Resource, Clone, Debug)]
+pub struct ClockSettings {
+    pub stages: Vec<TimeControl>,
+    pub timing: Timing,
+}
+
+/// Readings the next game's clocks start from instead of the time control's, for a game
+/// picked up where it was left.
+#[derive(Resource, Default)]
+pub struct ResumeClocks {
+    pub timers: Vec<Timer>,
+    /// The game's [`ClockHistory`] so far, so its moves can be taken back as if never left.
+    pub history: Vec<(usize, Timer)>,
+}
+
+/// Each clock's reading after every move its side made, by the plies made then; taking moves
+/// back brings them back.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct ClockHistory(pub Vec<(usize, Timer)>);
+
+#[derive(Component)]
+struct ClockRow;
+
+/// Seconds left on `color`'s clock, shown on the text it sits on.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Timer {
+    pub time: f32,
+    pub color: PieceColor,
+    /// Moves made so far.
+    pub moves: u32,
+    /// Stage of the time control the clock is in, and the moves made in it.
+    pub stage: usize,
+    pub stage_moves: u32,
+    /// Seconds spent on the move being thought about, for the delays.
+    pub thinking: f32,
+}
+
+impl FromStr for ClockSettings {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let stages = s.split(':').map(str::parse).collect::<Result<Vec<TimeControl>, _>>()?;
+
+        // A stage with no move count never ends, so nothing can follow it.
+        if stages[..stages.len() - 1].iter().any(|stage| stage.moves.is_none()) {
+            return Err(ParseError(format!("only the last stage of {s} may lack a move count")));
+        }
+
+        Ok(Self { stages, timing: Timing::default() })
+    }
+}
+
+impl Timer {
+    pub fn new(color: PieceColor, clock: &ClockSettings) -> Self {
+        Self { time: clock.stage(0).base.as_secs_f32(), color, moves: 0, stage: 0, stage_moves: 0, thinking: 0. }
+    }
+
+    /// Runs the clock down while its side thinks; a simple delay holds it for the first seconds of each move.
+    pub fn tick(&mut self, clock: &ClockSettings, seconds: f32) {
+        let before = self.thinking;
+        self.thinking += seconds;
+
+        self.time -= match clock.timing {
+            Timing::Simple => {
+                let delay = clock.stage(self.stage).increment.as_secs_f32();
+                (self.thinking - delay).max(0.) - (before - delay).max(0.)
+            }
+            Timing::Increment | Timing::Bronstein => seconds,
+        };
+    }
+
+    /// Credits the move just made, and moves to the next stage once this one's moves are made.
+    pub fn moved(&mut self, clock: &ClockSettings) {
+        let stage = clock.stage(self.stage);
+        let bonus = stage.increment.as_secs_f32();
+
+        self.time += match clock.timing {
+            Timing::Increment => bonus,
+            Timing::Bronstein => bonus.min(self.thinking),
+            Timing::Simple => 0.,
+        };
+
+        self.moves += 1;
+        self.stage_moves += 1;
+        self.thinking = 0.;
+
+        if stage.moves == Some(self.stage_moves) {
+            self.stage += 1;
+            self.stage_moves = 0;
+            self.time += clock.stage(self.stage).base.as_secs_f32();
+        }
+    }
+}
+
+/// Minutes and seconds, with tenths under ten seconds.
+impl fmt::Display for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time = self.time.max(0.);
+
+        if time < 10. {
+            let tenths = (time * 10.) as i32;
+            return write!(f, "{:?} 0:{:02}.{}", self.color, tenths / 10, tenths % 10);
+        }
+
+        let time = time as i32;
+        write!(f, "{:?} {}:{:02}", self.color, time / 60, time % 60)
+    }
+}
+
+/// Stages as given on the command line.
+impl fmt::Display for ClockSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stages: Vec<String> = self.stages.iter().map(|stage| stage.to_string()).collect();
+        write!(f, "{}", stages.join(":"))
+    }
+}
+
+impl ClockSettings {
+    /// Stage `i` of the time control; past the last stage the last one repeats.
+    pub fn stage(&self, i: usize) -> &TimeControl {
+        &self.stages[i.min(self.stages.len() - 1)]
+    }
+
+    /// Search command with both clocks as they stand, `turn` being the side to think.
+    /// UCI knows no delays, so they are passed on as increments: the engine gets about as much back.
+    pub fn go<'a>(&self, turn: PieceColor, timers: impl IntoIterator<Item = &'a Timer>) -> Go {
+        let ms = |t: f32| Some((t.max(0.) * 1000.) as u64);
+        let mut go = Go::default();
+
+        for timer in timers {
+            let stage = self.stage(timer.stage);
+            let increment = Some(stage.increment.as_millis() as u64);
+
+            match timer.color {
+                PieceColor::White => (go.wtime, go.winc) = (ms(timer.time), increment),
+                PieceColor::Black => (go.btime, go.binc) = (ms(timer.time), increment),
+            }
+
+            if timer.color == turn {
+                go.movestogo = stage.moves.map(|m| m - timer.stage_moves);
+            }
+        }
+
+        go
+    }
+}
+
+/// Brings `timer` up to date with a game that has gone from `played` plies to `moves`, `turn` now
+/// to move. Every new ply its side made is credited and its reading kept in `history`, by the
+/// plies made then; moves taken back bring back the reading after this side's last move still on
+/// the board. Redo replays a pair of moves at once, so several plies may come in together.
+pub fn follow_moves(timer: &mut Timer, clock: &ClockSettings, history: &mut Vec<(usize, Timer)>, played: usize, moves: usize, turn: PieceColor) {
+    if moves < played {
+        history.retain(|&(ply, _)| ply <= moves);
+        let earlier = history.iter().rev().find(|(_, t)| t.color == timer.color);
+        *timer = earlier.map_or_else(|| Timer::new(timer.color, clock), |&(_, t)| t);
+        return;
+    }
+
+    for ply in played..moves {
+        // The last ply was made by the side not to move, the one before it by the other.
+        let mover = if (moves - ply) % 2 == 1 { turn.other() } else { turn };
+
+        if mover == timer.color {
+            timer.moved(clock);
+            history.push((ply + 1, *timer));
+        }
+    }
+}
+
+/// Sets the clocks of a new game up from its config, or takes them away if it has none.
+fn reset_clock(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    mut resume: ResMut<ResumeClocks>,
+    mut history: ResMut<ClockHistory>,
+    column: Query<Entity, With<PanelColumn>>,
+    rows: Query<Entity, With<ClockRow>>,
+    mut new_game_ev: EventReader<NewGameEvent>,
+) {
+    if new_game_ev.read().count() == 0 {
+        return;
+    }
+
+    for row in &rows {
+        commands.entity(row).despawn_recursive();
+    }
+
+    let resumed = std::mem::take(&mut resume.timers);
+    history.0 = std::mem::take(&mut resume.history);
+
+    // The setup screen has checked the time control already.
+    let Ok(Some(clock)) = config.clock() else {
+        commands.remove_resource::<ClockSettings>();
+        return;
+    };
+
+    let row = commands
+        .spawn((
+            NodeBundle {
+                style: Style { justify_content: JustifyContent::SpaceBetween, ..default() },
+                ..default()
+            },
+            ClockRow,
+        ))
+        .with_children(|row| {
+            for color in [PieceColor::White, PieceColor::Black] {
+                let timer = resumed.iter().find(|t| t.color == color).copied().unwrap_or_else(|| Timer::new(color, &clock));
+                row.spawn((TextBundle::from_section(timer.to_string(), text_style(&asset_server, 28.)), timer));
+            }
+        })
+        .id();
+
+    commands.entity(column.single()).insert_children(0, &[row]);
+    commands.insert_resource(clock);
+}
+
+fn update_timer(
+    clock: Res<ClockSettings>,
+    time: Res<Time>,
+    mut game: ResMut<Game>,
+    mut played: Local<usize>,
+    mut history: ResMut<ClockHistory>,
+    mut timers: Query<(&mut Text, &mut Timer)>,
+) {
+    let moves = game.moves.len();
+    let turn = game.position.turn;
+    let running = game.outcome().is_none();
+
+    // Clocks just set up already read what they should, whatever was played before.
+    if timers.iter_mut().any(|(_, timer)| timer.is_added()) {
+        *played = moves;
+    }
+
+    for (mut text, mut timer) in &mut timers {
+        follow_moves(&mut timer, &clock, &mut history.0, *played, moves, turn);
+
+        if running && timer.color == turn {
+            timer.tick(&clock, time.delta_seconds());
+
+            // Flagging ends the game, so this happens once.
+            if timer.time <= 0. {
+                game.flag(turn);
+                info!("game over: {turn:?} lost on time");
+            }
+        }
+
+        let section = &mut text.sections[0];
+        section.value = timer.to_string();
+        section.style.color = if running && timer.color == turn { TEXT_COLOR } else { IDLE_TEXT_COLOR };
+    }
+
+    *played = moves;
+}
@@ -0,0 +1,576 @@
+//! Engine-vs-engine matches: playing the games, keeping the clocks and deciding
+//! when a game is over before the rules say so.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::game::Game;
+use crate::pgn::{Annotation, PgnGame};
+use crate::piece::PieceColor;
+use crate::rules::{Move, Outcome, ParseError, Position};
+use crate::search::{self, Limits, MATE};
+use crate::stats::{Sprt, SprtStatus, Tally};
+use crate::tablebase::{Tablebase, Wdl};
+use crate::uci::{EngineConfig, Event, Go, Score, State, UciEngine};
+
+/// `moves/base+increment` in seconds, e.g. `40/60+0.5` or `10+0.1`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TimeControl {
+    pub moves: Option<u32>,
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub enum EngineSpec {
+    Builtin { name: String, limits: Limits },
+    Uci { name: String, config: EngineConfig },
+}
+
+/// Score limits past which a game is called; scores are the engines' own.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ScoreRule {
+    pub cp: i32,
+    pub moves: u32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Adjudication {
+    /// Both engines agree one side is at least `cp` ahead for `moves` moves each.
+    pub resign: Option<ScoreRule>,
+    /// Both engines stay within `cp` of equality for `moves` moves each, from move `draw_after` on.
+    pub draw: Option<ScoreRule>,
+    pub draw_after: u32,
+    /// Positions with three men or fewer are looked up.
+    pub tablebase: bool,
+    pub max_moves: Option<u32>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Opening {
+    pub start: Position,
+    pub moves: Vec<Move>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+/// A move and what the engine thought of it, the score from its own side.
+#[derive(Clone, Debug)]
+pub struct Thought {
+    pub best: Move,
+    pub score: Option<Score>,
+    pub depth: Option<u32>,
+}
+
+pub trait Contestant {
+    fn new_game(&mut self) -> Result<(), String>;
+    fn think(&mut self, game: &Game, go: &Go) -> Result<Thought, String>;
+}
+
+pub struct BuiltinContestant {
+    limits: Limits,
+}
+
+pub struct UciContestant {
+    engine: UciEngine,
+    config: EngineConfig,
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayedGame {
+    pub game: PgnGame,
+    pub result: GameResult,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchConfig {
+    pub engines: [EngineSpec; 2],
+    pub games: usize,
+    pub time_control: TimeControl,
+    /// Played in order, each twice with colours swapped; the standard start if empty.
+    pub openings: Vec<Opening>,
+    pub adjudication: Adjudication,
+    pub sprt: Option<Sprt>,
+    pub concurrency: usize,
+}
+
+/// A finished game of a match; `first_white` tells which engine had White.
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    pub round: usize,
+    pub first_white: bool,
+    pub played: PlayedGame,
+}
+
+impl FromStr for TimeControl {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseError(format!("bad time control {s}"));
+        let seconds = |v: &str| v.parse::<f64>().ok().filter(|v| *v >= 0.).map(Duration::from_secs_f64).ok_or_else(err);
+
+        let (moves, rest) = match s.split_once('/') {
+            Some((moves, rest)) => (Some(moves.parse().ok().filter(|&m| m > 0).ok_or_else(err)?), rest),
+            None => (None, s),
+        };
+
+        let (base, increment) = match rest.split_once('+') {
+            Some((base, inc)) => (seconds(base)?, seconds(inc)?),
+            None => (seconds(rest)?, Duration::ZERO),
+        };
+
+        Ok(Self { moves, base, increment })
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(moves) = self.moves {
+            write!(f, "{moves}/")?;
+        }
+
+        write!(f, "{}", self.base.as_secs_f64())?;
+
+        if !self.increment.is_zero() {
+            write!(f, "+{}", self.increment.as_secs_f64())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl EngineSpec {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Builtin { name, .. } | Self::Uci { name, .. } => name,
+        }
+    }
+
+    pub fn start(&self) -> Result<Box<dyn Contestant>, String> {
+        match self {
+            Self::Builtin { limits, .. } => Ok(Box::new(BuiltinContestant { limits: limits.clone() })),
+            Self::Uci { config, .. } => Ok(Box::new(UciContestant::spawn(config.clone())?)),
+        }
+    }
+}
+
+impl GameResult {
+    pub fn pgn(self) -> &'static str {
+        match self {
+            Self::WhiteWins => "1-0",
+            Self::BlackWins => "0-1",
+            Self::Draw => "1/2-1/2",
+        }
+    }
+
+    fn win_for(color: PieceColor) -> Self {
+        match color {
+            PieceColor::White => Self::WhiteWins,
+            PieceColor::Black => Self::BlackWins,
+        }
+    }
+}
+
+impl Contestant for BuiltinContestant {
+    fn new_game(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn think(&mut self, game: &Game, go: &Go) -> Result<Thought, String> {
+        let mut limits = self.limits.clone();
+        if limits.depth.is_none() && limits.nodes.is_none() {
+            limits.budget = go.time_budget(game.position.turn);
+        }
+
+        let keys = game.keys();
+        let mut last = None;
+        let stop = AtomicBool::new(false);
+        let best = search::search(&game.position, &keys[..keys.len() - 1], &limits, 1, &stop, |info| last = Some(info));
+
+        Ok(Thought {
+            best: best.ok_or("no legal move")?,
+            score: last.as_ref().and_then(|info| info.score),
+            depth: last.and_then(|info| info.depth),
+        })
+    }
+}
+
+impl UciContestant {
+    fn spawn(config: EngineConfig) -> Result<Self, String> {
+        let engine = UciEngine::spawn(config.clone()).map_err(|e| format!("failed to start {}: {e}", config.path.display()))?;
+        let mut contestant = Self { engine, config };
+        contestant.wait_ready()?;
+        Ok(contestant)
+    }
+
+    fn wait_ready(&mut self) -> Result<(), String> {
+        loop {
+            match self.engine.next_event(Duration::from_secs(1)) {
+                Some(Event::Ready) => return Ok(()),
+                Some(Event::Crashed(reason)) => return Err(reason),
+                Some(Event::TimedOut(state)) => return Err(format!("timed out while in {state:?}")),
+                None if self.engine.state() == State::Dead => return Err(String::from("engine is gone")),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Contestant for UciContestant {
+    fn new_game(&mut self) -> Result<(), String> {
+        // An engine that crashed last game gets a fresh process.
+        if self.engine.state() == State::Dead {
+            *self = Self::spawn(self.config.clone())?;
+            return Ok(());
+        }
+
+        self.engine.new_game();
+        self.wait_ready()
+    }
+
+    fn think(&mut self, game: &Game, go: &Go) -> Result<Thought, String> {
+        self.engine.go(&game.start, &game.moves, go);
+
+        let (mut score, mut depth) = (None, None);
+        loop {
+            match self.engine.next_event(Duration::from_secs(1)) {
+                Some(Event::Info(info)) if info.multipv.unwrap_or(1) == 1 && info.score.is_some() => {
+                    score = info.score;
+                    depth = info.depth;
+                }
+                Some(Event::BestMove { best, .. }) => {
+                    return Ok(Thought { best: best.ok_or("no move returned")?, score, depth });
+                }
+                Some(Event::Crashed(reason)) => return Err(reason),
+                Some(Event::TimedOut(state)) => return Err(format!("timed out while in {state:?}")),
+                None if self.engine.state() == State::Dead => return Err(String::from("engine is gone")),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// A score from the side to move, in centipawns from White's point of view; mates count as `MATE`.
+fn white_cp(turn: PieceColor, score: Score) -> i32 {
+    let cp = match score {
+        Score::Cp(cp) => cp,
+        Score::Mate(n) if n > 0 => MATE,
+        Score::Mate(_) => -MATE,
+    };
+
+    if turn == PieceColor::White { cp } else { -cp }
+}
+
+fn outcome_reason(outcome: Outcome) -> (GameResult, String) {
+    match outcome {
+        Outcome::Checkmate(winner) => (GameResult::win_for(winner), format!("{winner:?} mates")),
+        Outcome::Stalemate => (GameResult::Draw, String::from("stalemate")),
+        Outcome::InsufficientMaterial => (GameResult::Draw, String::from("insufficient material")),
+        Outcome::FiftyMoves => (GameResult::Draw, String::from("fifty-move rule")),
+        Outcome::Repetition => (GameResult::Draw, String::from("threefold repetition")),
+        Outcome::Timeout(winner) => (GameResult::win_for(winner), format!("{:?} loses on time", winner.other())),
+        Outcome::TimeoutVsInsufficientMaterial => (GameResult::Draw, String::from("time out against insufficient material")),
+        Outcome::Resignation(winner) => (GameResult::win_for(winner), format!("{:?} resigns", winner.other())),
+        Outcome::Agreement => (GameResult::Draw, String::from("draw agreed")),
+    }
+}
+
+impl Adjudication {
+    /// Verdict on the game so far; `scores` holds White's view after each move, `None` for book moves.
+    fn check(&self, game: &Game, scores: &[Option<i32>], tablebase: &Tablebase) -> Option<(GameResult, String)> {
+        if self.tablebase {
+            let turn = game.position.turn;
+            match tablebase.probe(&game.position) {
+                Some(Wdl::Win) => return Some((GameResult::win_for(turn), String::from("tablebase win"))),
+                Some(Wdl::Loss) => return Some((GameResult::win_for(turn.other()), String::from("tablebase win"))),
+                Some(Wdl::Draw) => return Some((GameResult::Draw, String::from("tablebase draw"))),
+                None => {}
+            }
+        }
+
+        let recent = |rule: &ScoreRule| {
+            let n = 2 * rule.moves as usize;
+            (scores.len() >= n).then(|| &scores[scores.len() - n..]).filter(|s| s.iter().all(Option::is_some))
+        };
+
+        if let Some((rule, recent)) = self.resign.as_ref().and_then(|rule| Some((rule, recent(rule)?))) {
+            if recent.iter().all(|s| s.unwrap() >= rule.cp) {
+                return Some((GameResult::WhiteWins, String::from("Black resigns")));
+            }
+            if recent.iter().all(|s| s.unwrap() <= -rule.cp) {
+                return Some((GameResult::BlackWins, String::from("White resigns")));
+            }
+        }
+
+        if let Some((rule, recent)) = self.draw.as_ref().and_then(|rule| Some((rule, recent(rule)?))) {
+            if game.position.fullmove_number > self.draw_after && recent.iter().all(|s| s.unwrap().abs() <= rule.cp) {
+                return Some((GameResult::Draw, String::from("draw by adjudication")));
+            }
+        }
+
+        if self.max_moves.is_some_and(|max| game.position.fullmove_number > max) {
+            return Some((GameResult::Draw, String::from("move limit reached")));
+        }
+
+        None
+    }
+}
+
+/// Plays one game from `opening`, `players[0]` with White, until the rules or `adjudication` end it.
+pub fn play_game(
+    mut players: [&mut dyn Contestant; 2],
+    opening: &Opening,
+    time_control: &TimeControl,
+    adjudication: &Adjudication,
+    tablebase: &Tablebase,
+) -> PlayedGame {
+    let mut game = Game::new(opening.start.clone());
+    let mut annotations = Vec::new();
+    let mut scores = Vec::new();
+
+    for &mv in &opening.moves {
+        if !game.position.is_legal(mv) {
+            break;
+        }
+
+        game.play(mv);
+        annotations.push(Annotation { comment: Some(String::from("book")), ..Annotation::default() });
+        scores.push(None);
+    }
+
+    let mut clocks = [time_control.base; 2];
+    let mut moves_played = [0u32; 2];
+
+    for (player, color) in players.iter_mut().zip([PieceColor::White, PieceColor::Black]) {
+        if let Err(err) = player.new_game() {
+            return finish(game, annotations, GameResult::win_for(color.other()), format!("{color:?} disconnects ({err})"));
+        }
+    }
+
+    let (result, reason) = loop {
+        if let Some(outcome) = game.outcome() {
+            break outcome_reason(outcome);
+        }
+
+        if let Some(verdict) = adjudication.check(&game, &scores, tablebase) {
+            break verdict;
+        }
+
+        let turn = game.position.turn;
+        let side = (turn == PieceColor::Black) as usize;
+        let ms = |d: Duration| Some(d.as_millis() as u64);
+        let go = Go {
+            wtime: ms(clocks[0]),
+            btime: ms(clocks[1]),
+            winc: ms(time_control.increment),
+            binc: ms(time_control.increment),
+            movestogo: time_control.moves.map(|m| m - moves_played[side] % m),
+            ..Go::default()
+        };
+
+        let started = Instant::now();
+        let thought = match players[side].think(&game, &go) {
+            Ok(thought) => thought,
+            Err(err) => break (GameResult::win_for(turn.other()), format!("{turn:?} disconnects ({err})")),
+        };
+        let used = started.elapsed();
+
+        if used > clocks[side] {
+            if game.position.has_mating_material(turn.other()) {
+                break (GameResult::win_for(turn.other()), format!("{turn:?} loses on time"));
+            }
+            break (GameResult::Draw, format!("{turn:?} loses on time against insufficient material"));
+        }
+
+        if !game.position.is_legal(thought.best) {
+            break (GameResult::win_for(turn.other()), format!("{turn:?} makes an illegal move: {}", thought.best));
+        }
+
+        clocks[side] = clocks[side] - used + time_control.increment;
+        moves_played[side] += 1;
+        if time_control.moves.is_some_and(|m| moves_played[side] % m == 0) {
+            clocks[side] += time_control.base;
+        }
+
+        let comment = match (thought.score, thought.depth) {
+            (Some(score), Some(depth)) => format!("{}/{depth} {:.3}s", score_comment(score), used.as_secs_f64()),
+            (Some(score), None) => format!("{} {:.3}s", score_comment(score), used.as_secs_f64()),
+            (None, _) => format!("{:.3}s", used.as_secs_f64()),
+        };
+
+        annotations.push(Annotation { comment: Some(comment), ..Annotation::default() });
+        scores.push(thought.score.map(|s| white_cp(turn, s)));
+        game.play(thought.best);
+    };
+
+    finish(game, annotations, result, reason)
+}
+
+fn score_comment(score: Score) -> String {
+    match score {
+        Score::Cp(cp) => format!("{:+.2}", cp as f64 / 100.),
+        Score::Mate(n) if n > 0 => format!("+M{n}"),
+        Score::Mate(n) => format!("-M{}", -n),
+    }
+}
+
+fn finish(game: Game, annotations: Vec<Annotation>, result: GameResult, reason: String) -> PlayedGame {
+    let mut pgn = PgnGame::new(game.start.clone());
+    pgn.moves = game.moves;
+    pgn.annotations = annotations;
+    pgn.result = result.pgn().to_string();
+    pgn.set_tag("Termination", reason.clone());
+
+    PlayedGame { game: pgn, result, reason }
+}
+
+impl Tally {
+    pub fn add(&mut self, record: &GameRecord) {
+        match (record.played.result, record.first_white) {
+            (GameResult::Draw, _) => self.draws += 1,
+            (GameResult::WhiteWins, true) | (GameResult::BlackWins, false) => self.wins += 1,
+            _ => self.losses += 1,
+        }
+    }
+}
+
+/// Plays the match on `config.concurrency` threads, handing each game to `on_game`
+/// as it finishes. Stops early once the SPRT is decided.
+pub fn run(config: &MatchConfig, mut on_game: impl FnMut(&GameRecord, &Tally)) -> Result<Tally, String> {
+    let start = [Opening { start: Position::default(), moves: Vec::new() }];
+    let openings = if config.openings.is_empty() { &start[..] } else { &config.openings[..] };
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let tablebase = Tablebase::default();
+    let (tx, rx) = mpsc::channel();
+    let date = today();
+
+    let mut tally = Tally::default();
+    let mut failure = None;
+
+    thread::scope(|scope| {
+        for _ in 0..config.concurrency.max(1) {
+            let tx = tx.clone();
+            let (next, stop, tablebase, date) = (&next, &stop, &tablebase, &date);
+
+            scope.spawn(move || {
+                let mut contestants: [Option<Box<dyn Contestant>>; 2] = [None, None];
+
+                loop {
+                    let round = next.fetch_add(1, Ordering::Relaxed);
+                    if round >= config.games || stop.load(Ordering::Relaxed) {
+                        return;
+                    }
+
+                    for (slot, spec) in contestants.iter_mut().zip(&config.engines) {
+                        if slot.is_none() {
+                            match spec.start() {
+                                Ok(contestant) => *slot = Some(contestant),
+                                Err(err) => {
+                                    let _ = tx.send(Err(err));
+                                    return;
+                                }
+                            }
+                        }
+                    }
+
+                    let first_white = round % 2 == 0;
+                    let [first, second] = &mut contestants;
+                    let (first, second) = (first.as_deref_mut().unwrap(), second.as_deref_mut().unwrap());
+                    let players: [&mut dyn Contestant; 2] = if first_white { [first, second] } else { [second, first] };
+                    let opening = &openings[round / 2 % openings.len()];
+
+                    let mut played = play_game(players, opening, &config.time_control, &config.adjudication, tablebase);
+
+                    let names = [config.engines[0].name(), config.engines[1].name()];
+                    let (white, black) = if first_white { (names[0], names[1]) } else { (names[1], names[0]) };
+                    let tags = [
+                        ("Event", String::from("Engine match")),
+                        ("Site", String::from("?")),
+                        ("Date", date.clone()),
+                        ("Round", (round + 1).to_string()),
+                        ("White", white.to_string()),
+                        ("Black", black.to_string()),
+                        ("TimeControl", config.time_control.to_string()),
+                    ];
+                    for (name, value) in tags {
+                        played.game.set_tag(name, value);
+                    }
+
+                    if tx.send(Ok(GameRecord { round, first_white, played })).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+
+        drop(tx);
+
+        for message in rx {
+            match message {
+                Ok(record) => {
+                    tally.add(&record);
+                    on_game(&record, &tally);
+
+                    if config.sprt.is_some_and(|sprt| sprt.status(&tally) != SprtStatus::Continue) {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                }
+                Err(err) => {
+                    stop.store(true, Ordering::Relaxed);
+                    failure.get_or_insert(err);
+                }
+            }
+        }
+    });
+
+    match failure {
+        Some(err) => Err(err),
+        None => Ok(tally),
+    }
+}
+
+/// Today's date in PGN form, `YYYY.MM.DD`.
+fn today() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() / 86_400) as i64;
+
+    // Civil date from days since 1970-01-01, after Howard Hinnant's algorithm.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    format!("{year:04}.{month:02}.{day:02}")
+}
+
+/// Openings from an EPD or PGN file, PGN games cut to their first `plies` moves.
+pub fn load_openings(path: &std::path::Path, plies: usize) -> Result<Vec<Opening>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let err = |e: ParseError| format!("{}: {e}", path.display());
+
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("epd")) {
+        let positions = crate::pgn::read_epd(&text).map_err(err)?;
+        return Ok(positions.into_iter().map(|start| Opening { start, moves: Vec::new() }).collect());
+    }
+
+    let games = crate::pgn::read(&text).map_err(err)?;
+    Ok(games
+        .into_iter()
+        .map(|game| Opening { moves: game.moves.into_iter().take(plies).collect(), start: game.start })
+        .collect())
+}
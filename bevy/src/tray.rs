@@ -0,0 +1,109 @@
+use bevy::prelude::*;
+
+use crate::analysis::{setup_panel, text_style, PanelColumn};
+use crate::board::BoardView;
+use crate::game::Game;
+use crate::piece::{Kind, Piece, PieceColor};
+use crate::search;
+use crate::theme::Appearance;
+
+const ICON_SIZE: f32 = 20.;
+/// How far a captured piece slides under the previous one of the same kind.
+const ICON_OVERLAP: f32 = 10.;
+
+pub struct TrayPlugin;
+
+impl Plugin for TrayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, setup_trays.after(setup_panel))
+            .add_systems(Update, update_trays);
+    }
+}
+
+/// Holds one row per side, the side at the top of the board first.
+#[derive(Component)]
+struct Trays;
+
+/// Material on the board for `color`, in pawns.
+pub fn material(game: &Game, color: PieceColor) -> i32 {
+    game.position.pieces().filter(|&(_, team, _)| team == color).map(|(_, _, kind)| search::value(kind) / 100).sum()
+}
+
+fn setup_trays(mut commands: Commands, column: Query<Entity, With<PanelColumn>>) {
+    let trays = commands
+        .spawn((
+            NodeBundle {
+                style: Style { flex_direction: FlexDirection::Column, row_gap: Val::Px(4.), ..default() },
+                ..default()
+            },
+            Trays,
+        ))
+        .id();
+
+    commands.entity(column.single()).insert_children(0, &[trays]);
+}
+
+fn update_trays(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    game: Res<Game>,
+    view: Res<BoardView>,
+    appearance: Res<Appearance>,
+    trays: Query<Entity, With<Trays>>,
+) {
+    if !(game.is_changed() || view.is_changed() || appearance.is_changed()) {
+        return;
+    }
+
+    let trays = trays.single();
+    commands.entity(trays).despawn_descendants();
+
+    let mut captures = game.captures();
+    captures.sort_by_key(|&(_, kind)| kind as u8);
+
+    let lead = material(&game, PieceColor::White) - material(&game, PieceColor::Black);
+    let sides = if view.flipped { [PieceColor::White, PieceColor::Black] } else { [PieceColor::Black, PieceColor::White] };
+
+    commands.entity(trays).with_children(|parent| {
+        for side in sides {
+            parent
+                .spawn(NodeBundle {
+                    style: Style { height: Val::Px(ICON_SIZE), align_items: AlignItems::Center, ..default() },
+                    ..default()
+                })
+                .with_children(|row| {
+                    let mut last: Option<Kind> = None;
+
+                    for &(team, kind) in captures.iter().filter(|(team, _)| *team != side) {
+                        let gap = match last {
+                            None => 0.,
+                            Some(k) if k == kind => -ICON_OVERLAP,
+                            Some(_) => 4.,
+                        };
+                        last = Some(kind);
+
+                        row.spawn(ImageBundle {
+                            style: Style {
+                                width: Val::Px(ICON_SIZE),
+                                height: Val::Px(ICON_SIZE),
+                                margin: UiRect::left(Val::Px(gap)),
+                                ..default()
+                            },
+                            image: asset_server.load(Piece { kind, team, loc: (0, 0) }.get_asset_path(appearance.pieces())).into(),
+                            ..default()
+                        });
+                    }
+
+                    let ahead = if side == PieceColor::White { lead } else { -lead };
+
+                    if ahead > 0 {
+                        row.spawn(
+                            TextBundle::from_section(format!("+{ahead}"), text_style(&asset_server, 16.))
+                                .with_style(Style { margin: UiRect::left(Val::Px(6.)), ..default() }),
+                        );
+                    }
+                });
+        }
+    });
+}
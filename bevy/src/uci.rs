@@ -0,0 +1,596 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::piece::PieceColor;
+use crate::rules::{Move, Position, START_FEN};
+use crate::search::TimeBudget;
+
+#[derive(Clone, Debug)]
+pub struct EngineConfig {
+    pub path: PathBuf,
+    pub args: Vec<String>,
+    /// Forwarded as `setoption` once the engine has listed its options.
+    pub options: Vec<(String, String)>,
+    /// How long the engine may take to answer `uci`/`isready` or to return a move after `stop`.
+    pub timeout: Duration,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Score {
+    Cp(i32),
+    Mate(i32),
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Bound {
+    #[default]
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Info {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub multipv: Option<u32>,
+    pub score: Option<Score>,
+    pub bound: Bound,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub time: Option<u64>,
+    pub pv: Vec<Move>,
+    pub string: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OptionSpec {
+    pub name: String,
+    pub kind: String,
+    pub default: Option<String>,
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    pub vars: Vec<String>,
+}
+
+/// A line sent by the engine.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Message {
+    Id { key: String, value: String },
+    UciOk,
+    ReadyOk,
+    Option(OptionSpec),
+    Info(Info),
+    BestMove { best: Option<Move>, ponder: Option<Move> },
+    Other(String),
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Go {
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub movestogo: Option<u32>,
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<u64>,
+    pub infinite: bool,
+    pub ponder: bool,
+}
+
+/// Where the engine is in the protocol; `Dead` once it crashed, timed out or quit.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum State {
+    Handshake,
+    Syncing,
+    Idle,
+    Searching,
+    /// Searching the expected reply on the opponent's time, until `ponderhit` or `stop`.
+    Pondering,
+    Stopping,
+    Dead,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Event {
+    Ready,
+    Info(Info),
+    BestMove { best: Option<Move>, ponder: Option<Move> },
+    UnknownOption(String),
+    Crashed(String),
+    TimedOut(State),
+}
+
+pub struct UciEngine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    events: VecDeque<Event>,
+    state: State,
+    deadline: Option<Instant>,
+    /// Budget of a pondering search, which starts counting at `ponderhit`.
+    ponder_budget: Option<Duration>,
+    config: EngineConfig,
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub options: Vec<OptionSpec>,
+}
+
+impl EngineConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            args: Vec::new(),
+            options: Vec::new(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl Go {
+    pub fn movetime(time: Duration) -> Self {
+        Self { movetime: Some(time.as_millis() as u64), ..Self::default() }
+    }
+
+    pub fn infinite() -> Self {
+        Self { infinite: true, ..Self::default() }
+    }
+
+    pub fn command(&self) -> String {
+        let mut cmd = String::from("go");
+
+        if self.ponder {
+            cmd.push_str(" ponder");
+        }
+
+        for (key, value) in [
+            ("wtime", self.wtime),
+            ("btime", self.btime),
+            ("winc", self.winc),
+            ("binc", self.binc),
+            ("movestogo", self.movestogo.map(u64::from)),
+            ("depth", self.depth.map(u64::from)),
+            ("nodes", self.nodes),
+            ("movetime", self.movetime),
+        ] {
+            if let Some(value) = value {
+                cmd.push_str(&format!(" {key} {value}"));
+            }
+        }
+
+        if self.infinite {
+            cmd.push_str(" infinite");
+        }
+
+        cmd
+    }
+
+    /// Upper bound on how long a search may legitimately run, `None` if it only ends on `stop`.
+    fn budget(&self) -> Option<Duration> {
+        if self.infinite || self.ponder {
+            return None;
+        }
+
+        self.movetime.or(self.wtime.max(self.btime)).map(Duration::from_millis)
+    }
+
+    /// Share of `turn`'s clock the built-in search spends on this move, `None` without a clock.
+    pub fn time_budget(&self, turn: PieceColor) -> Option<TimeBudget> {
+        let (time, inc) = match turn {
+            PieceColor::White => (self.wtime?, self.winc),
+            PieceColor::Black => (self.btime?, self.binc),
+        };
+
+        Some(TimeBudget::new(
+            Duration::from_millis(time),
+            Duration::from_millis(inc.unwrap_or(0)),
+            self.movestogo,
+        ))
+    }
+}
+
+pub fn position_command(start: &Position, moves: &[Move]) -> String {
+    let fen = start.fen();
+    let mut cmd = if fen == START_FEN { String::from("position startpos") } else { format!("position fen {fen}") };
+
+    if !moves.is_empty() {
+        cmd.push_str(" moves");
+        for mv in moves {
+            cmd.push_str(&format!(" {mv}"));
+        }
+    }
+
+    cmd
+}
+
+pub fn parse_line(line: &str) -> Message {
+    let mut tokens = line.split_whitespace();
+
+    match tokens.next() {
+        Some("id") => {
+            let key = tokens.next().unwrap_or_default().to_string();
+            Message::Id { key, value: tokens.collect::<Vec<_>>().join(" ") }
+        }
+        Some("uciok") => Message::UciOk,
+        Some("readyok") => Message::ReadyOk,
+        Some("option") => Message::Option(parse_option(tokens)),
+        Some("info") => Message::Info(parse_info(tokens)),
+        Some("bestmove") => Message::BestMove {
+            best: tokens.next().and_then(|t| t.parse().ok()),
+            ponder: tokens.skip_while(|&t| t != "ponder").nth(1).and_then(|t| t.parse().ok()),
+        },
+        _ => Message::Other(line.to_string()),
+    }
+}
+
+fn parse_option<'a>(tokens: impl Iterator<Item = &'a str>) -> OptionSpec {
+    const KEYS: [&str; 6] = ["name", "type", "default", "min", "max", "var"];
+    let mut spec = OptionSpec::default();
+    let mut key = "";
+    let mut value: Vec<&str> = Vec::new();
+
+    let mut flush = |key: &str, value: &mut Vec<&str>| {
+        let text = value.join(" ");
+        match key {
+            "name" => spec.name = text,
+            "type" => spec.kind = text,
+            "default" => spec.default = Some(text).filter(|t| t != "<empty>"),
+            "min" => spec.min = text.parse().ok(),
+            "max" => spec.max = text.parse().ok(),
+            "var" => spec.vars.push(text),
+            _ => {}
+        }
+        value.clear();
+    };
+
+    for token in tokens {
+        if KEYS.contains(&token) && !(key == "name" && token != "type") {
+            flush(key, &mut value);
+            key = token;
+        } else {
+            value.push(token);
+        }
+    }
+    flush(key, &mut value);
+
+    spec
+}
+
+fn parse_info<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Info {
+    let mut info = Info::default();
+    let mut pending: Option<&str> = None;
+
+    while let Some(key) = pending.take().or_else(|| tokens.next()) {
+        let mut number = || tokens.next().and_then(|t| t.parse::<u64>().ok());
+
+        match key {
+            "depth" => info.depth = number().map(|n| n as u32),
+            "seldepth" => info.seldepth = number().map(|n| n as u32),
+            "multipv" => info.multipv = number().map(|n| n as u32),
+            "nodes" => info.nodes = number(),
+            "nps" => info.nps = number(),
+            "time" => info.time = number(),
+            "score" => {
+                let kind = tokens.next();
+                let value = tokens.next().and_then(|t| t.parse::<i32>().ok());
+
+                info.score = match (kind, value) {
+                    (Some("cp"), Some(v)) => Some(Score::Cp(v)),
+                    (Some("mate"), Some(v)) => Some(Score::Mate(v)),
+                    _ => None,
+                };
+
+                match tokens.next() {
+                    Some("lowerbound") => info.bound = Bound::Lower,
+                    Some("upperbound") => info.bound = Bound::Upper,
+                    other => pending = other,
+                }
+            }
+            "pv" => {
+                for token in tokens.by_ref() {
+                    match token.parse() {
+                        Ok(mv) => info.pv.push(mv),
+                        Err(_) => {
+                            pending = Some(token);
+                            break;
+                        }
+                    }
+                }
+            }
+            "string" => {
+                info.string = Some(tokens.by_ref().collect::<Vec<_>>().join(" "));
+            }
+            "currmove" | "currmovenumber" | "hashfull" | "tbhits" | "cpuload" | "sbhits" => {
+                tokens.next();
+            }
+            _ => {}
+        }
+    }
+
+    info
+}
+
+impl UciEngine {
+    /// Starts the engine and sends `uci`; it becomes usable once [`Event::Ready`] is reported.
+    pub fn spawn(config: EngineConfig) -> io::Result<Self> {
+        let mut child = Command::new(&config.path)
+            .args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (tx, lines) = mpsc::channel();
+
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break; };
+
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = Self {
+            child,
+            stdin,
+            lines,
+            events: VecDeque::new(),
+            state: State::Handshake,
+            deadline: None,
+            ponder_budget: None,
+            config,
+            name: None,
+            author: None,
+            options: Vec::new(),
+        };
+
+        engine.send("uci");
+        engine.arm();
+
+        Ok(engine)
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.state == State::Idle
+    }
+
+    /// Drains everything the engine said since the last call without blocking.
+    pub fn poll(&mut self) -> Vec<Event> {
+        loop {
+            match self.lines.try_recv() {
+                Ok(line) => self.handle(&line),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.exited();
+                    break;
+                }
+            }
+        }
+
+        self.check_deadline();
+        self.events.drain(..).collect()
+    }
+
+    /// Waits up to `timeout` for the next event, enforcing deadlines meanwhile.
+    pub fn next_event(&mut self, timeout: Duration) -> Option<Event> {
+        let until = Instant::now() + timeout;
+
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Some(event);
+            }
+
+            let now = Instant::now();
+            if now >= until || self.state == State::Dead {
+                return None;
+            }
+
+            let wake = self.deadline.map_or(until, |d| d.min(until));
+            match self.lines.recv_timeout(wake.saturating_duration_since(now)) {
+                Ok(line) => self.handle(&line),
+                Err(RecvTimeoutError::Timeout) => self.check_deadline(),
+                Err(RecvTimeoutError::Disconnected) => self.exited(),
+            }
+        }
+    }
+
+    /// Tells the engine the next search belongs to a new game; it is ready again after [`Event::Ready`].
+    pub fn new_game(&mut self) {
+        if self.state != State::Idle {
+            return;
+        }
+
+        self.send("ucinewgame");
+        self.sync();
+    }
+
+    pub fn go(&mut self, start: &Position, moves: &[Move], go: &Go) {
+        if self.state != State::Idle {
+            return;
+        }
+
+        self.send(&position_command(start, moves));
+        self.send(&go.command());
+
+        if self.state == State::Dead {
+            return;
+        }
+
+        if go.ponder {
+            self.state = State::Pondering;
+            self.ponder_budget = Go { ponder: false, ..go.clone() }.budget();
+        } else {
+            self.state = State::Searching;
+            self.deadline = go.budget().map(|b| Instant::now() + b + self.config.timeout);
+        }
+    }
+
+    /// The opponent played the expected move: the pondering search goes on as a normal one.
+    pub fn ponderhit(&mut self) {
+        if self.state != State::Pondering {
+            return;
+        }
+
+        self.send("ponderhit");
+
+        if self.state != State::Dead {
+            self.state = State::Searching;
+            self.deadline = self.ponder_budget.take().map(|b| Instant::now() + b + self.config.timeout);
+        }
+    }
+
+    /// Ends a search or a ponder; a pondering engine's move answers a position that never came.
+    pub fn stop(&mut self) {
+        if !matches!(self.state, State::Searching | State::Pondering) {
+            return;
+        }
+
+        self.send("stop");
+
+        if self.state != State::Dead {
+            self.state = State::Stopping;
+            self.arm();
+        }
+    }
+
+    pub fn quit(&mut self) {
+        if self.state == State::Dead {
+            return;
+        }
+
+        self.send("quit");
+        self.state = State::Dead;
+
+        let until = Instant::now() + self.config.timeout;
+        while Instant::now() < until {
+            if !matches!(self.child.try_wait(), Ok(None)) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+
+    fn send(&mut self, cmd: &str) {
+        if self.state == State::Dead {
+            return;
+        }
+
+        if let Err(err) = writeln!(self.stdin, "{cmd}").and_then(|_| self.stdin.flush()) {
+            self.die(Event::Crashed(format!("failed to write to engine: {err}")));
+        }
+    }
+
+    fn sync(&mut self) {
+        self.send("isready");
+
+        if self.state != State::Dead {
+            self.state = State::Syncing;
+            self.arm();
+        }
+    }
+
+    fn arm(&mut self) {
+        self.deadline = Some(Instant::now() + self.config.timeout);
+    }
+
+    fn handle(&mut self, line: &str) {
+        match parse_line(line) {
+            Message::Id { key, value } => match key.as_str() {
+                "name" => self.name = Some(value),
+                "author" => self.author = Some(value),
+                _ => {}
+            },
+            Message::Option(spec) if self.state == State::Handshake => self.options.push(spec),
+            Message::UciOk if self.state == State::Handshake => {
+                self.forward_options();
+                self.sync();
+            }
+            Message::ReadyOk if self.state == State::Syncing => {
+                self.state = State::Idle;
+                self.deadline = None;
+                self.events.push_back(Event::Ready);
+            }
+            Message::Info(info) => self.events.push_back(Event::Info(info)),
+            Message::BestMove { best, ponder } if matches!(self.state, State::Searching | State::Pondering | State::Stopping) => {
+                self.state = State::Idle;
+                self.deadline = None;
+                self.ponder_budget = None;
+                self.events.push_back(Event::BestMove { best, ponder });
+            }
+            _ => {}
+        }
+    }
+
+    fn forward_options(&mut self) {
+        for (name, value) in self.config.options.clone() {
+            let Some(spec) = self.options.iter().find(|o| o.name.eq_ignore_ascii_case(&name)) else {
+                self.events.push_back(Event::UnknownOption(name));
+                continue;
+            };
+
+            let cmd = if spec.kind == "button" {
+                format!("setoption name {}", spec.name)
+            } else {
+                format!("setoption name {} value {value}", spec.name)
+            };
+
+            self.send(&cmd);
+        }
+    }
+
+    fn check_deadline(&mut self) {
+        if self.deadline.is_none_or(|d| Instant::now() < d) {
+            return;
+        }
+
+        match self.state {
+            // Out of budget: ask for the move, the engine then gets the usual grace period.
+            State::Searching => self.stop(),
+            State::Dead | State::Idle | State::Pondering => self.deadline = None,
+            state => self.die(Event::TimedOut(state)),
+        }
+    }
+
+    fn exited(&mut self) {
+        if self.state == State::Dead {
+            return;
+        }
+
+        let status = self.child.wait().map_or_else(|e| e.to_string(), |s| s.to_string());
+        self.state = State::Dead;
+        self.deadline = None;
+        self.events.push_back(Event::Crashed(format!("engine exited ({status})")));
+    }
+
+    fn die(&mut self, event: Event) {
+        self.state = State::Dead;
+        self.deadline = None;
+        self.events.push_back(event);
+
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        self.quit();
+    }
+}